@@ -24,6 +24,7 @@ fn main() {
     let fps_upper_limit :u64 = 30;
 
     let mut engine = AsciaEngine::<PresetAsciaEnvironment>::new(width, height);
+    let root = engine.genesis_local.root();
 
     let cameras = available_preset_cameras();
     let mut now_camera_index = 0usize;
@@ -36,7 +37,7 @@ fn main() {
     }, -PI * 0.5, 1.0);
     cam_objn.attribute = cameras[now_camera_index].clone();
 
-    engine.genesis_local.add_child(cam_objn);
+    let camera_id = engine.genesis_local.add_child(root, cam_objn).unwrap();
 
     let mut null_container = ObjectNode::new("null container");
     null_container.position = Vec3{
@@ -54,8 +55,8 @@ fn main() {
         priority: 0,
     })));
 
-    null_container.add_child(cube);
-    engine.genesis_local.add_child(null_container);
+    let null_container = engine.genesis_local.add_child(root, null_container).unwrap();
+    engine.genesis_local.add_child(null_container, cube).unwrap();
 
     let mut light_objn = ObjectNode::new("light");
     light_objn.position.y = 100.0;
@@ -68,12 +69,12 @@ fn main() {
         }.into(),
         power: 1.0,
     }).make_shared();
-    engine.genesis_local.add_child(light_objn);
+    engine.genesis_local.add_child(root, light_objn).unwrap();
 
     let capture_flag: Arc<Mutex<bool>> = Arc::new(Mutex::new(false));
 
     let mut termios_controller = TermiosController::generate(|c, e|{
-        if let Some(camera) = e.genesis_local.child_mut("camera"){
+        if let Some(camera) = e.genesis_local.node_mut(camera_id){
             match c {
                 b'w' => move_camera(3.0 ,camera, &Vec3{ x: 1.0, y: 0.0, z:0.0 }),
                 b's' => move_camera(3.0, camera, &Vec3{ x: -1.0, y: 0.0, z:0.0 }),
@@ -106,7 +107,7 @@ fn main() {
         engine.update_global_nodes();
 
         
-        if let Ok(data) = engine.render(engine.genesis_global.child("camera").unwrap()){
+        if let Ok(data) = engine.render(engine.genesis_global.node(camera_id).unwrap()){
             let mut mg = capture_flag.lock().unwrap();
             #[cfg(feature = "export")]
             if *mg{
@@ -123,7 +124,7 @@ fn main() {
         println!("dur:{}      ",dur.as_millis());
         println!("fps:{}      ",1000 / dur.as_millis());
         println!("press [W][A][S][D] to move horizontally, [G][H] to move vertically, [I][J][K][L] to roll, [V] to change camera");
-        println!("current camera: {}     ", preset_camera_info(&engine.genesis_global.child("camera").unwrap().attribute));
+        println!("current camera: {}     ", preset_camera_info(&engine.genesis_global.node(camera_id).unwrap().attribute));
 
         last_time = Instant::now();
     }
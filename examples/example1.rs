@@ -20,6 +20,7 @@ fn main() {
     let fps_upper_limit :u64 = 30;
 
     let mut engine = AsciaEngine::<PresetAsciaEnvironment>::new(width, height);
+    let root = engine.genesis_local.root();
 
     let cameras = available_preset_cameras();
     let mut now_camera_index = 0usize;
@@ -32,7 +33,7 @@ fn main() {
         z: 0.0,
     }, -PI * 0.5, 1.0);
     
-    engine.genesis_local.add_child(cam_objn);
+    let camera_id = engine.genesis_local.add_child(root, cam_objn).unwrap();
     
     let mut container = ObjectNode::new("container");
     container.position = Vec3{
@@ -40,6 +41,7 @@ fn main() {
         y:0.0,
         z:25.0
     };
+    let container_id = engine.genesis_local.add_child(root, container).unwrap();
     
     let mut cube_1 = ObjectNode::from("cube 1", PrimitiveGenerator::cube(6.0, PresetPolygonMaterial::LambertMaterial(LambertMaterial{
         color: ColorRGBf32{
@@ -55,7 +57,7 @@ fn main() {
         z:0.0
     };
     
-    container.add_child(cube_1);
+    engine.genesis_local.add_child(container_id, cube_1).unwrap();
 
     let mut cube_2 = ObjectNode::from("cube 2", PrimitiveGenerator::cube(6.0, PresetPolygonMaterial::LambertMaterial(LambertMaterial{
         color: ColorRGBf32{
@@ -70,7 +72,7 @@ fn main() {
         y:0.0,
         z:0.0
     };
-    container.add_child(cube_2);
+    engine.genesis_local.add_child(container_id, cube_2).unwrap();
     
     let mut cube_3 = ObjectNode::from("cube 3", PrimitiveGenerator::cube(6.0, PresetPolygonMaterial::LambertMaterial(LambertMaterial{
         color: ColorRGBf32{
@@ -86,9 +88,9 @@ fn main() {
         y:0.0,
         z:14.0
     };
-    container.add_child(cube_3);
+    engine.genesis_local.add_child(container_id, cube_3).unwrap();
 
-    let mut cube_4 = ObjectNode::from("cube 3", PrimitiveGenerator::cube(6.0, PresetPolygonMaterial::LambertMaterial(LambertMaterial{
+    let mut cube_4 = ObjectNode::from("cube 4", PrimitiveGenerator::cube(6.0, PresetPolygonMaterial::LambertMaterial(LambertMaterial{
         color: ColorRGBf32{
             r: 1.0,
             g: 1.0,
@@ -102,7 +104,7 @@ fn main() {
         y:0.0,
         z:20.0
     };
-    container.add_child(cube_4);
+    engine.genesis_local.add_child(container_id, cube_4).unwrap();
     
     let red_square = ObjectNode::from("red square", PrimitiveGenerator::square(20.0, PresetPolygonMaterial::LambertWithShadowMaterial(
         LambertWithShadowMaterial{
@@ -115,7 +117,7 @@ fn main() {
         }
     )));
     
-    container.add_child(red_square);
+    engine.genesis_local.add_child(container_id, red_square).unwrap();
 
     let mut green_square = ObjectNode::from("green square", PrimitiveGenerator::square(20.0, PresetPolygonMaterial::FlatMaterial(
         FlatMaterial{
@@ -134,7 +136,7 @@ fn main() {
         y:1.0,
         z:0.0
     },-PI * 0.5, 1.0);
-    container.add_child(green_square);
+    engine.genesis_local.add_child(container_id, green_square).unwrap();
 
     let blue_square = ObjectNode::from("blue square", PrimitiveGenerator::square(20.0, PresetPolygonMaterial::LambertWithShadowMaterial(
         LambertWithShadowMaterial{
@@ -146,7 +148,7 @@ fn main() {
             priority: 0,
         }
     )));
-    container.add_child(blue_square);
+    engine.genesis_local.add_child(container_id, blue_square).unwrap();
     
     for i in 0..64{
         let mut tiny_cube = ObjectNode::from(&format!("tiny cube {}", i), PrimitiveGenerator::cube(10.0, PresetPolygonMaterial::LambertWithShadowMaterial(
@@ -172,7 +174,7 @@ fn main() {
             y:i as f32 * 10.0,
             z:0.0
         });
-        container.add_child(tiny_cube);
+        engine.genesis_local.add_child(container_id, tiny_cube).unwrap();
     }
     
     let pointlight = PresetLight::PointLight(PointLight{
@@ -189,12 +191,11 @@ fn main() {
     light.position.x = 30.0;
     light.position.y = 30.0;
     light.position.z = -30.0;
-    engine.genesis_local.add_child(light);
+    engine.genesis_local.add_child(root, light).unwrap();
     
-    engine.genesis_local.add_child(container);
 
     let mut termios_controller = TermiosController::generate(|c, e|{
-        if let Some(camera) = e.genesis_local.child_mut("camera"){
+        if let Some(camera) = e.genesis_local.node_mut(camera_id){
             match c {
                 b'w' => move_camera(3.0, camera, &Vec3{ x: 1.0, y: 0.0, z:0.0 }),
                 b's' => move_camera(3.0, camera, &Vec3{ x: -1.0, y: 0.0, z:0.0 }),
@@ -222,7 +223,7 @@ fn main() {
         let d = engine.engine_time();
 
         {
-            let container = engine.genesis_local.child_mut("container").unwrap();
+            let container = engine.genesis_local.node_mut(container_id).unwrap();
             container.direction = Quaternion::new(&Vec3{
                 x:1.0,
                 y:0.0,
@@ -234,7 +235,7 @@ fn main() {
             },(d.as_millis() as f32) / 5000.0 * 2.0 * PI, 1.0);
         }
         {
-            let cube_1 = engine.genesis_local.child_mut("container").unwrap().child_mut("cube 1").unwrap();
+            let cube_1 = engine.genesis_local.child_by_path_mut("container/cube 1").unwrap();
             cube_1.position.y = 8.0 * f32::sin((d.as_millis() as f32) / 2000.0 * 2.0 * PI);
             cube_1.direction = Quaternion::new(&Vec3{
                 x:1.0,
@@ -243,7 +244,7 @@ fn main() {
             }.normalize(),(d.as_millis() as f32) / 2000.0 * 2.0 * PI, 1.0);
         }
         {
-            let cube_2 = engine.genesis_local.child_mut("container").unwrap().child_mut("cube 1").unwrap();
+            let cube_2 = engine.genesis_local.child_by_path_mut("container/cube 1").unwrap();
             cube_2.position.y = 3.0 * f32::cos((d.as_millis() as f32) / 3000.0 * 2.0 * PI);
             cube_2.direction = Quaternion::new(&Vec3{
                 x:1.0,
@@ -252,7 +253,7 @@ fn main() {
             }.normalize(),(d.as_millis() as f32) / 3000.0 * 2.0 * PI, 1.0);
         }
         {
-            let blue_square = engine.genesis_local.child_mut("container").unwrap().child_mut("blue square").unwrap();
+            let blue_square = engine.genesis_local.child_by_path_mut("container/blue square").unwrap();
             blue_square.direction = Quaternion::new(&Vec3{
                 x: 0.0,
                 y: 1.0,
@@ -262,7 +263,7 @@ fn main() {
         
         termios_controller.input(&mut engine).expect("something went wrong with processing input from keyboard");
         engine.update_global_nodes();
-        engine.render(engine.genesis_global.child("camera").unwrap()).expect("failed rendering");
+        engine.render(engine.genesis_global.node(camera_id).unwrap()).expect("failed rendering");
 
         if (last_time.elapsed().as_millis() as u64) < (1000 / fps_upper_limit){
             thread::sleep(Duration::from_millis(1000 / fps_upper_limit - last_time.elapsed().as_millis() as u64));
@@ -273,7 +274,7 @@ fn main() {
         println!("dur:{}      ",dur.as_millis());
         println!("fps:{}      ",1000 / dur.as_millis());
         println!("press [W][A][S][D] to move horizontally, [G][H] to move vertically, [I][J][K][L] to roll, [V] to change camera");
-        println!("current camera: {}     ", preset_camera_info(&engine.genesis_global.child("camera").unwrap().attribute));
+        println!("current camera: {}     ", preset_camera_info(&engine.genesis_global.node(camera_id).unwrap().attribute));
         
         last_time = Instant::now();
     }
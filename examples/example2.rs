@@ -7,21 +7,21 @@ use std::str::FromStr;
 use std::time::{Duration, Instant};
 use ascia::ascia::camera::{SimpleCamera};
 use ascia::ascia::color::ColorRGBf32;
-use ascia::ascia::core::{LambertMaterial, Local, ObjectNode, ObjectNodeAttributeDispatcher, PresetAsciaEnvironment, PresetPolygonMaterial, PresetObjectNodeAttributeDispatcher, AsciaEngine};
+use ascia::ascia::core::{LambertMaterial, Local, NodeId, ObjectNode, ObjectNodeAttributeDispatcher, ObjectNodeTree, PresetAsciaEnvironment, PresetPolygonMaterial, PresetObjectNodeAttributeDispatcher, AsciaEngine};
 use ascia::ascia::lights::{PointLight};
 use ascia::ascia::math::{Quaternion, Vec3};
 use ascia::ascia::primitives::PrimitiveGenerator;
 use ascia::ascia::util::preset_camera_info;
 
 struct FlashingFloor{
-    label: String,
+    id: NodeId,
     animation_start:Duration,
     animation_duration:Duration,
 }
 
 struct RollingCube{
     size: f32,
-    label: String,
+    id: NodeId,
     animation_start:Duration,
     animation_duration:Duration,
     current_grid: (i64, i64),
@@ -29,7 +29,7 @@ struct RollingCube{
 }
 
 impl RollingCube {
-    fn new(size:f32, label: &str, animation_start: Duration, animation_duration: Duration) -> RollingCube{
+    fn new(size:f32, id: NodeId, animation_start: Duration, animation_duration: Duration) -> RollingCube{
         let current = ((rand::random::<f64>() * 128.0 - 64.0) as i64,(rand::random::<f64>() * 128.0 - 64.0) as i64);
         let next = if rand::random::<bool>() {
             if rand::random::<bool>() {
@@ -48,7 +48,7 @@ impl RollingCube {
         };
         return RollingCube{
             size,
-            label: label.to_string(),
+            id: id,
             animation_start: animation_start,
             animation_duration: animation_duration,
             current_grid: current,
@@ -56,8 +56,8 @@ impl RollingCube {
         };
     }
 
-    fn update(&mut self, root: &mut ObjectNode<PresetAsciaEnvironment, Local>, engine_time: &Duration){
-        let self_objn = root.child_mut(&self.label).unwrap();
+    fn update(&mut self, root: &mut ObjectNodeTree<PresetAsciaEnvironment, Local>, engine_time: &Duration){
+        let self_objn = root.node_mut(self.id).unwrap();
         if *engine_time > self.animation_start + self.animation_duration{
             self.decide_next();
         }
@@ -124,15 +124,15 @@ impl RollingCube {
 }
 
 impl FlashingFloor {
-    fn new(label: &str, animation_start: Duration, animation_duration: Duration) -> FlashingFloor{
+    fn new(id: NodeId, animation_start: Duration, animation_duration: Duration) -> FlashingFloor{
         return FlashingFloor{
-            label: label.to_string(),
+            id: id,
             animation_start: animation_start,
             animation_duration: animation_duration,
         };
     }
-    fn update(&mut self, root: &mut ObjectNode<PresetAsciaEnvironment, Local>, engine_time: &Duration) {
-        let self_objn = root.child_mut(&self.label).unwrap();
+    fn update(&mut self, root: &mut ObjectNodeTree<PresetAsciaEnvironment, Local>, engine_time: &Duration) {
+        let self_objn = root.node_mut(self.id).unwrap();
         let s = if *engine_time <= self.animation_start{
             1.0
         } else if *engine_time <= self.animation_start + self.animation_duration{
@@ -163,20 +163,24 @@ fn main() {
     let fps_upper_limit :u64 = 30;
 
     let mut engine = AsciaEngine::new(width, height);
+    let root = engine.genesis_local.root();
 
     let cube_size = 10.0;
 
     let mut cubes = vec![];
     for i in 0..32{
         let label = format!("cube {}", i);
-        engine.genesis_local.add_child(ObjectNode::from(&label, PrimitiveGenerator::cube(cube_size, PresetPolygonMaterial::LambertMaterial(LambertMaterial::default()))));
-        cubes.push(RollingCube::new(cube_size, &label, engine.engine_time(), Duration::new(0, 500000000)));
+        let id = engine.genesis_local.add_child(root, ObjectNode::from(&label, PrimitiveGenerator::cube(cube_size, PresetPolygonMaterial::LambertMaterial(LambertMaterial::default())))).unwrap();
+        cubes.push(RollingCube::new(cube_size, id, engine.engine_time(), Duration::new(0, 500000000)));
     }
 
 
     let mut floors = VecDeque::new();
     for i in 0..32{
         let label = format!("floor {} {}", cubes[i].current_grid.0, cubes[i].current_grid.1);
+        if engine.genesis_local.child(root, &label).is_some(){
+            continue;
+        }
         let mut f = ObjectNode::from(&label, PrimitiveGenerator::square(cube_size, PresetPolygonMaterial::LambertMaterial(LambertMaterial::default())));
         f.position = Vec3{
             x: (cubes[i].current_grid.0 as f32 + 0.5) * cube_size,
@@ -188,8 +192,8 @@ fn main() {
             y: 0.0,
             z: 1.0,
         }, PI * 0.5, 1.0);
-        engine.genesis_local.add_child(f);
-        floors.push_back(FlashingFloor::new(&label, engine.engine_time(), Duration::new(5,0)));
+        let id = engine.genesis_local.add_child(root, f).unwrap();
+        floors.push_back(FlashingFloor::new(id, engine.engine_time(), Duration::new(5,0)));
     }
 
 
@@ -208,9 +212,8 @@ fn main() {
 
     cam_objn.attribute = PresetObjectNodeAttributeDispatcher::from(SimpleCamera::default()).make_shared();
 
-    let mut cam_root = ObjectNode::new("camera root");
-    cam_root.add_child(cam_objn);
-    engine.genesis_local.add_child(cam_root);
+    let camera_root_id = engine.genesis_local.add_child(root, ObjectNode::new("camera root")).unwrap();
+    let camera_id = engine.genesis_local.add_child(camera_root_id, cam_objn).unwrap();
 
     let mut light_objn = ObjectNode::new("light");
     light_objn.attribute = PresetObjectNodeAttributeDispatcher::from(PointLight{
@@ -226,7 +229,7 @@ fn main() {
         y: 150.0,
         z: 50.0,
     };
-    engine.genesis_local.add_child(light_objn);
+    engine.genesis_local.add_child(root, light_objn).unwrap();
 
     let mut last_time = Instant::now();
     loop {
@@ -234,7 +237,7 @@ fn main() {
         let engine_time = engine.engine_time();
         for c in &mut cubes{
             let label = format!("floor {} {}", c.current_grid.0, c.current_grid.1);
-            if engine.genesis_local.child(&label).is_none(){
+            if engine.genesis_local.child(root, &label).is_none(){
                 let mut f = ObjectNode::from(&label, PrimitiveGenerator::square(cube_size, PresetPolygonMaterial::LambertMaterial(LambertMaterial::default())));
                 f.position = Vec3{
                     x: (c.current_grid.0 as f32 + 0.5) * cube_size,
@@ -246,8 +249,8 @@ fn main() {
                     y: 0.0,
                     z: 1.0,
                 }, PI * 0.5, 1.0);
-                engine.genesis_local.add_child(f);
-                floors.push_back(FlashingFloor::new(&label, engine.engine_time(), Duration::new(3,0)));
+                let id = engine.genesis_local.add_child(root, f).unwrap();
+                floors.push_back(FlashingFloor::new(id, engine.engine_time(), Duration::new(3,0)));
             }
             c.update(&mut engine.genesis_local, &engine_time);
         }
@@ -255,13 +258,13 @@ fn main() {
         for f in &mut floors{
             f.update(&mut engine.genesis_local, &engine_time);
             if f.animation_start + f.animation_duration < engine_time {
-                engine.genesis_local.remove_child(f.label.clone());
+                engine.genesis_local.remove(f.id).unwrap();
             }
         }
         floors.retain(|f|{ f.animation_start + f.animation_duration >= engine_time });
 
-        let cube_0_pos = engine.genesis_local.child("cube 0").unwrap().position;
-        let camera_root = engine.genesis_local.child_mut("camera root").unwrap();
+        let cube_0_pos = engine.genesis_local.node(cubes[0].id).unwrap().position;
+        let camera_root = engine.genesis_local.node_mut(camera_root_id).unwrap();
         camera_root.position = Vec3{
             x: cube_0_pos.x,
             y: cube_size * 0.5,
@@ -274,7 +277,7 @@ fn main() {
         }, engine_time.as_secs_f32(), 1.0);
 
         engine.update_global_nodes();
-        engine.render(&engine.genesis_global.child("camera root").unwrap().child("camera").unwrap()).expect("failed rendering");
+        engine.render(engine.genesis_global.node(camera_id).unwrap()).expect("failed rendering");
 
         if (last_time.elapsed().as_millis() as u64) < (1000 / fps_upper_limit){
             thread::sleep(Duration::from_millis(1000 / fps_upper_limit - last_time.elapsed().as_millis() as u64));
@@ -282,7 +285,7 @@ fn main() {
 
         let dur = last_time.elapsed();
         println!("fps:{}      ",1000 / dur.as_millis());
-        println!("current camera: {}     ", preset_camera_info(&engine.genesis_global.child("camera root").unwrap().child("camera").unwrap().attribute));
+        println!("current camera: {}     ", preset_camera_info(&engine.genesis_global.node(camera_id).unwrap().attribute));
 
         last_time = Instant::now();
     }
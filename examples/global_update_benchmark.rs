@@ -0,0 +1,74 @@
+extern crate ascia;
+
+use std::env;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use ascia::ascia::core::{Global, Local, ObjectNode, ObjectNodeTree, PresetAsciaEnvironment, PresetPolygonMaterial};
+use ascia::ascia::io::tris::load_tris;
+use ascia::ascia::math::{Quaternion, Vec3};
+use ascia::ascia::primitives::PrimitiveGenerator;
+
+// the teapot scene with a few more static pots, where only a small container rotates and the camera moves every frame
+fn build_scene() -> ObjectNodeTree<PresetAsciaEnvironment, Local>{
+    let polygons = load_tris("./examples/teapot_bezier1.tris.txt", &PresetPolygonMaterial::default()).expect("failed to load model");
+
+    let mut tree = ObjectNodeTree::new(ObjectNode::new("genesis"));
+    let root = tree.root();
+    tree.add_child(root, ObjectNode::new("camera")).unwrap();
+
+    let statics = tree.add_child(root, ObjectNode::new("statics")).unwrap();
+    for i in 0..8{
+        let mut pot = ObjectNode::from(&format!("teapot {}", i), polygons.clone());
+        pot.position = Vec3{
+            x: 100.0 * i as f32,
+            y: 0.0,
+            z: 300.0,
+        };
+        pot.scale = Vec3{
+            x: 10.0,
+            y: 10.0,
+            z: 10.0,
+        };
+        tree.add_child(statics, pot).unwrap();
+    }
+
+    let container = tree.add_child(root, ObjectNode::new("container")).unwrap();
+    tree.add_child(container, ObjectNode::from("cube", PrimitiveGenerator::cube(20.0, PresetPolygonMaterial::default()))).unwrap();
+    return tree;
+}
+
+fn step(root: &mut ObjectNodeTree<PresetAsciaEnvironment, Local>, frame: usize){
+    root.child_by_path_mut("container").unwrap().direction = Quaternion::new(&Vec3{
+        x: 0.0,
+        y: 1.0,
+        z: 0.0,
+    }, frame as f32 * 0.05, 1.0);
+    root.child_by_path_mut("camera").unwrap().position.x = frame as f32;
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let frames: usize = if args.len() > 1 { usize::from_str(&args[1]).unwrap() } else { 100 };
+
+    let mut root = build_scene();
+    let started = Instant::now();
+    let mut global: ObjectNodeTree<PresetAsciaEnvironment, Global> = root.generate_global_nodes();
+    for frame in 0..frames{
+        step(&mut root, frame);
+        global = root.generate_global_nodes();
+    }
+    let full: Duration = started.elapsed();
+
+    let mut root = build_scene();
+    root.update_global_nodes(&mut global);
+    let started = Instant::now();
+    for frame in 0..frames{
+        step(&mut root, frame);
+        root.update_global_nodes(&mut global);
+    }
+    let incremental: Duration = started.elapsed();
+
+    println!("{} frames", frames);
+    println!("generate_global_nodes: {:.3} ms/frame", full.as_secs_f64() * 1000.0 / frames as f64);
+    println!("update_global_nodes:   {:.3} ms/frame", incremental.as_secs_f64() * 1000.0 / frames as f64);
+}
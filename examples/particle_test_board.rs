@@ -20,6 +20,7 @@ fn main() {
     let fps_upper_limit :u64 = 30;
 
     let mut engine = AsciaEngine::<PresetAsciaEnvironment>::new(width, height);
+    let root = engine.genesis_local.root();
 
     let cameras = available_preset_cameras();
     let mut now_camera_index = 0usize;
@@ -32,7 +33,7 @@ fn main() {
     }, -PI * 0.5, 1.0);
     cam_objn.attribute = cameras[now_camera_index].clone();
 
-    engine.genesis_local.add_child(cam_objn);
+    let camera_id = engine.genesis_local.add_child(root, cam_objn).unwrap();
 
     let mut container = ObjectNode::new("container");
     container.position = Vec3{
@@ -68,8 +69,8 @@ fn main() {
         });
     }
 
-    container.add_child(en1);
-    engine.genesis_local.add_child(container);
+    let container_id = engine.genesis_local.add_child(root, container).unwrap();
+    let en1_id = engine.genesis_local.add_child(container_id, en1).unwrap();
 
     let light = PointLight{
         color: ColorRGBu8 {
@@ -84,10 +85,10 @@ fn main() {
     light_objn.position.y = 100.0;
     light_objn.position.z = -100.0;
     light_objn.attribute = PresetObjectNodeAttributeDispatcher::from(light).make_shared();
-    engine.genesis_local.add_child(light_objn);
+    engine.genesis_local.add_child(root, light_objn).unwrap();
 
     let mut termios_controller = TermiosController::generate(|c, e|{
-        if let Some(camera) = e.genesis_local.child_mut("camera"){
+        if let Some(camera) = e.genesis_local.node_mut(camera_id){
             match c {
                 b'w' => move_camera(3.0, camera, &Vec3{ x: 1.0, y: 0.0, z:0.0 }),
                 b's' => move_camera(3.0, camera, &Vec3{ x: -1.0, y: 0.0, z:0.0 }),
@@ -116,7 +117,7 @@ fn main() {
 
         let d = 0.5 + f32::sin(engine_time.as_secs_f32() * 0.3 * PI) * 0.5;
         for i in 0..2048 {
-            engine.genesis_local.child_mut("container").unwrap().child_mut("en1").unwrap().c_particles[i].position = d * Vec3 {
+            engine.genesis_local.node_mut(en1_id).unwrap().c_particles[i].position = d * Vec3 {
                 x: 1.0 * (i % 80) as f32 - 40.0,
                 y: 1.0 * (i / 80) as f32 - 40.0,
                 z: 100.0,
@@ -129,7 +130,7 @@ fn main() {
 
         termios_controller.input(&mut engine).expect("something went wrong with processing input from keyboard");
         engine.update_global_nodes();
-        engine.render(engine.genesis_global.child("camera").unwrap()).expect("failed rendering");
+        engine.render(engine.genesis_global.node(camera_id).unwrap()).expect("failed rendering");
 
         if (last_time.elapsed().as_millis() as u64) < (1000 / fps_upper_limit){
             thread::sleep(Duration::from_millis(1000 / fps_upper_limit - last_time.elapsed().as_millis() as u64));
@@ -140,7 +141,7 @@ fn main() {
         println!("dur:{}      ", dur.as_millis());
         println!("fps:{}      ", 1000 / dur.as_millis());
         println!("press [W][A][S][D] to move horizontally, [G][H] to move vertically, [I][J][K][L] to roll, [V] to change camera");
-        println!("current camera: {}     ", preset_camera_info(&engine.genesis_global.child("camera").unwrap().attribute));
+        println!("current camera: {}     ", preset_camera_info(&engine.genesis_global.node(camera_id).unwrap().attribute));
 
         last_time = Instant::now();
     }
@@ -1,17 +1,20 @@
 extern crate ascia;
 
 use std::f32::consts::PI;
+use std::rc::Rc;
 use std::{env, thread};
-use std::fs::File;
-use std::io::{BufRead, BufReader};
 use std::str::FromStr;
 use std::time::{Duration, Instant};
+use ascia::ascia::animation::{AnimationClip, AnimationMode, AnimationTrack};
+use ascia::ascia::bezier::BezierLod;
 use ascia::ascia::color::{ColorRGBf32, ColorRGBu8};
-use ascia::ascia::core::{LambertWithShadowMaterial, Local, ObjectNode, ObjectNodeAttributeDispatcher, Polygon, PresetAsciaEnvironment, PresetPolygonMaterial, PresetObjectNodeAttributeDispatcher, AsciaEngine};
+use ascia::ascia::core::{LambertWithShadowMaterial, Mesh, MeshInstance, ObjectNode, ObjectNodeAttributeDispatcher, PresetAsciaEnvironment, PresetPolygonMaterial, PresetObjectNodeAttributeDispatcher, AsciaEngine};
 use ascia::ascia::lights::PointLight;
-use ascia::ascia::math::{Matrix33, Quaternion, Vec3};
+use ascia::ascia::io::bpt::load_bpt;
+use ascia::ascia::io::tris::load_tris;
+use ascia::ascia::math::{Quaternion, Vec3};
 use ascia::ascia::primitives::PrimitiveGenerator;
-use ascia::ascia::util::{available_preset_cameras, move_camera, preset_camera_info, rotate_camera, sort_polygons_by_morton_code, TermiosController};
+use ascia::ascia::util::{available_preset_cameras, move_camera, preset_camera_info, rotate_camera, smooth_normals, sort_polygons_by_morton_code, TermiosController};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -22,6 +25,7 @@ fn main() {
     let fps_upper_limit :u64 = 30;
 
     let mut engine = AsciaEngine::<PresetAsciaEnvironment>::new(width, height);
+    let root = engine.genesis_local.root();
 
     let cameras = available_preset_cameras();
     let mut now_camera_index = 1usize;
@@ -35,11 +39,11 @@ fn main() {
 
     cam_objn.attribute = cameras[now_camera_index].clone();
 
-    engine.genesis_local.add_child(cam_objn);
+    let camera_id = engine.genesis_local.add_child(root, cam_objn).unwrap();
 
     let mut cube_objn = ObjectNode::from("cube",PrimitiveGenerator::cube(200.0, PresetPolygonMaterial::LambertMaterial(Default::default())));
     cube_objn.position.z = 400.0;
-    engine.genesis_local.add_child(cube_objn);
+    engine.genesis_local.add_child(root, cube_objn).unwrap();
 
     let mut container = ObjectNode::new("container");
     container.position = Vec3{
@@ -47,18 +51,52 @@ fn main() {
         y:0.0,
         z:100.0
     };
+    let container_id = engine.genesis_local.add_child(root, container).unwrap();
+
+    // the container turns once every 2π seconds, keyed every third of a turn so that each step takes the intended arc
+    let mut spin = AnimationClip::new("spin");
+    let mut track = AnimationTrack::new("container");
+    for i in 0..4{
+        let angle = i as f32 * PI * 2.0 / 3.0;
+        track.add_direction(angle, Quaternion::new(&Vec3{
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        }, angle, 1.0));
+    }
+    spin.tracks.push(track);
+    engine.play_animation(Rc::new(spin), AnimationMode::Loop);
+
+    let material = PresetPolygonMaterial::LambertWithShadowMaterial(
+        LambertWithShadowMaterial{
+            color: ColorRGBf32{
+                r: 1.0,
+                g: 1.0,
+                b: 1.0,
+            },
+            priority: 10,
+        }
+    );
+    // given the patches of a .bpt file the pot is tessellated from them, finer the closer the camera gets
+    let mut lod = args.iter().find(|a| a.ends_with(".bpt")).map(|path| {
+        BezierLod::<PresetAsciaEnvironment>::new(load_bpt(path).expect("failed to load patches"), material.clone(), 200.0, 16, 2)
+    });
+
+    // every pot instances the same mesh, so the model is loaded and kept only once
+    let teapot = match lod.as_mut() {
+        Some(lod) => { lod.mesh_at(0.0) }
+        None => {
+            let mut polygons = load_tris("./examples/teapot_bezier1.tris.txt", &material).expect("failed to load model");
+            sort_polygons_by_morton_code(&mut polygons);
+            // the patches are tessellated coarsely, only creases sharper than 60° are kept
+            smooth_normals(&mut polygons, PI / 3.0);
+            Rc::new(Mesh::new(polygons))
+        }
+    };
 
+    let mut pots = vec![];
     for i in 0..1{
-        let mut pot = ObjectNode::from(&format!("teapot {}", i), load_teapot("./examples/teapot_bezier1.tris.txt",&PresetPolygonMaterial::LambertWithShadowMaterial(
-            LambertWithShadowMaterial{
-                color: ColorRGBf32{
-                    r: 1.0,
-                    g: 1.0,
-                    b: 1.0,
-                },
-                priority: 10,
-            }
-        )));
+        let mut pot = ObjectNode::from_mesh(&format!("teapot {}", i), MeshInstance::new(teapot.clone()));
 
         pot.position = Vec3{
             x: 0.0,
@@ -70,16 +108,14 @@ fn main() {
             z: 0.0,
         },i as f32 * 0.5, 1.0));
 
-        pot.direction = Quaternion::new(&Vec3{
-            x: 1.0,
-            y: 0.0,
-            z: 0.0,
-        }, 0.0, 10.0);
-        container.add_child(pot);
+        pot.scale = Vec3{
+            x: 10.0,
+            y: 10.0,
+            z: 10.0,
+        };
+        pots.push(engine.genesis_local.add_child(container_id, pot).unwrap());
     }
 
-    engine.genesis_local.add_child(container);
-
     let light = PointLight{
         color: ColorRGBu8 {
             r:255,
@@ -97,10 +133,10 @@ fn main() {
     };
     light_objn.attribute = PresetObjectNodeAttributeDispatcher::from(light).make_shared();
 
-    engine.genesis_local.add_child(light_objn);
+    engine.genesis_local.add_child(root, light_objn).unwrap();
 
     let mut termios_controller = TermiosController::generate(|c, e|{
-        if let Some(camera) = e.genesis_local.child_mut("camera"){
+        if let Some(camera) = e.genesis_local.node_mut(camera_id){
             match c {
                 b'w' => move_camera(3.0, camera, &Vec3{ x: 1.0, y: 0.0, z:0.0 }),
                 b's' => move_camera(3.0, camera, &Vec3{ x: -1.0, y: 0.0, z:0.0 }),
@@ -125,16 +161,24 @@ fn main() {
 
     for _i in 0..65536 {
         engine.sync_engine_time();
-        engine.genesis_local.child_mut("container").unwrap().direction = Quaternion::new(&Vec3{
-            x: 0.0,
-            y: 1.0,
-            z: 0.0,
-        }, engine.engine_time().as_secs_f32(), 1.0);
-
 
         termios_controller.input(&mut engine).expect("something went wrong with processing input from keyboard");
-        engine.genesis_global = engine.genesis_local.generate_global_nodes();
-        engine.render(engine.genesis_global.child("camera").unwrap()).expect("failed rendering");
+        if let Some(lod) = lod.as_mut(){
+            let camera_position = engine.genesis_global.node(camera_id).unwrap().position;
+            for pot in &pots{
+                let distance = match engine.genesis_global.node(*pot) {
+                    Some(p) => { (p.position - camera_position).norm() }
+                    None => { continue; }
+                };
+                let mesh = lod.mesh_at(distance);
+                // only a change of detail touches the node, so the pot is not re-transformed every frame
+                if engine.genesis_local.node(*pot).is_some_and(|n| !Rc::ptr_eq(&n.mesh.as_ref().unwrap().mesh, &mesh)){
+                    engine.genesis_local.node_mut(*pot).unwrap().mesh = Some(MeshInstance::new(mesh));
+                }
+            }
+        }
+        engine.update_global_nodes();
+        engine.render(engine.genesis_global.node(camera_id).unwrap()).expect("failed rendering");
 
         if (last_time.elapsed().as_millis() as u64) < (1000 / fps_upper_limit){
             thread::sleep(Duration::from_millis(1000 / fps_upper_limit - last_time.elapsed().as_millis() as u64));
@@ -145,67 +189,8 @@ fn main() {
         println!("dur:{}      ",dur.as_millis());
         println!("fps:{}      ",1000 / dur.as_millis());
         println!("press [W][A][S][D] to move horizontally, [G][H] to move vertically, [I][J][K][L] to roll, [V] to change camera");
-        println!("current camera: {}     ", preset_camera_info(&engine.genesis_global.child("camera").unwrap().attribute));
+        println!("current camera: {}     ", preset_camera_info(&engine.genesis_global.node(camera_id).unwrap().attribute));
         last_time = Instant::now();
     }
 }
 
-
-
-// https://users.cs.utah.edu/~dejohnso/models/teapot.html
-fn load_teapot(path:&str, material:&PresetPolygonMaterial) -> Vec<Polygon<PresetAsciaEnvironment, Local>>{
-    let mut polygons:Vec<Polygon<PresetAsciaEnvironment, Local>> = vec![];
-
-    if let Ok(f) = File::open(path){
-        let mut reader = BufReader::new(f);
-        let mut s = String::new();
-        if let Ok(_) = reader.read_line(&mut s){
-            if let Ok(num_polygon) = usize::from_str(&(s.trim())){
-                let mut m = Matrix33{
-                    v1: Default::default(),
-                    v2: Default::default(),
-                    v3: Default::default(),
-                };
-                for _ in 0..num_polygon{
-                    s.clear();
-                    reader.read_line(&mut s).expect("failed to load model");
-                    let mut vs = s.split(" ");
-
-                    m.v1.x = f32::from_str(vs.next().unwrap().trim()).unwrap();
-                    m.v1.y = f32::from_str(vs.next().unwrap().trim()).unwrap();
-                    m.v1.z = f32::from_str(vs.next().unwrap().trim()).unwrap();
-
-                    s.clear();
-                    reader.read_line(&mut s).expect("failed to load model");
-                    let mut vs = s.split(" ");
-                    m.v2.x = f32::from_str(vs.next().unwrap().trim()).unwrap();
-                    m.v2.y = f32::from_str(vs.next().unwrap().trim()).unwrap();
-                    m.v2.z = f32::from_str(vs.next().unwrap().trim()).unwrap();
-
-                    s.clear();
-                    reader.read_line(&mut s).expect("failed to load model");
-                    let mut vs = s.split(" ");
-                    m.v3.x = f32::from_str(vs.next().unwrap().trim()).unwrap();
-                    m.v3.y = f32::from_str(vs.next().unwrap().trim()).unwrap();
-                    m.v3.z = f32::from_str(vs.next().unwrap().trim()).unwrap();
-
-                    reader.read_line(&mut s).expect("failed to load model");
-
-                    polygons.push(Polygon{
-                        poses: m,
-                        material: material.clone(),
-                        _ph: Default::default(),
-                    });
-                }
-            }
-            else{
-                return vec![];
-            }
-        }
-        else{
-            return vec![];
-        }
-    }
-    sort_polygons_by_morton_code(&mut polygons);
-    return polygons;
-}
\ No newline at end of file
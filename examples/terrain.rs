@@ -0,0 +1,110 @@
+extern crate ascia;
+
+use std::{env, thread};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use ascia::ascia::color::ColorRGBu8;
+use ascia::ascia::core::{AsciaEngine, ObjectNode, ObjectNodeAttributeDispatcher, PresetAsciaEnvironment, PresetPolygonMaterial, PresetObjectNodeAttributeDispatcher};
+use ascia::ascia::lights::PointLight;
+use ascia::ascia::math::{Quaternion, Vec3};
+use ascia::ascia::terrain::{fbm, lambert_bands, TerrainGenerator};
+use ascia::ascia::util::{available_preset_cameras, move_camera, preset_camera_info, rotate_camera, TermiosController};
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    let width:usize = if cfg!(debug_assertions) { 140 } else { usize::from_str(&args[1]).unwrap() };
+    let height:usize = if cfg!(debug_assertions) { 40 } else { usize::from_str(&args[2]).unwrap() };
+
+    let fps_upper_limit :u64 = 30;
+
+    let mut engine = AsciaEngine::<PresetAsciaEnvironment>::new(width, height);
+    let root = engine.genesis_local.root();
+
+    let cameras = available_preset_cameras();
+    let mut now_camera_index = 1usize;
+
+    // the camera starts over the western edge looking along +x and a little down
+    let mut cam_objn = ObjectNode::new("camera");
+    cam_objn.position = Vec3{
+        x: -180.0,
+        y: 60.0,
+        z: 0.0,
+    };
+    cam_objn.direction = Quaternion::new(&Vec3{
+        x: 0.0,
+        y: 0.0,
+        z: 1.0,
+    }, -0.3, 1.0);
+    cam_objn.attribute = cameras[now_camera_index].clone();
+    let camera_id = engine.genesis_local.add_child(root, cam_objn).unwrap();
+
+    // chunked so that the frustum culling of the preset cameras skips the tiles behind the camera as a whole
+    let mut terrain = TerrainGenerator::<PresetAsciaEnvironment>::new(400.0, 400.0, 80, 80, PresetPolygonMaterial::default());
+    terrain.chunk_segments = Some(16);
+    terrain.bands = lambert_bands(0.0, 40.0);
+    let landscape = terrain.generate("terrain", |x, z| fbm(1, x / 80.0, z / 80.0, 5) * 50.0);
+    engine.genesis_local.add_tree(root, landscape).unwrap();
+
+    let mut light_objn = ObjectNode::new("light");
+    light_objn.position = Vec3{
+        x: 0.0,
+        y: 500.0,
+        z: -200.0,
+    };
+    light_objn.attribute = PresetObjectNodeAttributeDispatcher::from(PointLight{
+        color: ColorRGBu8 {
+            r: 255,
+            g: 255,
+            b: 255
+        }.into(),
+        power: 1.0,
+    }).make_shared();
+    engine.genesis_local.add_child(root, light_objn).unwrap();
+
+    let mut termios_controller = TermiosController::generate(|c, e|{
+        if let Some(camera) = e.genesis_local.node_mut(camera_id){
+            match c {
+                b'h' => move_camera(3.0, camera, &Vec3{ x: 0.0, y: 1.0, z:0.0 }),
+                b'g' => move_camera(3.0, camera, &Vec3{ x: 0.0, y: -1.0, z:0.0 }),
+                b'i' => rotate_camera(0.1, camera, &Vec3{ x: 0.0, y: 0.0, z:1.0 }),
+                b'k' => rotate_camera(0.1,camera, &Vec3{ x: 0.0, y: 0.0, z:-1.0 }),
+                b'j' => rotate_camera(0.1,camera, &Vec3{ x: 0.0, y: -1.0, z: 0.0 }),
+                b'l' => rotate_camera(0.1,camera, &Vec3{ x: 0.0, y: 1.0, z: 0.0 }),
+                b'a' => rotate_camera(0.1,camera, &Vec3{ x: 1.0, y: 0.0, z: 0.0 }),
+                b'd' => rotate_camera(0.1,camera, &Vec3{ x: -1.0, y: 0.0, z: 0.0 }),
+                b'v' => {
+                    now_camera_index = (now_camera_index + 1) % cameras.len();
+                    camera.attribute = cameras[now_camera_index].clone()
+                },
+                _ => {}
+            }
+        }
+    }).unwrap();
+
+    let mut last_time = Instant::now();
+
+    loop{
+        engine.sync_engine_time();
+
+        termios_controller.input(&mut engine).expect("something went wrong with processing input from keyboard");
+        // the plane keeps flying forward on its own
+        if let Some(camera) = engine.genesis_local.node_mut(camera_id){
+            move_camera(1.0, camera, &Vec3{ x: 1.0, y: 0.0, z: 0.0 });
+        }
+        engine.update_global_nodes();
+        engine.render(engine.genesis_global.node(camera_id).unwrap()).expect("failed rendering");
+
+        if (last_time.elapsed().as_millis() as u64) < (1000 / fps_upper_limit){
+            thread::sleep(Duration::from_millis(1000 / fps_upper_limit - last_time.elapsed().as_millis() as u64));
+        }
+
+        let dur = last_time.elapsed();
+        println!("dur:{}      ",dur.as_millis());
+        println!("fps:{}      ",1000 / dur.as_millis());
+        println!("press [I][K] to pitch, [J][L] to turn, [A][D] to roll, [G][H] to move vertically, [V] to change camera");
+        println!("current camera: {}     ", preset_camera_info(&engine.genesis_global.node(camera_id).unwrap().attribute));
+
+        last_time = Instant::now();
+    }
+}
@@ -7,5 +7,11 @@ pub mod charmapper;
 pub mod color;
 #[allow(unused_imports)]
 pub mod util;
+pub mod io;
+pub mod animation;
+pub mod texture;
+pub mod bezier;
+pub mod mesh;
+pub mod terrain;
 #[cfg(feature = "wgpu")]
 pub mod camera_wgpu;
@@ -0,0 +1,389 @@
+use std::rc::Rc;
+use std::time::Duration;
+use crate::ascia::core::{AsciaEnvironment, Local, ObjectNodeTree};
+use crate::ascia::math::{Quaternion, Vec3};
+
+#[cfg(feature = "export")]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "export", derive(Serialize, Deserialize))]
+pub enum AnimationMode{
+    // holds the last keyframe once the clip has ended
+    Once,
+    Loop,
+    PingPong,
+}
+
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "export", derive(Serialize, Deserialize))]
+pub struct Keyframe<T>{
+    pub time: f32,
+    pub value: T,
+}
+
+// keyframes of one node, addressed by its path from the root, channels without keyframes are left alone
+#[cfg_attr(feature = "export", derive(Serialize, Deserialize))]
+pub struct AnimationTrack{
+    pub path: String,
+    position: Vec<Keyframe<Vec3>>,
+    direction: Vec<Keyframe<Quaternion>>,
+    scale: Vec<Keyframe<Vec3>>,
+}
+
+impl AnimationTrack{
+    pub fn new(path: &str) -> Self{
+        return AnimationTrack{
+            path: path.to_string(),
+            position: vec![],
+            direction: vec![],
+            scale: vec![],
+        };
+    }
+
+    pub fn add_position(&mut self, time: f32, value: Vec3){
+        insert_keyframe(&mut self.position, time, value);
+    }
+
+    pub fn add_direction(&mut self, time: f32, value: Quaternion){
+        insert_keyframe(&mut self.direction, time, value);
+    }
+
+    pub fn add_scale(&mut self, time: f32, value: Vec3){
+        insert_keyframe(&mut self.scale, time, value);
+    }
+
+    pub fn position(&self) -> &[Keyframe<Vec3>]{
+        return &self.position;
+    }
+
+    pub fn direction(&self) -> &[Keyframe<Quaternion>]{
+        return &self.direction;
+    }
+
+    pub fn scale(&self) -> &[Keyframe<Vec3>]{
+        return &self.scale;
+    }
+
+    pub fn sample_position(&self, time: f32) -> Option<Vec3>{
+        return sample_keyframes(&self.position, time, |a, b, t| *a + (*b - *a) * t);
+    }
+
+    pub fn sample_direction(&self, time: f32) -> Option<Quaternion>{
+        return sample_keyframes(&self.direction, time, |a, b, t| a.slerp(b, t));
+    }
+
+    pub fn sample_scale(&self, time: f32) -> Option<Vec3>{
+        return sample_keyframes(&self.scale, time, |a, b, t| *a + (*b - *a) * t);
+    }
+
+    fn duration(&self) -> f32{
+        return [self.position.last().map(|k| k.time), self.direction.last().map(|k| k.time), self.scale.last().map(|k| k.time)].into_iter().flatten().fold(0.0, f32::max);
+    }
+}
+
+// a keyframe at the time of an existing one replaces it
+fn insert_keyframe<T>(keyframes: &mut Vec<Keyframe<T>>, time: f32, value: T){
+    let i = keyframes.partition_point(|k| k.time < time);
+    if i < keyframes.len() && keyframes[i].time == time{
+        keyframes[i].value = value;
+    }
+    else{
+        keyframes.insert(i, Keyframe{
+            time: time,
+            value: value,
+        });
+    }
+}
+
+fn sample_keyframes<T: Copy, F: Fn(&T, &T, f32) -> T>(keyframes: &[Keyframe<T>], time: f32, interpolate: F) -> Option<T>{
+    let i = keyframes.partition_point(|k| k.time <= time);
+    if i == 0{
+        return keyframes.first().map(|k| k.value);
+    }
+    if i == keyframes.len(){
+        return Some(keyframes[i - 1].value);
+    }
+    let (a, b) = (&keyframes[i - 1], &keyframes[i]);
+    return Some(interpolate(&a.value, &b.value, (time - a.time) / (b.time - a.time)));
+}
+
+#[cfg_attr(feature = "export", derive(Serialize, Deserialize))]
+pub struct AnimationClip{
+    pub name: String,
+    pub tracks: Vec<AnimationTrack>,
+}
+
+impl AnimationClip{
+    pub fn new(name: &str) -> Self{
+        return AnimationClip{
+            name: name.to_string(),
+            tracks: vec![],
+        };
+    }
+
+    // the time of the last keyframe of any track
+    pub fn duration(&self) -> f32{
+        return self.tracks.iter().map(|t| t.duration()).fold(0.0, f32::max);
+    }
+
+    pub fn track(&self, path: &str) -> Option<&AnimationTrack>{
+        return self.tracks.iter().find(|t| t.path == path);
+    }
+}
+
+pub struct AnimationState{
+    pub clip: Rc<AnimationClip>,
+    pub mode: AnimationMode,
+    // the share of this clip when several clips animate the same channel
+    pub weight: f32,
+    pub speed: f32,
+    pub started: Duration,
+}
+
+impl AnimationState{
+    pub fn new(clip: Rc<AnimationClip>, mode: AnimationMode, started: Duration) -> Self{
+        return AnimationState{
+            clip: clip,
+            mode: mode,
+            weight: 1.0,
+            speed: 1.0,
+            started: started,
+        };
+    }
+
+    // the time within the clip at engine time now
+    pub fn clip_time(&self, now: Duration) -> f32{
+        let duration = self.clip.duration();
+        let t = f32::max((now.as_secs_f32() - self.started.as_secs_f32()) * self.speed, 0.0);
+        if duration <= 0.0{
+            return 0.0;
+        }
+        return match self.mode {
+            AnimationMode::Once => { f32::min(t, duration) }
+            AnimationMode::Loop => { t % duration }
+            AnimationMode::PingPong => {
+                let u = t % (duration * 2.0);
+                if u <= duration { u } else { duration * 2.0 - u }
+            }
+        };
+    }
+
+    pub fn is_finished(&self, now: Duration) -> bool{
+        return self.mode == AnimationMode::Once && (now.as_secs_f32() - self.started.as_secs_f32()) * self.speed >= self.clip.duration();
+    }
+}
+
+#[derive(Default)]
+pub struct Animator{
+    pub states: Vec<AnimationState>,
+}
+
+impl Animator{
+    pub fn new() -> Self{
+        return Animator{
+            states: vec![],
+        };
+    }
+
+    pub fn play(&mut self, clip: Rc<AnimationClip>, mode: AnimationMode, now: Duration) -> &mut AnimationState{
+        self.states.push(AnimationState::new(clip, mode, now));
+        return self.states.last_mut().unwrap();
+    }
+
+    pub fn stop(&mut self, name: &str){
+        self.states.retain(|s| s.clip.name != name);
+    }
+
+    // writes the blended pose at engine time now into the local tree, tracks of nodes that no longer exist are skipped
+    // only channels whose value changed are written, so resting nodes are not marked dirty, and a finished Once clip
+    // writes its last pose one more time and is then dropped, leaving the nodes where it ended
+    pub fn apply<E: AsciaEnvironment>(&mut self, now: Duration, tree: &mut ObjectNodeTree<E, Local>){
+        let mut paths: Vec<&str> = vec![];
+        for s in &self.states{
+            for t in &s.clip.tracks{
+                if !paths.contains(&t.path.as_str()){
+                    paths.push(&t.path);
+                }
+            }
+        }
+        for path in paths{
+            let mut position: Option<(Vec3, f32)> = None;
+            let mut direction: Option<(Quaternion, f32)> = None;
+            let mut scale: Option<(Vec3, f32)> = None;
+            for s in self.states.iter().filter(|s| s.weight > 0.0){
+                if let Some(track) = s.clip.track(path){
+                    let t = s.clip_time(now);
+                    // each sample is folded into the running blend with its share of the weights so far
+                    if let Some(p) = track.sample_position(t){
+                        position = Some(match position {
+                            Some((b, w)) => { (b + (p - b) * (s.weight / (w + s.weight)), w + s.weight) }
+                            None => { (p, s.weight) }
+                        });
+                    }
+                    if let Some(d) = track.sample_direction(t){
+                        direction = Some(match direction {
+                            Some((b, w)) => { (b.slerp(&d, s.weight / (w + s.weight)), w + s.weight) }
+                            None => { (d, s.weight) }
+                        });
+                    }
+                    if let Some(c) = track.sample_scale(t){
+                        scale = Some(match scale {
+                            Some((b, w)) => { (b + (c - b) * (s.weight / (w + s.weight)), w + s.weight) }
+                            None => { (c, s.weight) }
+                        });
+                    }
+                }
+            }
+            if position.is_none() && direction.is_none() && scale.is_none(){
+                continue;
+            }
+            let changed = match tree.child_by_path(path) {
+                Some(node) => {
+                    position.is_some_and(|(p, _)| p != node.position) || direction.is_some_and(|(d, _)| d != node.direction) || scale.is_some_and(|(c, _)| c != node.scale)
+                }
+                None => { false }
+            };
+            if !changed{
+                continue;
+            }
+            let node = tree.child_by_path_mut(path).unwrap();
+            if let Some((p, _)) = position{
+                node.position = p;
+            }
+            if let Some((d, _)) = direction{
+                node.direction = d;
+            }
+            if let Some((c, _)) = scale{
+                node.scale = c;
+            }
+        }
+        self.states.retain(|s| !s.is_finished(now));
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use std::f32::consts::PI;
+    use std::rc::Rc;
+    use std::time::Duration;
+    use crate::ascia::animation::{AnimationClip, AnimationMode, AnimationTrack, Animator};
+    use crate::ascia::core::{Local, ObjectNode, ObjectNodeTree, PresetAsciaEnvironment};
+    use crate::ascia::math::{Quaternion, Vec3};
+
+    fn secs(t: f32) -> Duration{
+        return Duration::from_secs_f32(t);
+    }
+
+    #[test]
+    fn test_sample_track(){
+        let mut track = AnimationTrack::new("a");
+        track.add_position(2.0, Vec3{ x: 10.0, y: 0.0, z: 0.0 });
+        track.add_position(0.0, Vec3{ x: 0.0, y: 0.0, z: 0.0 });
+        track.add_position(1.0, Vec3{ x: 4.0, y: 0.0, z: 0.0 });
+        assert_eq!(track.position().len(), 3);
+        assert_eq!(track.sample_position(-1.0).unwrap().x, 0.0);
+        assert_eq!(track.sample_position(0.5).unwrap().x, 2.0);
+        assert_eq!(track.sample_position(1.5).unwrap().x, 7.0);
+        assert_eq!(track.sample_position(5.0).unwrap().x, 10.0);
+        assert!(track.sample_direction(0.5).is_none());
+
+        let axis = Vec3{ x: 0.0, y: 1.0, z: 0.0 };
+        track.add_direction(0.0, Quaternion::new(&axis, 0.0, 1.0));
+        track.add_direction(1.0, Quaternion::new(&axis, PI / 2.0, 1.0));
+        let d = track.sample_direction(0.5).unwrap();
+        assert!((d.vec4 - Quaternion::new(&axis, PI / 4.0, 1.0).vec4).norm() < 1e-5);
+    }
+
+    #[test]
+    fn test_modes(){
+        let mut track = AnimationTrack::new("a");
+        track.add_position(0.0, Vec3{ x: 0.0, y: 0.0, z: 0.0 });
+        track.add_position(2.0, Vec3{ x: 2.0, y: 0.0, z: 0.0 });
+        let mut clip = AnimationClip::new("move");
+        clip.tracks.push(track);
+        let clip = Rc::new(clip);
+        assert_eq!(clip.duration(), 2.0);
+
+        let mut animator = Animator::new();
+        animator.play(clip.clone(), AnimationMode::Once, secs(1.0));
+        animator.play(clip.clone(), AnimationMode::Loop, secs(1.0));
+        animator.play(clip.clone(), AnimationMode::PingPong, secs(1.0)).speed = 2.0;
+        let times: Vec<f32> = animator.states.iter().map(|s| s.clip_time(secs(3.5))).collect();
+        assert!(f32::abs(times[0] - 2.0) < 1e-4);
+        assert!(f32::abs(times[1] - 0.5) < 1e-4);
+        assert!(f32::abs(times[2] - 1.0) < 1e-4);
+        assert!(animator.states[0].is_finished(secs(3.5)));
+        assert!(!animator.states[1].is_finished(secs(3.5)));
+        assert_eq!(animator.states[0].clip_time(secs(0.0)), 0.0);
+        animator.stop("move");
+        assert!(animator.states.is_empty());
+    }
+
+    #[test]
+    fn test_apply_and_blend(){
+        let mut tree: ObjectNodeTree<PresetAsciaEnvironment, Local> = ObjectNodeTree::new(ObjectNode::new("root"));
+        let arm = tree.add_child(tree.root(), ObjectNode::new("arm")).unwrap();
+        let hand = tree.add_child(arm, ObjectNode::new("hand")).unwrap();
+        let mut global = tree.generate_global_nodes();
+        tree.update_global_nodes(&mut global);
+
+        let mut walk = AnimationClip::new("walk");
+        let mut track = AnimationTrack::new("arm/hand");
+        track.add_position(0.0, Vec3{ x: 0.0, y: 0.0, z: 0.0 });
+        track.add_position(1.0, Vec3{ x: 4.0, y: 0.0, z: 0.0 });
+        track.add_scale(0.0, Vec3{ x: 2.0, y: 2.0, z: 2.0 });
+        walk.tracks.push(track);
+        walk.tracks.push(AnimationTrack::new("missing"));
+        let mut wave = AnimationClip::new("wave");
+        let mut track = AnimationTrack::new("arm/hand");
+        track.add_position(0.0, Vec3{ x: 0.0, y: 8.0, z: 0.0 });
+        wave.tracks.push(track);
+
+        let mut animator = Animator::new();
+        animator.play(Rc::new(walk), AnimationMode::Loop, secs(0.0));
+        animator.apply(secs(0.5), &mut tree);
+        assert_eq!(tree.node(hand).unwrap().position, Vec3{ x: 2.0, y: 0.0, z: 0.0 });
+        assert!(tree.root_node().is_dirty());
+        assert!(tree.node(arm).unwrap().position == Vec3::default());
+
+        animator.play(Rc::new(wave), AnimationMode::Loop, secs(0.0)).weight = 3.0;
+        animator.apply(secs(0.5), &mut tree);
+        let hand_node = tree.node(hand).unwrap();
+        assert!((hand_node.position - Vec3{ x: 0.5, y: 6.0, z: 0.0 }).norm() < 1e-5);
+        assert_eq!(hand_node.scale, Vec3{ x: 2.0, y: 2.0, z: 2.0 });
+        assert_eq!(hand_node.direction, Quaternion::default());
+    }
+
+    #[test]
+    fn test_apply_only_changes(){
+        let mut tree: ObjectNodeTree<PresetAsciaEnvironment, Local> = ObjectNodeTree::new(ObjectNode::new("root"));
+        let hand = tree.add_child(tree.root(), ObjectNode::new("hand")).unwrap();
+        let mut global = tree.generate_global_nodes();
+        tree.update_global_nodes(&mut global);
+
+        let mut track = AnimationTrack::new("hand");
+        track.add_position(0.0, Vec3{ x: 0.0, y: 0.0, z: 0.0 });
+        track.add_position(1.0, Vec3{ x: 4.0, y: 0.0, z: 0.0 });
+        let mut clip = AnimationClip::new("reach");
+        clip.tracks.push(track);
+        let mut animator = Animator::new();
+        animator.play(Rc::new(clip), AnimationMode::Once, secs(0.0));
+
+        // the same pose twice leaves the tree clean
+        animator.apply(secs(0.5), &mut tree);
+        assert!(tree.root_node().is_dirty());
+        tree.update_global_nodes(&mut global);
+        animator.apply(secs(0.5), &mut tree);
+        assert!(!tree.root_node().is_dirty());
+
+        // the finished clip writes its end pose and is dropped
+        animator.apply(secs(2.0), &mut tree);
+        assert_eq!(tree.node(hand).unwrap().position, Vec3{ x: 4.0, y: 0.0, z: 0.0 });
+        assert!(animator.states.is_empty());
+        tree.update_global_nodes(&mut global);
+        animator.apply(secs(3.0), &mut tree);
+        assert!(!tree.root_node().is_dirty());
+        assert_eq!(tree.node(hand).unwrap().position, Vec3{ x: 4.0, y: 0.0, z: 0.0 });
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+use crate::ascia::core::{AsciaEnvironment, Local, Mesh, Polygon};
+use crate::ascia::math::{Matrix33, Vec2, Vec3};
+
+#[cfg(feature = "export")]
+use serde::{Deserialize, Serialize};
+
+// a bicubic patch, points[i][j] is the control point in row i along v and column j along u
+// the surface faces towards the u tangent crossed with the v tangent
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "export", derive(Serialize, Deserialize))]
+pub struct BezierPatch{
+    pub points: [[Vec3; 4]; 4],
+}
+
+impl BezierPatch{
+    pub fn evaluate(&self, u: f32, v: f32) -> Vec3{
+        return self.evaluate_with_tangents(u, v).0;
+    }
+
+    // the position and its derivatives along u and v
+    pub fn evaluate_with_tangents(&self, u: f32, v: f32) -> (Vec3, Vec3, Vec3){
+        let rows = self.points.map(|row| de_casteljau(&row, u));
+        let (position, dv) = de_casteljau(&rows.map(|r| r.0), v);
+        let (du, _) = de_casteljau(&rows.map(|r| r.1), v);
+        return (position, du, dv);
+    }
+
+    pub fn normal(&self, u: f32, v: f32) -> Vec3{
+        let (_, du, dv) = self.evaluate_with_tangents(u, v);
+        let n = du ^ dv;
+        if n.norm() > f32::EPSILON{
+            return n.normalize();
+        }
+        // a collapsed edge like the tip of a lid has no tangent plane of its own, the one right next to it stands in
+        let (_, du, dv) = self.evaluate_with_tangents(u + (0.5 - u) * 1e-3, v + (0.5 - v) * 1e-3);
+        return (du ^ dv).normalize();
+    }
+
+    // segments x segments cells of two triangles each, cells that collapse at a degenerate edge leave out their empty half
+    pub fn tessellate<E: AsciaEnvironment>(&self, segments: usize, material: &E::PolygonMaterials) -> Vec<Polygon<E, Local>>{
+        let segments = segments.max(1);
+        let vertex = |i: usize, j: usize| {
+            let uv = Vec2{ x: i as f32 / segments as f32, y: j as f32 / segments as f32 };
+            (self.evaluate(uv.x, uv.y), self.normal(uv.x, uv.y), uv)
+        };
+        let grid: Vec<Vec<(Vec3, Vec3, Vec2)>> = (0..=segments).map(|i| (0..=segments).map(|j| vertex(i, j)).collect()).collect();
+
+        let mut polygons = Vec::with_capacity(segments * segments * 2);
+        for i in 0..segments{
+            for j in 0..segments{
+                let (v00, v10, v11, v01) = (&grid[i][j], &grid[i + 1][j], &grid[i + 1][j + 1], &grid[i][j + 1]);
+                for (a, b, c) in [(v00, v10, v11), (v00, v11, v01)]{
+                    if a.0 == b.0 || b.0 == c.0 || c.0 == a.0{
+                        continue;
+                    }
+                    polygons.push(Polygon{
+                        poses: Matrix33{
+                            v1: a.0,
+                            v2: b.0,
+                            v3: c.0,
+                        },
+                        normals: Some(Matrix33{
+                            v1: a.1,
+                            v2: b.1,
+                            v3: c.1,
+                        }),
+                        uvs: Some([a.2, b.2, c.2]),
+                        material: material.clone(),
+                        _ph: Default::default(),
+                    });
+                }
+            }
+        }
+        return polygons;
+    }
+}
+
+pub fn tessellate_patches<E: AsciaEnvironment>(patches: &[BezierPatch], segments: usize, material: &E::PolygonMaterials) -> Vec<Polygon<E, Local>>{
+    return patches.iter().flat_map(|p| p.tessellate(segments, material)).collect();
+}
+
+// the point and the derivative of a cubic curve, lerping keeps a collapsed curve exactly on its point
+fn de_casteljau(p: &[Vec3; 4], t: f32) -> (Vec3, Vec3){
+    let lerp = |a: &Vec3, b: &Vec3| *a + (*b - *a) * t;
+    let (a, b, c) = (lerp(&p[0], &p[1]), lerp(&p[1], &p[2]), lerp(&p[2], &p[3]));
+    let (d, e) = (lerp(&a, &b), lerp(&b, &c));
+    return (lerp(&d, &e), (e - d) * 3.0);
+}
+
+// tessellations of a set of patches that get coarser with distance, each built once and shared as a mesh
+// max_segments is used up to base_distance and every doubling of the distance beyond halves the segments down to min_segments
+pub struct BezierLod<E: AsciaEnvironment + ?Sized + 'static>{
+    pub patches: Vec<BezierPatch>,
+    pub material: E::PolygonMaterials,
+    pub base_distance: f32,
+    pub max_segments: usize,
+    pub min_segments: usize,
+    meshes: HashMap<usize, Rc<Mesh<E>>>,
+}
+
+impl<E: AsciaEnvironment> BezierLod<E>{
+    pub fn new(patches: Vec<BezierPatch>, material: E::PolygonMaterials, base_distance: f32, max_segments: usize, min_segments: usize) -> Self{
+        return BezierLod{
+            patches: patches,
+            material: material,
+            base_distance: base_distance,
+            max_segments: max_segments.max(1),
+            min_segments: min_segments.clamp(1, max_segments.max(1)),
+            meshes: HashMap::new(),
+        };
+    }
+
+    pub fn segments_at(&self, distance: f32) -> usize{
+        if distance.is_nan() || distance <= self.base_distance{
+            return self.max_segments;
+        }
+        let halvings = f32::log2(distance / self.base_distance) as u32;
+        return self.max_segments.checked_shr(halvings).unwrap_or(0).max(self.min_segments);
+    }
+
+    pub fn mesh_at(&mut self, distance: f32) -> Rc<Mesh<E>>{
+        let segments = self.segments_at(distance);
+        let (patches, material) = (&self.patches, &self.material);
+        return self.meshes.entry(segments).or_insert_with(|| Rc::new(Mesh::new(tessellate_patches(patches, segments, material)))).clone();
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::ascia::bezier::{BezierLod, BezierPatch};
+    use crate::ascia::core::{Local, Polygon, PresetAsciaEnvironment, PresetPolygonMaterial};
+    use crate::ascia::math::Vec3;
+
+    // a saddle over the unit square on the xy plane, bulging along z inside
+    fn saddle() -> BezierPatch{
+        let mut points = [[Vec3::default(); 4]; 4];
+        for i in 0..4{
+            for j in 0..4{
+                let (x, y) = (j as f32 / 3.0, i as f32 / 3.0);
+                points[i][j] = Vec3{ x: x, y: y, z: (x - 0.5) * (y - 0.5) };
+            }
+        }
+        return BezierPatch{
+            points: points,
+        };
+    }
+
+    #[test]
+    fn test_evaluate(){
+        let patch = saddle();
+        // evenly spaced control points reproduce the bilinear function they sample
+        for (u, v) in [(0.0, 0.0), (1.0, 0.0), (0.3, 0.8), (0.5, 0.5)]{
+            let p = patch.evaluate(u, v);
+            assert!((p - Vec3{ x: u, y: v, z: (u - 0.5) * (v - 0.5) }).norm() < 1e-6);
+        }
+        let (_, du, dv) = patch.evaluate_with_tangents(0.25, 0.5);
+        assert!((du - Vec3{ x: 1.0, y: 0.0, z: 0.0 }).norm() < 1e-5);
+        assert!((dv - Vec3{ x: 0.0, y: 1.0, z: -0.25 }).norm() < 1e-5);
+        assert!((patch.normal(0.5, 0.5) - Vec3{ x: 0.0, y: 0.0, z: 1.0 }).norm() < 1e-6);
+    }
+
+    #[test]
+    fn test_tessellate(){
+        let polygons: Vec<Polygon<PresetAsciaEnvironment, Local>> = saddle().tessellate(5, &PresetPolygonMaterial::default());
+        assert_eq!(polygons.len(), 5 * 5 * 2);
+        for p in &polygons{
+            let normals = p.normals.unwrap();
+            for n in [normals.v1, normals.v2, normals.v3]{
+                assert!(p.normal() * n > 0.9);
+            }
+        }
+        let uv = polygons[0].uvs.unwrap();
+        assert_eq!((uv[0].x, uv[0].y, uv[1].x, uv[2].y), (0.0, 0.0, 0.2, 0.2));
+
+        // the first row collapsed into a point like the top of a lid leaves a fan
+        let mut cone = saddle();
+        cone.points[0] = [Vec3{ x: 0.5, y: 0.0, z: 1.0 }; 4];
+        let polygons: Vec<Polygon<PresetAsciaEnvironment, Local>> = cone.tessellate(4, &PresetPolygonMaterial::default());
+        assert_eq!(polygons.len(), 4 * 4 * 2 - 4);
+        assert!(f32::abs(cone.normal(0.5, 0.0).norm() - 1.0) < 1e-4);
+        assert!(cone.normal(0.5, 0.0) * cone.normal(0.5, 0.01) > 0.9);
+    }
+
+    #[test]
+    fn test_lod(){
+        let mut lod = BezierLod::<PresetAsciaEnvironment>::new(vec![saddle(), saddle()], PresetPolygonMaterial::default(), 10.0, 16, 2);
+        assert_eq!(lod.segments_at(5.0), 16);
+        assert_eq!(lod.segments_at(19.0), 16);
+        assert_eq!(lod.segments_at(20.0), 8);
+        assert_eq!(lod.segments_at(45.0), 4);
+        assert_eq!(lod.segments_at(1e9), 2);
+
+        let near = lod.mesh_at(1.0);
+        assert_eq!(near.polygons().len(), 2 * 16 * 16 * 2);
+        assert!(std::rc::Rc::ptr_eq(&near, &lod.mesh_at(15.0)));
+        assert_eq!(lod.mesh_at(1000.0).polygons().len(), 2 * 2 * 2 * 2);
+    }
+}
@@ -3,9 +3,10 @@ use std::cell::RefCell;
 use std::collections::VecDeque;
 use std::f32::consts::PI;
 use std::marker::PhantomData;
+use std::rc::Rc;
 use crate::ascia::charmapper;
-use crate::ascia::core::{AsciaEngine, Camera, CParticle, CParticleMode, CParticleRayIntersection, FlatMaterial, Global, LambertMaterial, LambertWithShadowMaterial, Material, ObjectNode, Polygon, PolygonRayIntersection, Ray, RaytracingTarget, RayIntersection, RenderChar, PresetPolygonMaterial, PresetCamera, ObjectNodeAttribute, AsciaEnvironment, ObjectNodeAttributeDispatcher, LightDispatcher, MaterialDispatcher, PresetObjectNodeAttributeDispatcher, CameraDispatcher, PresetLight, PresetCParticleMaterial};
-use crate::ascia::math::{AABB3D, Vec3};
+use crate::ascia::core::{ALL_LAYERS, AsciaEngine, Camera, CoordinateType, CParticle, CParticleMode, CParticleRayIntersection, FlatMaterial, Global, LambertMaterial, LambertWithShadowMaterial, Local, Material, Mesh, ObjectNode, Placement, Polygon, PolygonRayIntersection, Ray, RaytracingTarget, RayIntersection, RenderChar, PresetPolygonMaterial, PresetCamera, ObjectNodeAttribute, AsciaEnvironment, ObjectNodeAttributeDispatcher, LightDispatcher, MaterialDispatcher, PresetObjectNodeAttributeDispatcher, CameraDispatcher, PresetLight, PresetCParticleMaterial, TexturedMaterial};
+use crate::ascia::math::{AABB3D, Frustum, Vec2, Vec3};
 use crate::ascia::color::{ColorRGBf32, ColorRGBu8};
 
 impl<E: AsciaEnvironment, CA:Camera<E>> Material<E, CA, Polygon<E, Global>> for &FlatMaterial{
@@ -18,9 +19,9 @@ impl<E: AsciaEnvironment, CA:Camera<E>> Material<E, CA, Polygon<E, Global>> for
 
 impl<E: AsciaEnvironment, CA:Camera<E>> Material<E, CA, Polygon<E, Global>> for &LambertMaterial{
     type Output = (ColorRGBf32, u32);
-    fn calc_color(&self, intersection: &PolygonRayIntersection<E, Global>, engine: &AsciaEngine<E>, _camera: &CA, camera_node: &ObjectNode<E, Global>, _global_polygons: &Vec<Polygon<E, Global>>) -> Self::Output{
+    fn calc_color(&self, intersection: &PolygonRayIntersection<E, Global>, engine: &AsciaEngine<E>, camera: &CA, camera_node: &ObjectNode<E, Global>, _global_polygons: &Vec<Polygon<E, Global>>) -> Self::Output{
         let mut result = ColorRGBf32::default();
-        for node in engine.genesis_global.iter(){
+        for node in engine.genesis_global.iter().filter(|n| n.is_visible_to(camera.layer_mask())){
             if let Some(a) = &*node.attribute.borrow(){
                 if let Some(light) = a.light(){
                     let co = (node.position - intersection.position).normalize() * intersection.normal;
@@ -41,9 +42,9 @@ impl<E: AsciaEnvironment, CA:Camera<E>> Material<E, CA, Polygon<E, Global>> for
 
 impl<'a, E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMaterials=PresetCParticleMaterial, Lights = PresetLight>> Material<E, SimpleCamera<E>, Polygon<E, Global>> for &LambertWithShadowMaterial{
     type Output = (ColorRGBf32, u32);
-    fn calc_color(&self, intersection: &PolygonRayIntersection<E, Global>, engine: &AsciaEngine<E>, _camera: &SimpleCamera<E>, camera_node: &ObjectNode<E, Global>, global_polygons: &Vec<Polygon<E, Global>>) -> Self::Output{
+    fn calc_color(&self, intersection: &PolygonRayIntersection<E, Global>, engine: &AsciaEngine<E>, camera: &SimpleCamera<E>, camera_node: &ObjectNode<E, Global>, global_polygons: &Vec<Polygon<E, Global>>) -> Self::Output{
         let mut result = ColorRGBf32::default();
-        for node in engine.genesis_global.iter(){
+        for node in engine.genesis_global.iter().filter(|n| n.is_visible_to(camera.layer_mask)){
             if let Some(a) = &*node.attribute.borrow(){
                 if let Some(light) = a.light(){
                     let co = (node.position - intersection.position).normalize() * intersection.normal;
@@ -85,15 +86,20 @@ impl<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMateri
             g:0.0,
             b:0.0
         };
-        for node in engine.genesis_global.iter(){
+        for node in engine.genesis_global.iter().filter(|n| n.is_visible_to(camera.layer_mask)){
             if let Some(a) = &*node.attribute.borrow() {
                 if let Some(light) = a.light() {
                     let co = (node.position - intersection.position).normalize() * intersection.normal;
                     let color = light.ray(node, &intersection.position);
-                    let is_prevented = Ray{
+                    let ray = Ray{
                         position: intersection.position,
                         direction: node.position - intersection.position
-                    }.project(&*camera.polygons_bvh_tree.borrow(), &(|i: &PolygonRayIntersection<E, Global>| -> bool { std::ptr::eq(i.polygon, intersection.polygon)})).is_some();
+                    };
+                    let source = camera.instance_hits.borrow().source(intersection.polygon);
+                    let is_prevented = ray.project(&*camera.polygons_bvh_tree.borrow(), &(|i: &PolygonRayIntersection<E, Global>| -> bool { std::ptr::eq(i.polygon, intersection.polygon)})).is_some()
+                        || ray.project(&*camera.mesh_instances_bvh_tree.borrow(), &(|i: &MeshInstanceRayIntersection<E>| -> bool { Some((i.instance as *const MeshInstanceProxy<E>, i.polygon as *const Polygon<E, Local>)) == source })).is_some()
+                        || ray.project(&*camera.out_of_view_polygons_bvh_tree.borrow(), &|_|{false}).is_some()
+                        || ray.project(&*camera.out_of_view_mesh_instances_bvh_tree.borrow(), &|_|{false}).is_some();
                     if is_prevented{
                         continue;
                     }
@@ -111,6 +117,21 @@ impl<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMateri
     }
 }
 
+impl<E: AsciaEnvironment, CA:Camera<E>> Material<E, CA, Polygon<E, Global>> for &TexturedMaterial{
+    type Output = (ColorRGBf32, u32);
+    fn calc_color(&self, intersection: &PolygonRayIntersection<E, Global>, engine: &AsciaEngine<E>, camera: &CA, camera_node: &ObjectNode<E, Global>, global_polygons: &Vec<Polygon<E, Global>>) -> Self::Output{
+        let color = self.color_at(intersection.polygon, &intersection.position_on_polygon);
+        if !self.lit{
+            return (color, self.priority);
+        }
+        let lambert = LambertMaterial{
+            color: color,
+            priority: self.priority,
+        };
+        return <_ as Material<E, CA, Polygon<E, Global>>>::calc_color(&&lambert, intersection, engine, camera, camera_node, global_polygons);
+    }
+}
+
 // auto deref specialization
 impl<'a, E: AsciaEnvironment, CA: Camera<E>> Material<E, CA, Polygon<E, Global>> for LambertWithShadowMaterial{
     type Output = (ColorRGBf32, u32);
@@ -145,6 +166,10 @@ impl<E: AsciaEnvironment, CA:Camera<E>> Material<E, CA, CParticle<E, Global>> fo
 pub struct SimpleCamera<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial>>{
     pub angle_of_view: (f32, f32),
     pub sampling_size: u32,
+    // camera rays skip nodes outside of the view, shadow rays still hit them
+    pub frustum_culling: bool,
+    // only nodes on one of these layers are rendered and only lights on one of them are applied
+    pub layer_mask: u32,
     pub ph: PhantomData<E>
 }
 
@@ -153,6 +178,8 @@ impl<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial>> Default for Si
         return SimpleCamera{
             angle_of_view: (PI / 3.0,PI / 4.0),
             sampling_size: 1,
+            frustum_culling: true,
+            layer_mask: ALL_LAYERS,
             ph: Default::default(),
         }
     }
@@ -163,6 +190,8 @@ impl<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial>> SimpleCamera<E
         return SimpleCamera{
             angle_of_view: angle_of_view,
             sampling_size: sampling_size,
+            frustum_culling: true,
+            layer_mask: ALL_LAYERS,
             ph: Default::default(),
         }
     }
@@ -179,15 +208,18 @@ impl<E: AsciaEnvironment<ObjectNodeAttributes = PresetObjectNodeAttributeDispatc
 
 impl<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMaterials=PresetCParticleMaterial, Lights = PresetLight>> MaterialDispatcher<E, Polygon<E, Global>> for SimpleCamera<E>{
     fn calc_color(&self, intersection: &PolygonRayIntersection<E, Global>, engine: &AsciaEngine<E>, camera_node: &ObjectNode<E, Global>, global_polygons: &Vec<Polygon<E, Global>>) -> (ColorRGBf32, u32) {
-        match intersection.polygon.material {
+        match &intersection.polygon.material {
             PresetPolygonMaterial::FlatMaterial(m) => {
-                <_ as Material<E, SimpleCamera<E>, Polygon<E, Global>>>::calc_color(&(&m), intersection, engine, self, camera_node, global_polygons)
+                <_ as Material<E, SimpleCamera<E>, Polygon<E, Global>>>::calc_color(&m, intersection, engine, self, camera_node, global_polygons)
             }
             PresetPolygonMaterial::LambertMaterial(m) => {
-                <_ as Material<E, SimpleCamera<E>, Polygon<E, Global>>>::calc_color(&(&m), intersection, engine, self, camera_node, global_polygons)
+                <_ as Material<E, SimpleCamera<E>, Polygon<E, Global>>>::calc_color(&m, intersection, engine, self, camera_node, global_polygons)
             }
             PresetPolygonMaterial::LambertWithShadowMaterial(m) => {
-                <_ as Material<E, SimpleCamera<E>, Polygon<E, Global>>>::calc_color(&(&m), intersection, engine, self, camera_node, global_polygons)
+                <_ as Material<E, SimpleCamera<E>, Polygon<E, Global>>>::calc_color(&m, intersection, engine, self, camera_node, global_polygons)
+            }
+            PresetPolygonMaterial::TexturedMaterial(m) => {
+                <_ as Material<E, SimpleCamera<E>, Polygon<E, Global>>>::calc_color(&m, intersection, engine, self, camera_node, global_polygons)
             }
         }
     }
@@ -209,7 +241,39 @@ impl<E: AsciaEnvironment<PolygonMaterials = PresetPolygonMaterial, CParticleMate
     }
 }
 
+type VisibleNodes<'a, E> = (Vec<&'a ObjectNode<E, Global>>, Vec<&'a ObjectNode<E, Global>>);
+
+// the nodes on the camera's layers split into the ones camera rays can hit and the rest, which only shadow rays need
+pub(crate) fn visible_nodes<'a, E: AsciaEnvironment>(engine: &'a AsciaEngine<E>, camera_node: &ObjectNode<E, Global>, angle_of_view: (f32, f32), frustum_culling: bool, layer_mask: u32) -> VisibleNodes<'a, E>{
+    let nodes = engine.genesis_global.iter().filter(|n| n.is_visible_to(layer_mask));
+    if !frustum_culling{
+        return (nodes.collect(), vec![]);
+    }
+    let frustum = Frustum::new(&camera_node.position, &camera_node.direction, angle_of_view);
+    return nodes.partition(|n| n.bounds().map(|b| frustum.intersects(&b)).unwrap_or(false));
+}
+
+// the ray through the upper left corner of cell (x, y), +x is forward and +y is up
+fn perspective_ray<E: AsciaEnvironment>(angle_of_view: &(f32, f32), camera_node: &ObjectNode<E, Global>, x: usize, y: usize, width: usize, height: usize) -> Ray{
+    return Ray{
+        position: camera_node.position,
+        direction: camera_node.direction.rotate(&Vec3{
+            x: 1.0,
+            y: f32::tan(angle_of_view.1 * 0.5) * (1.0 - 2.0 * y as f32 / height as f32),
+            z: f32::tan(angle_of_view.0 * 0.5) * (1.0 - 2.0 * x as f32 / width as f32),
+        }),
+    };
+}
+
 impl<E:AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMaterials=PresetCParticleMaterial, Lights = PresetLight>> Camera<E> for SimpleCamera<E>{
+    fn screen_ray(&self, camera_node: &ObjectNode<E, Global>, x: usize, y: usize, width: usize, height: usize) -> Ray{
+        return perspective_ray(&self.angle_of_view, camera_node, x, y, width, height);
+    }
+
+    fn layer_mask(&self) -> u32{
+        return self.layer_mask;
+    }
+
     fn render(&self, node: &ObjectNode<E, Global>, engine: &AsciaEngine<E>) -> Vec<Vec<RenderChar>> {
         let height = engine.viewport.borrow().height();
         let width = engine.viewport.borrow().width();
@@ -221,33 +285,27 @@ impl<E:AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMateria
         let mut global_polygons = vec![];
         let mut global_c_particles = vec![];
 
-        for iter in engine.genesis_global.iter(){
+        let (in_view, out_of_view) = visible_nodes(engine, node, self.angle_of_view, self.frustum_culling, self.layer_mask);
+        for iter in in_view{
             global_polygons.extend(iter.polygons.clone());
+            global_polygons.extend(iter.mesh_polygons());
             global_c_particles.extend(iter.c_particles.clone());
             global_c_particles.extend(iter.c_particles.clone());
         }
+        // camera rays only look at the polygons in view, the ones behind them are kept for the shadow rays
+        let in_view_polygons = global_polygons.len();
+        for iter in out_of_view{
+            global_polygons.extend(iter.polygons.clone());
+            global_polygons.extend(iter.mesh_polygons());
+        }
 
         let mut c_particle_counters = vec![0u32; global_c_particles.len()];
 
         if self.sampling_size == 1{
             for x in 0..width{
                 for y in 0..height{
-                    polygon_intersections[x][y] = Ray{
-                        position: node.position,
-                        direction: node.direction.rotate(&Vec3{
-                            x:1.0,
-                            y:f32::tan(self.angle_of_view.1 * 0.5) * (1.0 - 2.0 * y as f32 / height as f32),
-                            z:f32::tan(self.angle_of_view.0 * 0.5) * (1.0 - 2.0 * x as f32 / width as f32),
-                        })
-                    }.project(&global_polygons, &|_|{false});
-                    c_particle_intersections[x][y] = Ray{
-                        position: node.position,
-                        direction: node.direction.rotate(&Vec3{
-                            x:1.0,
-                            y:f32::tan(self.angle_of_view.1 * 0.5) * (1.0 - 2.0 * y as f32 / height as f32),
-                            z:f32::tan(self.angle_of_view.0 * 0.5) * (1.0 - 2.0 * x as f32 / width as f32),
-                        })
-                    }.project(&global_c_particles, &|i|{
+                    polygon_intersections[x][y] = self.screen_ray(node, x, y, width, height).project(&global_polygons[..in_view_polygons], &|_|{false});
+                    c_particle_intersections[x][y] = self.screen_ray(node, x, y, width, height).project(&global_c_particles, &|i|{
                         c_particle_counters[((i.particle as *const CParticle<E, Global>) as usize - (&global_c_particles[0] as *const CParticle<E, Global>) as usize) / Layout::for_value(&global_c_particles[0]).size()] > 0
                     });
                     if let Some(i) = &c_particle_intersections[x][y]{
@@ -281,26 +339,12 @@ impl<E:AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMateria
                             if i == 1 && j == 1{
                                 continue;
                             }
-                            let ray = Ray{
-                                position: node.position,
-                                direction: node.direction.rotate(&Vec3{
-                                    x:1.0,
-                                    y:f32::tan(self.angle_of_view.1 * 0.5) * (1.0 - 2.0 * (y * 3 + i) as f32 / (height * 3) as f32),
-                                    z:f32::tan(self.angle_of_view.0 * 0.5) * (1.0 - 2.0 * (x * 3 + j) as f32 / (width * 3) as f32),
-                                }),
-                            };
-                            let v = &global_polygons;
+                            let ray = self.screen_ray(node, x * 3 + j, y * 3 + i, width * 3, height * 3);
+                            let v = &global_polygons[..in_view_polygons];
                             polygon_intersections[x * 3 + j][y * 3 + i] =  ray.project(v, &|_|{false});
                         }
                     }
-                    c_particle_intersections[x][y] = Ray{
-                        position: node.position,
-                        direction: node.direction.rotate(&Vec3{
-                            x:1.0,
-                            y:f32::tan(self.angle_of_view.1 * 0.5) * (1.0 - 2.0 * y as f32 / height as f32),
-                            z:f32::tan(self.angle_of_view.0 * 0.5) * (1.0 - 2.0 * x as f32 / width as f32),
-                        })
-                    }.project(&global_c_particles, &|i|{
+                    c_particle_intersections[x][y] = self.screen_ray(node, x, y, width, height).project(&global_c_particles, &|i|{
                         c_particle_counters[((i.particle as *const CParticle<E, Global>) as usize - (&global_c_particles[0] as *const CParticle<E, Global>) as usize) / Layout::for_value(&global_c_particles[0]).size()] > 0
                     });
                     if let Some(i) = &c_particle_intersections[x][y]{
@@ -384,16 +428,15 @@ pub struct NaiveBVH<T>{
     data: Vec<T>,
 }
 
-impl<E: AsciaEnvironment> NaiveBVH<Polygon<E, Global>>{
-    pub fn from_polygons(polygons: Vec<Polygon<E, Global>>) -> Self{
+impl<T> NaiveBVH<T>{
+    fn build<F: Fn(&T) -> AABB3D>(data: Vec<T>, aabb: F) -> Self{
         let mut tree_width = 1usize;
-        while tree_width < polygons.len(){
+        while tree_width < data.len(){
             tree_width <<= 1;
         }
         let mut tree_buf = vec![None; tree_width << 1];
-        for i in 0..polygons.len(){
-            let p = &polygons[i];
-            tree_buf[tree_width + i] = Some(p.aabb());
+        for i in 0..data.len(){
+            tree_buf[tree_width + i] = Some(aabb(&data[i]));
         }
 
         let mut current_width = tree_width;
@@ -416,20 +459,20 @@ impl<E: AsciaEnvironment> NaiveBVH<Polygon<E, Global>>{
             }
             current_width >>= 1;
         }
-        return NaiveBVH { buf: tree_buf, data:polygons};
+        return NaiveBVH { buf: tree_buf, data: data };
+    }
+}
+
+impl<E: AsciaEnvironment, CO: CoordinateType> NaiveBVH<Polygon<E, CO>>{
+    pub fn from_polygons(polygons: Vec<Polygon<E, CO>>) -> Self{
+        return NaiveBVH::build(polygons, |p| p.aabb());
     }
 }
 
 impl<E:AsciaEnvironment> NaiveBVH<CParticle<E, Global>>{
     pub fn from_c_particles(c_particles: Vec<CParticle<E, Global>>, camera_pos: &Vec3) -> Self{
-        let mut tree_width = 1usize;
-        while tree_width < c_particles.len(){
-            tree_width <<= 1;
-        }
-        let mut tree_buf = vec![None; tree_width << 1];
-        for i in 0..c_particles.len(){
-            let p = &c_particles[i];
-            let aabb = match p.mode {
+        return NaiveBVH::build(c_particles, |p| {
+            match p.mode {
                 CParticleMode::SPHERE => {
                     AABB3D::generate_2(&(p.position - Vec3{
                         x: p.threshold,
@@ -453,35 +496,171 @@ impl<E:AsciaEnvironment> NaiveBVH<CParticle<E, Global>>{
                         z: r,
                     }))
                 }
-            };
-            tree_buf[tree_width + i] = Some(aabb);
+            }
+        });
+    }
+}
+
+impl<E: AsciaEnvironment> NaiveBVH<MeshInstanceProxy<E>>{
+    fn from_mesh_instances(instances: Vec<MeshInstanceProxy<E>>) -> Self{
+        return NaiveBVH::build(instances, |i| i.aabb);
+    }
+}
+
+// one node instancing a mesh, its rays are moved into the mesh's space and traced through the mesh's own tree
+pub struct MeshInstanceProxy<E: AsciaEnvironment + ?Sized + 'static>{
+    placement: Placement,
+    material: Option<E::PolygonMaterials>,
+    bvh: Rc<NaiveBVH<Polygon<E, Local>>>,
+    aabb: AABB3D,
+}
+
+pub struct MeshInstanceRayIntersection<'a, E: AsciaEnvironment + ?Sized + 'static>{
+    pub instance: &'a MeshInstanceProxy<E>,
+    pub polygon: &'a Polygon<E, Local>,
+    pub depth: f32,
+    pub position_on_polygon: Vec2,
+    pub position: Vec3,
+    pub ray: Ray,
+}
+
+impl<'a, E: AsciaEnvironment + ?Sized> RayIntersection for MeshInstanceRayIntersection<'a, E>{
+    fn position(&self) -> Vec3 {
+        return self.position;
+    }
+
+    fn ray(&self) -> Ray {
+        return self.ray;
+    }
+
+    fn depth(&self) -> f32 {
+        return self.depth;
+    }
+}
+
+impl<E: AsciaEnvironment> MeshInstanceProxy<E>{
+    fn lift<'a>(&'a self, i: &PolygonRayIntersection<'a, E, Local>, ray: &Ray, local_ray: &Ray) -> MeshInstanceRayIntersection<'a, E>{
+        return MeshInstanceRayIntersection{
+            instance: self,
+            polygon: i.polygon,
+            depth: i.depth / local_ray.direction.norm() * ray.direction.norm(),
+            position_on_polygon: i.position_on_polygon,
+            position: self.placement.point(&i.position),
+            ray: *ray,
+        };
+    }
+
+    fn world_polygon(&self, polygon: &Polygon<E, Local>) -> Polygon<E, Global>{
+        return self.placement.polygon(polygon, match &self.material {
+            Some(m) => { m.clone() }
+            None => { polygon.material.clone() }
+        });
+    }
+}
+
+impl<E: AsciaEnvironment> RaytracingTarget<0> for MeshInstanceProxy<E>{
+    type Intersection<'a> = MeshInstanceRayIntersection<'a, E>;
+
+    fn project_by<'a, F: Fn(&Self::Intersection<'a>) -> bool>(&'a self, ray: &Ray, exclude_cond: &F) -> Option<Self::Intersection<'a>> {
+        let local_ray = self.placement.inverse_ray(ray)?;
+        return local_ray.project(&*self.bvh, &|i: &PolygonRayIntersection<'a, E, Local>| exclude_cond(&self.lift(i, ray, &local_ray))).map(|i| self.lift(&i, ray, &local_ray));
+    }
+}
+
+impl<'a, E: AsciaEnvironment + ?Sized> Clone for MeshInstanceRayIntersection<'a, E>{
+    fn clone(&self) -> Self {
+        return MeshInstanceRayIntersection{
+            instance: self.instance,
+            polygon: self.polygon,
+            depth: self.depth,
+            position_on_polygon: self.position_on_polygon,
+            position: self.position,
+            ray: self.ray,
         }
+    }
+}
 
-        let mut current_width = tree_width;
-        while current_width > 0 {
-            for i in 0..(current_width >> 1){
-                let parent_index = (current_width >> 1) + i;
-                if let Some(lhs) = tree_buf[current_width + (i << 1)]{
-                    if let Some(rhs) = tree_buf[current_width + (i << 1) + 1]{
-                        tree_buf[parent_index] = Some(AABB3D::concat(&lhs, &rhs));
-                    }
-                    else{
-                        tree_buf[parent_index] = Some(lhs);
-                    }
-                }
-                else{
-                    if let Some(rhs) = tree_buf[current_width + (i << 1) + 1]{
-                        tree_buf[parent_index] = Some(rhs);
-                    }
+enum PolygonHit<'a, E: AsciaEnvironment + 'static>{
+    World(PolygonRayIntersection<'a, E, Global>),
+    Instance(MeshInstanceRayIntersection<'a, E>),
+}
+
+impl<'a, E: AsciaEnvironment> Clone for PolygonHit<'a, E>{
+    fn clone(&self) -> Self {
+        return match self {
+            PolygonHit::World(i) => { PolygonHit::World(i.clone()) }
+            PolygonHit::Instance(i) => { PolygonHit::Instance(i.clone()) }
+        };
+    }
+}
+
+fn trace_polygons<'a, E: AsciaEnvironment>(ray: &Ray, polygons: &'a NaiveBVH<Polygon<E, Global>>, instances: &'a NaiveBVH<MeshInstanceProxy<E>>) -> Option<PolygonHit<'a, E>>{
+    let polygon = ray.project(polygons, &|_|{false});
+    let instance = ray.project(instances, &|_|{false});
+    return match (polygon, instance) {
+        (Some(p), Some(i)) => {
+            if i.depth < p.depth { Some(PolygonHit::Instance(i)) } else { Some(PolygonHit::World(p)) }
+        }
+        (Some(p), None) => { Some(PolygonHit::World(p)) }
+        (None, Some(i)) => { Some(PolygonHit::Instance(i)) }
+        (None, None) => { None }
+    };
+}
+
+// world space copies of the instanced polygons hit in this frame, so that the materials shade them like any other polygon
+struct InstanceHits<E: AsciaEnvironment + 'static>{
+    polygons: Vec<Polygon<E, Global>>,
+    sources: Vec<(*const MeshInstanceProxy<E>, *const Polygon<E, Local>)>,
+}
+
+impl<E: AsciaEnvironment> InstanceHits<E>{
+    fn collect(hits: &[Vec<Option<PolygonHit<E>>>]) -> Self{
+        let mut result = InstanceHits{
+            polygons: vec![],
+            sources: vec![],
+        };
+        for i in hits.iter().flatten().flatten(){
+            if let PolygonHit::Instance(i) = i{
+                result.polygons.push(i.instance.world_polygon(i.polygon));
+                result.sources.push((i.instance as *const MeshInstanceProxy<E>, i.polygon as *const Polygon<E, Local>));
+            }
+        }
+        return result;
+    }
+
+    // hits must be the ones this was collected from
+    fn resolve<'a>(&'a self, hits: Vec<Vec<Option<PolygonHit<'a, E>>>>) -> Vec<Vec<Option<PolygonRayIntersection<'a, E, Global>>>>{
+        let mut k = 0;
+        return hits.into_iter().map(|column| column.into_iter().map(|hit| {
+            match hit {
+                Some(PolygonHit::World(i)) => { Some(i) }
+                Some(PolygonHit::Instance(i)) => {
+                    let polygon = &self.polygons[k];
+                    k += 1;
+                    let position_on_polygon = i.instance.placement.position_on_polygon(&i.position_on_polygon);
+                    Some(PolygonRayIntersection{
+                        polygon: polygon,
+                        depth: i.depth,
+                        position_on_polygon: position_on_polygon,
+                        position: i.position,
+                        ray: i.ray,
+                        normal: polygon.normal_at(&position_on_polygon),
+                    })
                 }
+                None => { None }
             }
-            current_width >>= 1;
+        }).collect()).collect();
+    }
+
+    // the instance and mesh polygon a copy was made from, None for polygons that are not copies
+    fn source(&self, polygon: &Polygon<E, Global>) -> Option<(*const MeshInstanceProxy<E>, *const Polygon<E, Local>)>{
+        if !self.polygons.as_ptr_range().contains(&(polygon as *const Polygon<E, Global>)){
+            return None;
         }
-        return NaiveBVH { buf: tree_buf, data: c_particles };
+        return Some(self.sources[(polygon as *const Polygon<E, Global> as usize - self.polygons.as_ptr() as usize) / Layout::for_value(polygon).size()]);
     }
 }
 
-
 impl<T> RaytracingTarget<1> for NaiveBVH<T> where T : RaytracingTarget<0> {
     type Intersection<'a> = <T as RaytracingTarget<0>>::Intersection<'a> where T: 'a;
     fn project_by<'a, F:Fn(&Self::Intersection<'a>) -> bool>(&'a self, ray: &Ray, f: &F) -> Option<Self::Intersection<'a>> {
@@ -537,11 +716,23 @@ impl<T> RaytracingTarget<1> for NaiveBVH<T> where T : RaytracingTarget<0> {
     }
 }
 
+type MeshBVHTrees<E> = Vec<(Rc<Mesh<E>>, Rc<NaiveBVH<Polygon<E, Local>>>)>;
+
 pub struct SimpleBVHCamera<E: AsciaEnvironment>{
     pub angle_of_view: (f32, f32),
     pub sampling_size: u32,
+    // camera rays skip nodes outside of the view, shadow rays still hit them
+    pub frustum_culling: bool,
+    // only nodes on one of these layers are rendered and only lights on one of them are applied
+    pub layer_mask: u32,
     polygons_bvh_tree: RefCell<NaiveBVH<Polygon<E, Global>>>,
     c_particles_bvh_tree: RefCell<NaiveBVH<CParticle<E, Global>>>,
+    mesh_instances_bvh_tree: RefCell<NaiveBVH<MeshInstanceProxy<E>>>,
+    out_of_view_polygons_bvh_tree: RefCell<NaiveBVH<Polygon<E, Global>>>,
+    out_of_view_mesh_instances_bvh_tree: RefCell<NaiveBVH<MeshInstanceProxy<E>>>,
+    // one tree per instanced mesh, kept while some visible node still instances the mesh
+    mesh_bvh_trees: RefCell<MeshBVHTrees<E>>,
+    instance_hits: RefCell<InstanceHits<E>>,
 }
 
 impl<E: AsciaEnvironment> Default for SimpleBVHCamera<E>{
@@ -549,8 +740,18 @@ impl<E: AsciaEnvironment> Default for SimpleBVHCamera<E>{
         return SimpleBVHCamera{
             angle_of_view: (PI / 3.0,PI / 4.0),
             sampling_size: 1,
+            frustum_culling: true,
+            layer_mask: ALL_LAYERS,
             polygons_bvh_tree: RefCell::new(NaiveBVH::from_polygons(vec![])),
             c_particles_bvh_tree: RefCell::new(NaiveBVH::from_c_particles(vec![], &Default::default())),
+            mesh_instances_bvh_tree: RefCell::new(NaiveBVH::from_mesh_instances(vec![])),
+            out_of_view_polygons_bvh_tree: RefCell::new(NaiveBVH::from_polygons(vec![])),
+            out_of_view_mesh_instances_bvh_tree: RefCell::new(NaiveBVH::from_mesh_instances(vec![])),
+            mesh_bvh_trees: RefCell::new(vec![]),
+            instance_hits: RefCell::new(InstanceHits{
+                polygons: vec![],
+                sources: vec![],
+            }),
         }
     }
 }
@@ -560,8 +761,18 @@ impl<E: AsciaEnvironment> SimpleBVHCamera<E>{
         return SimpleBVHCamera{
             angle_of_view: angle_of_view,
             sampling_size: sampling_size,
+            frustum_culling: true,
+            layer_mask: ALL_LAYERS,
             polygons_bvh_tree: RefCell::new(NaiveBVH::from_polygons(vec![])),
             c_particles_bvh_tree: RefCell::new(NaiveBVH::from_c_particles(vec![], &Default::default())),
+            mesh_instances_bvh_tree: RefCell::new(NaiveBVH::from_mesh_instances(vec![])),
+            out_of_view_polygons_bvh_tree: RefCell::new(NaiveBVH::from_polygons(vec![])),
+            out_of_view_mesh_instances_bvh_tree: RefCell::new(NaiveBVH::from_mesh_instances(vec![])),
+            mesh_bvh_trees: RefCell::new(vec![]),
+            instance_hits: RefCell::new(InstanceHits{
+                polygons: vec![],
+                sources: vec![],
+            }),
         }
     }
 }
@@ -577,15 +788,18 @@ impl<E: AsciaEnvironment<ObjectNodeAttributes = PresetObjectNodeAttributeDispatc
 
 impl<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMaterials=PresetCParticleMaterial, Lights = PresetLight>> MaterialDispatcher<E, Polygon<E, Global>> for SimpleBVHCamera<E>{
     fn calc_color(&self, intersection: &PolygonRayIntersection<E, Global>, engine: &AsciaEngine<E>, camera_node: &ObjectNode<E, Global>, global_polygons: &Vec<Polygon<E, Global>>) -> (ColorRGBf32, u32) {
-        match intersection.polygon.material {
+        match &intersection.polygon.material {
             PresetPolygonMaterial::FlatMaterial(m) => {
-                <_ as Material<E, SimpleBVHCamera<E>, Polygon<E, Global>>>::calc_color(&(&m), intersection, engine, self, camera_node, global_polygons)
+                <_ as Material<E, SimpleBVHCamera<E>, Polygon<E, Global>>>::calc_color(&m, intersection, engine, self, camera_node, global_polygons)
             }
             PresetPolygonMaterial::LambertMaterial(m) => {
-                <_ as Material<E, SimpleBVHCamera<E>, Polygon<E, Global>>>::calc_color(&(&m), intersection, engine, self, camera_node, global_polygons)
+                <_ as Material<E, SimpleBVHCamera<E>, Polygon<E, Global>>>::calc_color(&m, intersection, engine, self, camera_node, global_polygons)
             }
             PresetPolygonMaterial::LambertWithShadowMaterial(m) => {
-                <_ as Material<E, SimpleBVHCamera<E>, Polygon<E, Global>>>::calc_color(&(&m), intersection, engine, self, camera_node, global_polygons)
+                <_ as Material<E, SimpleBVHCamera<E>, Polygon<E, Global>>>::calc_color(&m, intersection, engine, self, camera_node, global_polygons)
+            }
+            PresetPolygonMaterial::TexturedMaterial(m) => {
+                <_ as Material<E, SimpleBVHCamera<E>, Polygon<E, Global>>>::calc_color(&m, intersection, engine, self, camera_node, global_polygons)
             }
         }
     }
@@ -607,48 +821,82 @@ impl<E: AsciaEnvironment<PolygonMaterials = PresetPolygonMaterial, CParticleMate
     }
 }
 impl<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMaterials=PresetCParticleMaterial, Lights = PresetLight>> Camera<E> for SimpleBVHCamera<E>{
+    fn screen_ray(&self, camera_node: &ObjectNode<E, Global>, x: usize, y: usize, width: usize, height: usize) -> Ray{
+        return perspective_ray(&self.angle_of_view, camera_node, x, y, width, height);
+    }
+
+    fn layer_mask(&self) -> u32{
+        return self.layer_mask;
+    }
+
     fn render(&self, node: &ObjectNode<E, Global>, engine: &AsciaEngine<E>) -> Vec<Vec<RenderChar>> {
         let height = engine.viewport.borrow().height();
         let width = engine.viewport.borrow().width();
 
         let mut output:Vec<Vec<RenderChar>> = vec![vec![RenderChar::default();width];height];
-        let mut polygon_intersections:Vec<Vec<Option<PolygonRayIntersection<E, Global>>>> = vec![vec![None;self.sampling_size as usize * height];self.sampling_size as usize * width];
+        let mut polygon_hits:Vec<Vec<Option<PolygonHit<E>>>> = vec![vec![None;self.sampling_size as usize * height];self.sampling_size as usize * width];
         let mut c_particle_intersections:Vec<Vec<Option<CParticleRayIntersection<E, Global>>>> = vec![vec![None; self.sampling_size as usize * height]; self.sampling_size as usize * width];
 
         let mut global_polygons = vec![];
         let mut global_c_particles = vec![];
-        for iter in engine.genesis_global.iter(){
+        let mut mesh_instances = vec![];
+        let mut mesh_bvh_trees = self.mesh_bvh_trees.borrow_mut();
+        let mut used = vec![false; mesh_bvh_trees.len()];
+        let mut instance = |iter: &ObjectNode<E, Global>| -> Option<MeshInstanceProxy<E>> {
+            let m = iter.mesh.as_ref()?;
+            let index = match mesh_bvh_trees.iter().position(|(mesh, _)| Rc::ptr_eq(mesh, &m.mesh)) {
+                Some(i) => { i }
+                None => {
+                    mesh_bvh_trees.push((m.mesh.clone(), Rc::new(NaiveBVH::from_polygons(m.mesh.polygons().to_vec()))));
+                    used.push(false);
+                    mesh_bvh_trees.len() - 1
+                }
+            };
+            used[index] = true;
+            return m.mesh.bounds().map(|aabb| MeshInstanceProxy{
+                placement: iter.placement(),
+                material: m.material.clone(),
+                bvh: mesh_bvh_trees[index].1.clone(),
+                aabb: iter.placement().aabb(&aabb),
+            });
+        };
+        let (in_view, out_of_view) = visible_nodes(engine, node, self.angle_of_view, self.frustum_culling, self.layer_mask);
+        for iter in in_view{
             global_polygons.extend(iter.polygons.clone());
             global_c_particles.extend(iter.c_particles.clone());
+            mesh_instances.extend(instance(iter));
+        }
+        // camera rays only traverse the trees of the nodes in view, the rest are kept for the shadow rays
+        let mut out_of_view_polygons = vec![];
+        let mut out_of_view_mesh_instances = vec![];
+        for iter in out_of_view{
+            out_of_view_polygons.extend(iter.polygons.clone());
+            out_of_view_mesh_instances.extend(instance(iter));
         }
+        let mut i = 0;
+        mesh_bvh_trees.retain(|_| {
+            i += 1;
+            return used[i - 1];
+        });
+        drop(mesh_bvh_trees);
 
         let mut c_particle_counters = vec![0u32; global_c_particles.len()];
 
         *self.polygons_bvh_tree.borrow_mut() = NaiveBVH::from_polygons(global_polygons);
         *self.c_particles_bvh_tree.borrow_mut() = NaiveBVH::from_c_particles(global_c_particles, &node.position);
+        *self.mesh_instances_bvh_tree.borrow_mut() = NaiveBVH::from_mesh_instances(mesh_instances);
+        *self.out_of_view_polygons_bvh_tree.borrow_mut() = NaiveBVH::from_polygons(out_of_view_polygons);
+        *self.out_of_view_mesh_instances_bvh_tree.borrow_mut() = NaiveBVH::from_mesh_instances(out_of_view_mesh_instances);
         
         let polygons_bvh_tree = self.polygons_bvh_tree.borrow();
         let c_particles_bvh_tree = self.c_particles_bvh_tree.borrow();
+        let mesh_instances_bvh_tree = self.mesh_instances_bvh_tree.borrow();
 
         if self.sampling_size == 1{
             for x in 0..width{
                 for y in 0..height{
-                    polygon_intersections[x][y] = Ray{
-                        position: node.position,
-                        direction: node.direction.rotate(&Vec3{
-                            x:1.0,
-                            y:f32::tan(self.angle_of_view.1 * 0.5) * (1.0 - 2.0 * y as f32 / height as f32),
-                            z:f32::tan(self.angle_of_view.0 * 0.5) * (1.0 - 2.0 * x as f32 / width as f32),
-                        })
-                    }.project(&*polygons_bvh_tree, &|_|{false});
-                    c_particle_intersections[x][y] = Ray{
-                        position: node.position,
-                        direction: node.direction.rotate(&Vec3{
-                            x:1.0,
-                            y:f32::tan(self.angle_of_view.1 * 0.5) * (1.0 - 2.0 * y as f32 / height as f32),
-                            z:f32::tan(self.angle_of_view.0 * 0.5) * (1.0 - 2.0 * x as f32 / width as f32),
-                        })
-                    }.project(&*c_particles_bvh_tree, &|i|{
+                    polygon_hits[x][y] = trace_polygons(&self.screen_ray(node, x, y, width, height), &polygons_bvh_tree, &mesh_instances_bvh_tree);
+                    c_particle_intersections[x][y] = self.screen_ray(node, x, y, width, height).project(&*c_particles_bvh_tree, &|i|{
                         c_particle_counters[((i.particle as *const CParticle<E, Global>) as usize - (&c_particles_bvh_tree.data[0] as *const CParticle<E, Global>) as usize) / Layout::for_value(&c_particles_bvh_tree.data[0]).size()] > 0
                     });
                     if let Some(i) = &c_particle_intersections[x][y]{
@@ -657,6 +905,10 @@ impl<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMateri
                 }
             }
 
+            *self.instance_hits.borrow_mut() = InstanceHits::collect(&polygon_hits);
+            let instance_hits = self.instance_hits.borrow();
+            let polygon_intersections = instance_hits.resolve(polygon_hits);
+
             for x in 0..width{
                 for y in 0..height{
                     let mut depth = f32::MAX;
@@ -682,24 +934,10 @@ impl<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMateri
                             if i == 1 && j == 1{
                                 continue;
                             }
-                            polygon_intersections[x * 3 + j][y * 3 + i] = Ray{
-                                position: node.position,
-                                direction: node.direction.rotate(&Vec3{
-                                    x:1.0,
-                                    y:f32::tan(self.angle_of_view.1 * 0.5) * (1.0 - 2.0 * (y * 3 + i) as f32 / (height * 3) as f32),
-                                    z:f32::tan(self.angle_of_view.0 * 0.5) * (1.0 - 2.0 * (x * 3 + j) as f32 / (width * 3) as f32),
-                                }),
-                            }.project(&*polygons_bvh_tree, &|_|{false});
+                            polygon_hits[x * 3 + j][y * 3 + i] = trace_polygons(&self.screen_ray(node, x * 3 + j, y * 3 + i, width * 3, height * 3), &polygons_bvh_tree, &mesh_instances_bvh_tree);
                         }
                     }
-                    c_particle_intersections[x][y] = Ray{
-                        position: node.position,
-                        direction: node.direction.rotate(&Vec3{
-                            x:1.0,
-                            y:f32::tan(self.angle_of_view.1 * 0.5) * (1.0 - 2.0 * y as f32 / height as f32),
-                            z:f32::tan(self.angle_of_view.0 * 0.5) * (1.0 - 2.0 * x as f32 / width as f32),
-                        })
-                    }.project(&*c_particles_bvh_tree, &|i|{
+                    c_particle_intersections[x][y] = self.screen_ray(node, x, y, width, height).project(&*c_particles_bvh_tree, &|i|{
                         c_particle_counters[((i.particle as *const CParticle<E, Global>) as usize - (&c_particles_bvh_tree.data[0] as *const CParticle<E, Global>) as usize) / Layout::for_value(&c_particles_bvh_tree.data[0]).size()] > 0
                     });
                     if let Some(i) = &c_particle_intersections[x][y]{
@@ -708,6 +946,10 @@ impl<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMateri
                 }
             }
 
+            *self.instance_hits.borrow_mut() = InstanceHits::collect(&polygon_hits);
+            let instance_hits = self.instance_hits.borrow();
+            let polygon_intersections = instance_hits.resolve(polygon_hits);
+
             for x in 0..width{
                 for y in 0..height{
                     let mut material_results:[(ColorRGBf32, u32);8] = [(Default::default(),0);8];
@@ -776,4 +1018,79 @@ impl<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMateri
         }
         return output;
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests{
+    use std::f32::consts::PI;
+    use crate::ascia::camera::{visible_nodes, SimpleBVHCamera, SimpleCamera};
+    use crate::ascia::core::{ALL_LAYERS, AsciaEngine, Camera, LambertWithShadowMaterial, ObjectNode, ObjectNodeAttributeDispatcher, PresetAsciaEnvironment, PresetObjectNodeAttributeDispatcher, PresetPolygonMaterial};
+    use crate::ascia::lights::PointLight;
+    use crate::ascia::math::Vec3;
+    use crate::ascia::primitives::PrimitiveGenerator;
+
+    #[test]
+    fn test_visible_nodes(){
+        let mut engine = AsciaEngine::<PresetAsciaEnvironment>::new(4, 4);
+        let root = engine.genesis_local.root();
+        let camera = engine.genesis_local.add_child(root, ObjectNode::new("camera")).unwrap();
+        for (tag, x, layers) in [("ahead", 20.0, ALL_LAYERS), ("behind", -20.0, ALL_LAYERS), ("other layer", 20.0, 0b10)]{
+            let mut cube = ObjectNode::from(tag, PrimitiveGenerator::cube(2.0, PresetPolygonMaterial::default()));
+            cube.position = Vec3{ x: x, y: 0.0, z: 0.0 };
+            cube.layers = layers;
+            engine.genesis_local.add_child(root, cube).unwrap();
+        }
+        engine.update_global_nodes();
+        let camera_node = engine.genesis_global.node(camera).unwrap();
+        let tags = |frustum_culling: bool| -> Vec<String> {
+            visible_nodes(&engine, camera_node, (PI / 2.0, PI / 2.0), frustum_culling, 0b01).0.into_iter().map(|n| n.tag.clone()).collect()
+        };
+
+        // the camera looks along +x, so culling drops the cube behind it and keeps the one in view
+        assert!(tags(true).contains(&"ahead".to_string()));
+        assert!(!tags(true).contains(&"behind".to_string()));
+        assert!(tags(false).contains(&"behind".to_string()));
+        // but keeps it for the shadow rays
+        assert!(visible_nodes(&engine, camera_node, (PI / 2.0, PI / 2.0), true, 0b01).1.iter().any(|n| n.tag == "behind"));
+        assert!(!tags(true).contains(&"other layer".to_string()));
+        assert!(!tags(false).contains(&"other layer".to_string()));
+    }
+
+    // a wall in front of the camera lit from behind it, with an occluder between the light and the wall that is out of view
+    fn render_shadowed_wall<C: Camera<PresetAsciaEnvironment>>(camera: &C) -> Vec<Vec<(u8, u8, u8)>>{
+        let mut engine = AsciaEngine::<PresetAsciaEnvironment>::new(8, 8);
+        let root = engine.genesis_local.root();
+        let camera_node = engine.genesis_local.add_child(root, ObjectNode::new("camera")).unwrap();
+        let material = PresetPolygonMaterial::LambertWithShadowMaterial(LambertWithShadowMaterial::default());
+        let mut wall = ObjectNode::from("wall", PrimitiveGenerator::cube(40.0, material.clone()));
+        wall.position = Vec3{ x: 30.0, y: 0.0, z: 0.0 };
+        engine.genesis_local.add_child(root, wall).unwrap();
+        let mut occluder = ObjectNode::from("occluder", PrimitiveGenerator::cube(2.0, material));
+        occluder.position = Vec3{ x: -5.0, y: 0.0, z: 0.0 };
+        engine.genesis_local.add_child(root, occluder).unwrap();
+        let mut light = ObjectNode::new("light");
+        light.position = Vec3{ x: -10.0, y: 0.0, z: 0.0 };
+        light.attribute = PresetObjectNodeAttributeDispatcher::from(PointLight::default()).make_shared();
+        engine.genesis_local.add_child(root, light).unwrap();
+        engine.update_global_nodes();
+        let output = camera.render(engine.genesis_global.node(camera_node).unwrap(), &engine);
+        return output.iter().map(|row| row.iter().map(|c| (c.color.r, c.color.g, c.color.b)).collect()).collect();
+    }
+
+    #[test]
+    fn test_culled_nodes_cast_shadows(){
+        let mut camera = SimpleCamera::new((PI / 2.0, PI / 2.0), 1);
+        let culled = render_shadowed_wall(&camera);
+        camera.frustum_culling = false;
+        assert_eq!(culled, render_shadowed_wall(&camera));
+        // the middle of the wall is in the occluder's shadow, the corners are lit
+        assert_eq!(culled[4][4], (0, 0, 0));
+        assert_ne!(culled[0][0], (0, 0, 0));
+
+        let mut bvh_camera = SimpleBVHCamera::new((PI / 2.0, PI / 2.0), 1);
+        let culled = render_shadowed_wall(&bvh_camera);
+        bvh_camera.frustum_culling = false;
+        assert_eq!(culled, render_shadowed_wall(&bvh_camera));
+        assert_eq!(culled[4][4], (0, 0, 0));
+        assert_ne!(culled[0][0], (0, 0, 0));
+    }
+}
@@ -4,10 +4,10 @@ use std::marker::PhantomData;
 use std::mem;
 use wgpu::{BindGroupDescriptor, BindGroupEntry, BufferDescriptor, Device, Queue, ShaderModule, ShaderModuleDescriptor};
 use wgpu::util::{BufferInitDescriptor, DeviceExt};
-use crate::ascia::camera::{SimpleBVHCamera, SimpleCamera};
+use crate::ascia::camera::{visible_nodes, SimpleBVHCamera, SimpleCamera};
 use crate::ascia::charmapper::CHARMAP3X3;
 use crate::ascia::color::{ColorRGBf32, ColorRGBu8};
-use crate::ascia::core::{AsciaEngine, Camera, CParticle, FlatMaterial, Global, LambertMaterial, LambertWithShadowMaterial, ObjectNode, Polygon, PresetLight, PresetPolygonMaterial, RenderChar, ObjectNodeAttribute, PresetCamera, AsciaEnvironment, PresetObjectNodeAttributeDispatcher, CameraDispatcher, ObjectNodeAttributeDispatcher, PresetCParticleMaterial};
+use crate::ascia::core::{AsciaEngine, Camera, CParticle, FlatMaterial, Global, LambertMaterial, LambertWithShadowMaterial, ObjectNode, Polygon, PresetLight, PresetPolygonMaterial, Ray, RenderChar, ObjectNodeAttribute, PresetCamera, AsciaEnvironment, PresetObjectNodeAttributeDispatcher, CameraDispatcher, ObjectNodeAttributeDispatcher, PresetCParticleMaterial};
 use crate::ascia::core::CParticleMode::SPHERE;
 use crate::ascia::lights::PointLight;
 use crate::ascia::math::{Matrix33, Quaternion, Vec3, Vec4};
@@ -143,6 +143,12 @@ impl GPUMemoryConvertStatic<20> for PresetPolygonMaterial {
             PresetPolygonMaterial::LambertWithShadowMaterial(m) => {
                 buf[0..20].copy_from_slice(&m.convert());
             }
+            // the shaders have no textures, so textured polygons take the average color of theirs
+            PresetPolygonMaterial::TexturedMaterial(m) => {
+                buf[0..12].copy_from_slice(&m.texture.average().convert());
+                buf[12..16].copy_from_slice(&(if m.lit { 1u32 } else { 0u32 }).convert());
+                buf[16..20].copy_from_slice(&m.priority.convert());
+            }
         }
         return buf;
     }
@@ -167,12 +173,19 @@ impl GPUMemoryConvertStatic<12> for PresetCParticleMaterial {
     }
 }
 
-impl<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial>> GPUMemoryConvertStatic<80> for Polygon<E, Global> {
+impl<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial>> GPUMemoryConvertStatic<128> for Polygon<E, Global> {
     #[inline]
-    fn convert(&self) -> [u8; 80] {
-        let mut buf: [u8; 80] = [0; 80];
+    fn convert(&self) -> [u8; 128] {
+        let mut buf: [u8; 128] = [0; 128];
         buf[0..48].copy_from_slice(&self.poses.convert());
         buf[48..68].copy_from_slice(&self.material.convert());
+        // flat polygons repeat their face normal so that the shader always interpolates
+        let n = self.normal();
+        buf[80..128].copy_from_slice(&self.normals.unwrap_or(Matrix33{
+            v1: n,
+            v2: n,
+            v3: n,
+        }).convert());
         return buf;
     }
 }
@@ -253,13 +266,14 @@ struct RaytracingSetting {
     sampling_size: u32,
     sampling_threshold: u32,
     render_range_radius: f32,
+    in_view_polygons: u32,
 }
 
 
-impl GPUMemoryConvertStatic<64> for RaytracingSetting {
+impl GPUMemoryConvertStatic<80> for RaytracingSetting {
     #[inline]
-    fn convert(&self) -> [u8; 64] {
-        let mut buf: [u8; 64] = [0; 64];
+    fn convert(&self) -> [u8; 80] {
+        let mut buf: [u8; 80] = [0; 80];
         buf[0..12].copy_from_slice(&self.camera_position.convert());
         buf[16..32].copy_from_slice(&self.camera_direction.convert());
         buf[32..36].copy_from_slice(&self.screen_width.convert());
@@ -270,6 +284,7 @@ impl GPUMemoryConvertStatic<64> for RaytracingSetting {
         buf[52..56].copy_from_slice(&self.sampling_size.convert());
         buf[56..60].copy_from_slice(&self.sampling_threshold.convert());
         buf[60..64].copy_from_slice(&self.render_range_radius.convert());
+        buf[64..68].copy_from_slice(&self.in_view_polygons.convert());
         return buf;
     }
 }
@@ -319,6 +334,15 @@ impl<E: AsciaEnvironment<ObjectNodeAttributes=PresetObjectNodeAttributeDispatche
 }
 
 impl<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMaterials=PresetCParticleMaterial, Lights=PresetLight>> Camera<E> for GPUWrapper<E, SimpleCamera<E>> {
+    // the shaders build their rays the same way as the cpu camera
+    fn screen_ray(&self, camera_node: &ObjectNode<E, Global>, x: usize, y: usize, width: usize, height: usize) -> Ray{
+        return self.cpu_camera.screen_ray(camera_node, x, y, width, height);
+    }
+
+    fn layer_mask(&self) -> u32{
+        return self.cpu_camera.layer_mask;
+    }
+
     fn render(&self, node: &ObjectNode<E, Global>, engine: &AsciaEngine<E>) -> Vec<Vec<RenderChar>> {
         let device = &self.daq.0;
         let calc_intersections_polygons_1x_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
@@ -359,9 +383,19 @@ impl<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMateri
         let mut polygons = vec![];
         let mut c_particles = vec![];
         let mut pointlights = vec![];
-        for iter in engine.genesis_global.iter() {
+        let (in_view, out_of_view) = visible_nodes(engine, node, self.cpu_camera.angle_of_view, self.cpu_camera.frustum_culling, self.cpu_camera.layer_mask);
+        for iter in &in_view {
             polygons.extend(iter.polygons.clone());
+            polygons.extend(iter.mesh_polygons());
             c_particles.extend(iter.c_particles.clone());
+        }
+        // the shader casts camera rays against the first in_view_polygons polygons and shadow rays against all of them
+        let in_view_polygons = polygons.len() as u32;
+        for iter in &out_of_view {
+            polygons.extend(iter.polygons.clone());
+            polygons.extend(iter.mesh_polygons());
+        }
+        for iter in in_view.iter().chain(out_of_view.iter()) {
             let attr_rr = RefCell::borrow(&iter.attribute);
             if let Some(s) = &*attr_rr {
                 if let Some(PresetLight::PointLight(p)) = &s.light() {
@@ -411,6 +445,7 @@ impl<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMateri
                 sampling_size: self.cpu_camera.sampling_size,
                 sampling_threshold: 0,
                 render_range_radius: 10000.0,
+                in_view_polygons: in_view_polygons,
             }.convert(),
             usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
         });
@@ -738,6 +773,15 @@ impl<E: AsciaEnvironment<ObjectNodeAttributes=PresetObjectNodeAttributeDispatche
 }
 
 impl<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMaterials=PresetCParticleMaterial, Lights=PresetLight>> Camera<E> for GPUWrapper<E, SimpleBVHCamera<E>> {
+    // the shaders build their rays the same way as the cpu camera
+    fn screen_ray(&self, camera_node: &ObjectNode<E, Global>, x: usize, y: usize, width: usize, height: usize) -> Ray{
+        return self.cpu_camera.screen_ray(camera_node, x, y, width, height);
+    }
+
+    fn layer_mask(&self) -> u32{
+        return self.cpu_camera.layer_mask;
+    }
+
     fn render(&self, node: &ObjectNode<E, Global>, engine: &AsciaEngine<E>) -> Vec<Vec<RenderChar>> {
         let device = &self.daq.0;
         let build_bvh_polygons_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
@@ -790,9 +834,19 @@ impl<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMateri
         let mut polygons = vec![];
         let mut c_particles = vec![];
         let mut pointlights = vec![];
-        for iter in engine.genesis_global.iter() {
+        let (in_view, out_of_view) = visible_nodes(engine, node, self.cpu_camera.angle_of_view, self.cpu_camera.frustum_culling, self.cpu_camera.layer_mask);
+        for iter in &in_view {
             polygons.extend(iter.polygons.clone());
+            polygons.extend(iter.mesh_polygons());
             c_particles.extend(iter.c_particles.clone());
+        }
+        // the shader casts camera rays against the first in_view_polygons polygons and shadow rays against all of them
+        let in_view_polygons = polygons.len() as u32;
+        for iter in &out_of_view {
+            polygons.extend(iter.polygons.clone());
+            polygons.extend(iter.mesh_polygons());
+        }
+        for iter in in_view.iter().chain(out_of_view.iter()) {
             let attr_rr = RefCell::borrow(&iter.attribute);
             if let Some(s) = &*attr_rr {
                 if let Some(PresetLight::PointLight(p)) = &s.light() {
@@ -842,6 +896,7 @@ impl<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMateri
                     sampling_size: self.cpu_camera.sampling_size,
                     sampling_threshold: 0,
                     render_range_radius: 10000.0,
+                    in_view_polygons: in_view_polygons,
                 }.convert(),
                 usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
             });
@@ -1,3 +1,6 @@
+#[cfg(feature = "export")]
+use serde::{Deserialize, Serialize};
+
 #[derive(Debug, Copy, Clone)]
 pub struct ColorANSI256 {
     pub data: u8,
@@ -95,6 +98,7 @@ impl From<ColorRGBf32> for ColorRGBu8 {
 
 #[derive(Debug, Copy, Clone)]
 #[repr(C)]
+#[cfg_attr(feature = "export", derive(Serialize, Deserialize))]
 pub struct ColorRGBf32 {
     pub r: f32,
     pub g: f32,
@@ -1,17 +1,21 @@
 use std::cell::{RefCell};
-use std::collections::{HashMap, VecDeque};
 use std::io::{BufWriter, stdout, StdoutLock, Write};
 use std::marker::PhantomData;
 use std::rc::{Rc};
 use std::time::{Duration, Instant};
+use crate::ascia::animation::{AnimationClip, AnimationMode, AnimationState, Animator};
 use crate::ascia::camera::{SimpleBVHCamera, SimpleCamera};
 use crate::ascia::color::{ColorANSI256, ColorRGBf32, ColorRGBu8};
 use crate::ascia::lights::PointLight;
-use crate::ascia::math::{AABB3D, Matrix33, Quaternion, Vec2, Vec3};
+use crate::ascia::math::{AABB3D, Frustum, Matrix33, Matrix44, Quaternion, Vec2, Vec3, Vec4};
+use crate::ascia::texture::{Texture, TextureFilter, TextureWrap};
 
 #[cfg(feature = "wgpu")]
 use crate::ascia::camera_wgpu::GPUWrapper;
 
+#[cfg(feature = "export")]
+use serde::{Deserialize, Serialize};
+
 pub trait AsciaEnvironment where Self: 'static{
     type PolygonMaterials: MaterialCollection<Polygon<Self, Global>> + Clone;
     type CParticleMaterials: MaterialCollection<CParticle<Self, Global>> + Clone;
@@ -38,12 +42,18 @@ pub trait MaterialDispatcher<E: AsciaEnvironment + ?Sized, RT: RaytracingTarget<
 
 pub trait Camera<E: AsciaEnvironment + ?Sized>: ObjectNodeAttribute<E> {
     fn render(&self, camera_node: &ObjectNode<E, Global>, engine: &AsciaEngine<E>) -> Vec<Vec<RenderChar>>;
+    fn screen_ray(&self, camera_node: &ObjectNode<E, Global>, x: usize, y: usize, width: usize, height: usize) -> Ray;
+    // the layers this camera renders, lights outside of them do not light its view either
+    fn layer_mask(&self) -> u32{
+        ALL_LAYERS
+    }
     fn make_camera_dispatcher(self) -> E::Cameras where E::Cameras: From<Self>, Self: Sized{
         E::Cameras::from(self)
     }
 }
 pub trait CameraDispatcher<E: AsciaEnvironment + ?Sized>: Default{
     fn render(&self, camera_node: &ObjectNode<E, Global>, engine: &AsciaEngine<E>) -> Vec<Vec<RenderChar>>;
+    fn screen_ray(&self, camera_node: &ObjectNode<E, Global>, x: usize, y: usize, width: usize, height: usize) -> Ray;
     fn make_attribute_enum(self) -> E::ObjectNodeAttributes where E::ObjectNodeAttributes: From<Self>, Self:Sized{
         E::ObjectNodeAttributes::from(self)
     }
@@ -71,11 +81,13 @@ pub trait ObjectNodeAttributeDispatcher<E: AsciaEnvironment + ?Sized>{
 
 pub struct PresetAsciaEnvironment{}
 
-#[derive(Copy, Clone)]
+#[derive(Clone)]
+#[cfg_attr(feature = "export", derive(Serialize, Deserialize))]
 pub enum PresetPolygonMaterial {
     FlatMaterial(FlatMaterial),
     LambertMaterial(LambertMaterial),
     LambertWithShadowMaterial(LambertWithShadowMaterial),
+    TexturedMaterial(TexturedMaterial),
 }
 
 impl Default for PresetPolygonMaterial {
@@ -114,6 +126,13 @@ impl<E: AsciaEnvironment<Cameras = PresetCamera<E>, PolygonMaterials = PresetPol
             PresetCamera::SimpleBVHCamera(c) => {c.render(camera_node, engine)}
         }
     }
+
+    fn screen_ray(&self, camera_node: &ObjectNode<E, Global>, x: usize, y: usize, width: usize, height: usize) -> Ray{
+        match self {
+            PresetCamera::SimpleCamera(c) => {c.screen_ray(camera_node, x, y, width, height)}
+            PresetCamera::SimpleBVHCamera(c) => {c.screen_ray(camera_node, x, y, width, height)}
+        }
+    }
 }
 
 #[cfg(feature = "wgpu")]
@@ -126,8 +145,18 @@ impl<E: AsciaEnvironment<Cameras = PresetCamera<E>, PolygonMaterials = PresetPol
             PresetCamera::SimpleBVHCameraGPU(c) => {c.render(camera_node, engine)}
         }
     }
+
+    fn screen_ray(&self, camera_node: &ObjectNode<E, Global>, x: usize, y: usize, width: usize, height: usize) -> Ray{
+        match self {
+            PresetCamera::SimpleCamera(c) => {c.screen_ray(camera_node, x, y, width, height)}
+            PresetCamera::SimpleBVHCamera(c) => {c.screen_ray(camera_node, x, y, width, height)}
+            PresetCamera::SimpleCameraGPU(c) => {c.screen_ray(camera_node, x, y, width, height)}
+            PresetCamera::SimpleBVHCameraGPU(c) => {c.screen_ray(camera_node, x, y, width, height)}
+        }
+    }
 }
 
+#[cfg_attr(feature = "export", derive(Serialize, Deserialize))]
 pub enum PresetLight{
     PointLight(PointLight)
 }
@@ -188,6 +217,7 @@ impl<E:AsciaEnvironment> ObjectNodeAttributeDispatcher<E> for PresetObjectNodeAt
 }
 
 #[derive(Copy, Clone)]
+#[cfg_attr(feature = "export", derive(Serialize, Deserialize))]
 pub enum PresetCParticleMaterial {
     FlatMaterial(FlatMaterial),
     LambertMaterial(LambertMaterial),
@@ -221,6 +251,7 @@ pub struct Global{}
 impl CoordinateType for Global{}
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "export", derive(Serialize, Deserialize))]
 pub struct FlatMaterial{
     pub color: ColorRGBf32,
     pub priority: u32,
@@ -240,6 +271,7 @@ impl Default for FlatMaterial{
 }
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "export", derive(Serialize, Deserialize))]
 pub struct LambertMaterial{
     pub color: ColorRGBf32,
     pub priority: u32,
@@ -259,6 +291,7 @@ impl Default for LambertMaterial{
 }
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "export", derive(Serialize, Deserialize))]
 pub struct LambertWithShadowMaterial{
     pub color: ColorRGBf32,
     pub priority: u32,
@@ -276,6 +309,37 @@ impl Default for LambertWithShadowMaterial{
     }
 }
 
+// samples the texture at the uvs of the polygon, polygons without uvs take the texture's average color
+// lit textures are shaded like LambertMaterial with the sampled color
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "export", derive(Serialize, Deserialize))]
+pub struct TexturedMaterial{
+    pub texture: Rc<Texture>,
+    pub filter: TextureFilter,
+    pub wrap: TextureWrap,
+    pub lit: bool,
+    pub priority: u32,
+}
+
+impl TexturedMaterial{
+    pub fn new(texture: Rc<Texture>) -> Self{
+        return TexturedMaterial{
+            texture: texture,
+            filter: TextureFilter::Bilinear,
+            wrap: TextureWrap::Repeat,
+            lit: true,
+            priority: 0,
+        };
+    }
+
+    pub fn color_at<E: AsciaEnvironment + ?Sized, CO: CoordinateType>(&self, polygon: &Polygon<E, CO>, position_on_polygon: &Vec2) -> ColorRGBf32{
+        return match polygon.uv_at(position_on_polygon) {
+            Some(uv) => { self.texture.sample(&uv, self.filter, self.wrap) }
+            None => { self.texture.average() }
+        };
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct Ray{
     pub position:Vec3, // TODO CoordinateType
@@ -283,7 +347,7 @@ pub struct Ray{
 }
 
 impl Ray{
-    pub fn project<'a, const RC: usize, T: RaytracingTarget<RC>, F: Fn(&T::Intersection<'a>) -> bool>(&self, target: &'a T, exclude_cond: &F) -> Option<T::Intersection<'a>>{
+    pub fn project<'a, const RC: usize, T: RaytracingTarget<RC> + ?Sized, F: Fn(&T::Intersection<'a>) -> bool>(&self, target: &'a T, exclude_cond: &F) -> Option<T::Intersection<'a>>{
         return target.project_by(self, exclude_cond);
     }
 }
@@ -398,9 +462,18 @@ pub trait RaytracingTarget<const RECURSION_COUNT: usize>{
 
 
 #[derive(Debug, Copy)]
+#[cfg_attr(feature = "export", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "export", serde(bound(serialize = "E::PolygonMaterials: Serialize", deserialize = "E::PolygonMaterials: Deserialize<'de>")))]
 pub struct Polygon<E: AsciaEnvironment + ?Sized + 'static, CO: CoordinateType>{
     pub poses:Matrix33,
+    // normals at poses.v1, v2 and v3 for smooth shading, the face is flat when None
+    #[cfg_attr(feature = "export", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub normals: Option<Matrix33>,
+    // texture coordinates at poses.v1, v2 and v3
+    #[cfg_attr(feature = "export", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub uvs: Option<[Vec2; 3]>,
     pub material: E::PolygonMaterials,
+    #[cfg_attr(feature = "export", serde(skip))]
     pub _ph: PhantomData<CO>
 }
 
@@ -430,7 +503,10 @@ impl<E:AsciaEnvironment + ?Sized + 'static, CO:CoordinateType> RaytracingTarget<
                             depth: depth,
                             position: self.poses.v1 + v2.y * (self.poses.v2 - self.poses.v1) + v2.z * (self.poses.v3 - self.poses.v1),
                             ray: ray.clone(),
-                            normal: ((self.poses.v2 - self.poses.v1) ^ (self.poses.v3 - self.poses.v1)).normalize()
+                            normal: self.normal_at(&Vec2{
+                                x: v2.y,
+                                y: v2.z
+                            })
                         };
                         return if exclude_cond(&i) { None } else { Some(i) };
                     }
@@ -570,6 +646,8 @@ impl<E: AsciaEnvironment, CO: CoordinateType> Clone for Polygon<E, CO>{
     fn clone(&self) -> Self {
         return Polygon{
             poses: self.poses,
+            normals: self.normals,
+            uvs: self.uvs,
             material: self.material.clone(),
             _ph: self._ph
         }
@@ -577,7 +655,7 @@ impl<E: AsciaEnvironment, CO: CoordinateType> Clone for Polygon<E, CO>{
 }
 
 
-impl<E:AsciaEnvironment,CO:CoordinateType> Polygon<E, CO> {
+impl<E:AsciaEnvironment + ?Sized,CO:CoordinateType> Polygon<E, CO> {
     pub fn new(p1:&Vec3, p2:&Vec3, p3:&Vec3) -> Self{
         return Polygon {
             poses:Matrix33{
@@ -585,6 +663,8 @@ impl<E:AsciaEnvironment,CO:CoordinateType> Polygon<E, CO> {
                 v2:p2.clone(),
                 v3:p3.clone()
             },
+            normals: None,
+            uvs: None,
             material: E::PolygonMaterials::default(),
             _ph: Default::default()
         }
@@ -593,6 +673,202 @@ impl<E:AsciaEnvironment,CO:CoordinateType> Polygon<E, CO> {
     pub fn aabb(&self) -> AABB3D{
         return AABB3D::generate_3(&self.poses.v1, &self.poses.v2, &self.poses.v3);
     }
+
+    // the flat normal, following the winding of the vertices
+    pub fn normal(&self) -> Vec3{
+        return ((self.poses.v2 - self.poses.v1) ^ (self.poses.v3 - self.poses.v1)).normalize();
+    }
+
+    // p is position_on_polygon, the weights of v2 and v3
+    pub fn normal_at(&self, p: &Vec2) -> Vec3{
+        return match &self.normals {
+            Some(n) => { (n.v1 * (1.0 - p.x - p.y) + n.v2 * p.x + n.v3 * p.y).normalize() }
+            None => { self.normal() }
+        };
+    }
+
+    pub fn uv_at(&self, p: &Vec2) -> Option<Vec2>{
+        return self.uvs.map(|uv| uv[0] * (1.0 - p.x - p.y) + uv[1] * p.x + uv[2] * p.y);
+    }
+}
+
+// polygons shared by every node that instances them, they stay in the mesh's own space and each node only adds its transform
+pub struct Mesh<E: AsciaEnvironment + ?Sized + 'static>{
+    polygons: Vec<Polygon<E, Local>>,
+    bounds: Option<AABB3D>,
+}
+
+impl<E: AsciaEnvironment> Mesh<E>{
+    pub fn new(polygons: Vec<Polygon<E, Local>>) -> Self{
+        return Mesh{
+            bounds: polygons.iter().map(|p| p.aabb()).reduce(|a, b| AABB3D::concat(&a, &b)),
+            polygons: polygons,
+        };
+    }
+
+    pub fn polygons(&self) -> &[Polygon<E, Local>]{
+        return &self.polygons;
+    }
+
+    pub fn bounds(&self) -> Option<AABB3D>{
+        return self.bounds;
+    }
+}
+
+pub struct MeshInstance<E: AsciaEnvironment + ?Sized + 'static>{
+    pub mesh: Rc<Mesh<E>>,
+    // replaces the materials of all of the mesh's polygons
+    pub material: Option<E::PolygonMaterials>,
+}
+
+impl<E: AsciaEnvironment> Clone for MeshInstance<E>{
+    fn clone(&self) -> Self {
+        return MeshInstance{
+            mesh: self.mesh.clone(),
+            material: self.material.clone(),
+        }
+    }
+}
+
+impl<E: AsciaEnvironment> MeshInstance<E>{
+    pub fn new(mesh: Rc<Mesh<E>>) -> Self{
+        return MeshInstance{
+            mesh: mesh,
+            material: None,
+        };
+    }
+
+    pub fn material_of(&self, polygon: &Polygon<E, Local>) -> E::PolygonMaterials{
+        return match &self.material {
+            Some(m) => { m.clone() }
+            None => { polygon.material.clone() }
+        };
+    }
+}
+
+// where a global node puts the contents of its own space, the transform composed from the node and all of its ancestors
+#[derive(Debug, Copy, Clone)]
+pub struct Placement{
+    matrix: Matrix44,
+    // None when the placement flattens space, like a zero scale does
+    inverse: Option<Matrix44>,
+    normal_matrix: Option<Matrix33>,
+}
+
+impl Placement{
+    pub fn new(matrix: &Matrix44) -> Self{
+        return Placement{
+            matrix: *matrix,
+            inverse: matrix.inverse(),
+            normal_matrix: matrix.upper().inverse().map(|m| m.transpose()),
+        };
+    }
+
+    pub fn trs(position: &Vec3, direction: &Quaternion, scale: &Vec3) -> Self{
+        return Placement::new(&Matrix44::trs(position, direction, scale));
+    }
+
+    pub fn point(&self, p: &Vec3) -> Vec3{
+        return self.matrix.transform_point(p);
+    }
+
+    pub fn inverse_point(&self, p: &Vec3) -> Option<Vec3>{
+        return self.inverse.map(|m| m.transform_point(p));
+    }
+
+    // the ray parameter is kept, so a hit at depth d in the placed space lies at d / |inverse direction| * |direction| in the world
+    // a flattened placement has no space to trace through
+    pub fn inverse_ray(&self, ray: &Ray) -> Option<Ray>{
+        return self.inverse.map(|m| Ray{
+            position: m.transform_point(&ray.position),
+            direction: m.transform_vector(&ray.direction),
+        });
+    }
+
+    // mirroring along an odd number of axes turns the polygons inside out, so the winding is flipped back
+    pub fn polygon<E: AsciaEnvironment>(&self, polygon: &Polygon<E, Local>, material: E::PolygonMaterials) -> Polygon<E, Global>{
+        let v1 = self.point(&polygon.poses.v1);
+        let v2 = self.point(&polygon.poses.v2);
+        let v3 = self.point(&polygon.poses.v3);
+        let mirrored = self.is_mirrored();
+        let normals = polygon.normals.map(|n| Matrix33{
+            v1: self.normal(&n.v1),
+            v2: if mirrored { self.normal(&n.v3) } else { self.normal(&n.v2) },
+            v3: if mirrored { self.normal(&n.v2) } else { self.normal(&n.v3) },
+        });
+        let uvs = polygon.uvs.map(|uv| if mirrored { [uv[0], uv[2], uv[1]] } else { uv });
+        return Polygon{
+            poses: if mirrored {
+                Matrix33 {
+                    v1: v1,
+                    v2: v3,
+                    v3: v2,
+                }
+            } else {
+                Matrix33 {
+                    v1: v1,
+                    v2: v2,
+                    v3: v3,
+                }
+            },
+            normals: normals,
+            uvs: uvs,
+            material: material,
+            _ph: Default::default(),
+        };
+    }
+
+    fn is_mirrored(&self) -> bool{
+        return self.matrix.upper().det() < 0.0;
+    }
+
+    // moves a position_on_polygon of a polygon onto the copy made by polygon(), whose v2 and v3 are swapped when mirrored
+    pub fn position_on_polygon(&self, p: &Vec2) -> Vec2{
+        return if self.is_mirrored() {
+            Vec2{
+                x: p.y,
+                y: p.x,
+            }
+        } else {
+            *p
+        };
+    }
+
+    // the inverse transpose of the linear part, so normals stay perpendicular to stretched surfaces
+    pub fn normal(&self, n: &Vec3) -> Vec3{
+        return match &self.normal_matrix {
+            Some(m) => { (*m * *n).normalize() }
+            None => { self.matrix.transform_vector(n).normalize() }
+        };
+    }
+
+    pub fn matrix(&self) -> Matrix44{
+        return self.matrix;
+    }
+
+    pub fn aabb(&self, aabb: &AABB3D) -> AABB3D{
+        let (a, b) = (aabb.min(), aabb.max());
+        let mut result = AABB3D::generate_2(&self.point(&a), &self.point(&a));
+        for i in 1..8{
+            let corner = Vec3{
+                x: if i & 1 == 0 { a.x } else { b.x },
+                y: if i & 2 == 0 { a.y } else { b.y },
+                z: if i & 4 == 0 { a.z } else { b.z },
+            };
+            let p = self.point(&corner);
+            result = AABB3D::concat(&result, &AABB3D::generate_2(&p, &p));
+        }
+        return result;
+    }
+}
+
+// nodes are on every layer and cameras see every layer unless told otherwise
+pub const ALL_LAYERS: u32 = u32::MAX;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct NodeId{
+    index: usize,
+    generation: u32,
 }
 
 pub struct ObjectNode<E: AsciaEnvironment + ?Sized + 'static,CO:CoordinateType>{
@@ -600,13 +876,29 @@ pub struct ObjectNode<E: AsciaEnvironment + ?Sized + 'static,CO:CoordinateType>{
     pub attribute: Rc<RefCell<Option<E::ObjectNodeAttributes>>>,
     pub position: Vec3,
     pub direction: Quaternion,
+    pub scale: Vec3,
     pub polygons: Vec<Polygon<E, CO>>,
     pub c_particles: Vec<CParticle<E, CO>>,
-    pub children: HashMap<String, ObjectNode<E, CO>>,
+    pub mesh: Option<MeshInstance<E>>,
+    // in the global tree these already include the ancestors, a hidden node hides its subtree and layers are intersected
+    pub visible: bool,
+    pub layers: u32,
+    // in the global tree the parent's world matrix times the local transform, identity in the local tree
+    // position is its translation, direction and scale are composed on their own and only describe it fully while no non-uniform scale sits above a rotation
+    world: Matrix44,
+    id: NodeId,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    dirty: bool,
+    dirty_descendant: bool,
+    bounds: Option<AABB3D>,
+    subtree_bounds: Option<AABB3D>,
 }
 
 #[derive(Copy)]
 #[repr(C)]
+#[cfg_attr(feature = "export", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "export", serde(bound(serialize = "E::CParticleMaterials: Serialize", deserialize = "E::CParticleMaterials: Deserialize<'de>")))]
 pub struct CParticle<E: AsciaEnvironment + ?Sized + 'static, C: CoordinateType> {
     pub position:Vec3,
     pub velocity:Vec3,
@@ -614,6 +906,7 @@ pub struct CParticle<E: AsciaEnvironment + ?Sized + 'static, C: CoordinateType>
     pub threshold:f32,
     pub mode:CParticleMode,
     pub material: E::CParticleMaterials,
+    #[cfg_attr(feature = "export", serde(skip))]
     pub _ph: PhantomData<C>
 }
 
@@ -631,34 +924,51 @@ impl<E: AsciaEnvironment, C: CoordinateType> Clone for CParticle<E, C>{
     }
 }
 
+impl<E: AsciaEnvironment, C: CoordinateType> CParticle<E, C>{
+    // a sphere particle covers threshold around its position, an arg particle only its position
+    pub fn aabb(&self) -> AABB3D{
+        let r = if self.mode == CParticleMode::SPHERE { self.threshold } else { 0.0 };
+        let d = Vec3{
+            x: r,
+            y: r,
+            z: r,
+        };
+        return AABB3D::generate_2(&(self.position - d), &(self.position + d));
+    }
+}
+
 #[derive(Copy, Clone, PartialEq)]
 #[repr(u32)]
+#[cfg_attr(feature = "export", derive(Serialize, Deserialize))]
 pub enum CParticleMode{
     SPHERE = 0u32,
     ARG = 1u32
 }
 
+struct ObjectNodeSlot<E: AsciaEnvironment + ?Sized + 'static, CO: CoordinateType>{
+    generation: u32,
+    node: Option<ObjectNode<E, CO>>,
+}
+
+pub struct ObjectNodeTree<E: AsciaEnvironment + ?Sized + 'static, CO: CoordinateType>{
+    slots: Vec<ObjectNodeSlot<E, CO>>,
+    free: Vec<usize>,
+    root: NodeId,
+}
+
 pub struct ObjectNodeIter<'a, E:AsciaEnvironment, CO: CoordinateType>{
-    prior: Option<&'a ObjectNode<E, CO>>,
-    stack: VecDeque<std::collections::hash_map::Iter<'a,String, ObjectNode<E, CO>>>,
+    tree: &'a ObjectNodeTree<E, CO>,
+    stack: Vec<NodeId>,
 }
 
 impl<'a, E:AsciaEnvironment, CO: CoordinateType> Iterator for ObjectNodeIter<'a, E, CO>{
     type Item = &'a ObjectNode<E, CO>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(root) = self.prior{
-            self.stack.push_back(root.children.iter());
-            self.prior = None;
-            return Some(root);
-        }
-        while let Some(iter) = self.stack.back_mut(){
-            if let Some(now) = iter.next(){
-                self.stack.push_back(now.1.children.iter());
-                return Some(now.1);
-            }
-            else{
-                self.stack.pop_back();
+        while let Some(id) = self.stack.pop(){
+            if let Some(node) = self.tree.node(id){
+                self.stack.extend(node.children.iter().rev());
+                return Some(node);
             }
         }
         return None;
@@ -674,104 +984,670 @@ impl<E:AsciaEnvironment, C:CoordinateType> ObjectNode<E, C>{
         return self.direction.clone();
     }
 
-    pub fn add_child(&mut self, child: ObjectNode<E, C>){
-        self.children.insert(child.tag.clone(), child);
+    pub fn id(&self) -> NodeId{
+        return self.id;
+    }
+
+    pub fn parent(&self) -> Option<NodeId>{
+        return self.parent;
+    }
+
+    pub fn children(&self) -> &[NodeId]{
+        return &self.children;
+    }
+
+    pub fn is_dirty(&self) -> bool{
+        return self.dirty || self.dirty_descendant;
+    }
+}
+
+impl<E:AsciaEnvironment, C:CoordinateType> ObjectNodeTree<E, C>{
+    pub fn new(root: ObjectNode<E, C>) -> Self{
+        let mut tree = ObjectNodeTree{
+            slots: vec![],
+            free: vec![],
+            root: NodeId{
+                index: 0,
+                generation: 0,
+            },
+        };
+        tree.root = tree.insert(None, root);
+        return tree;
+    }
+
+    pub fn root(&self) -> NodeId{
+        return self.root;
     }
 
-    pub fn remove_child(&mut self, tag: String){
-        self.children.remove(&tag);
+    pub fn root_node(&self) -> &ObjectNode<E, C>{
+        return self.node(self.root).unwrap();
     }
 
-    pub fn child(&self, tag: &str) -> Option<&ObjectNode<E, C>>{
-        return self.children.get(&tag.to_string());
+    pub fn root_node_mut(&mut self) -> &mut ObjectNode<E, C>{
+        return self.node_mut(self.root).unwrap();
     }
 
-    pub fn child_mut(&mut self, tag: &str) -> Option<&mut ObjectNode<E, C>>{
-        return self.children.get_mut(&tag.to_string());
+    pub fn contains(&self, id: NodeId) -> bool{
+        return self.node(id).is_some();
+    }
+
+    pub fn node(&self, id: NodeId) -> Option<&ObjectNode<E, C>>{
+        return match self.slots.get(id.index) {
+            Some(s) if s.generation == id.generation => { s.node.as_ref() }
+            _ => { None }
+        };
     }
 
-    pub fn iter(&self) -> ObjectNodeIter<E, C>{
-        let stack = VecDeque::new();
+    // the returned node may be modified, so it is treated as changed on the next update
+    pub fn node_mut(&mut self, id: NodeId) -> Option<&mut ObjectNode<E, C>>{
+        if !self.contains(id){
+            return None;
+        }
+        self.mark_dirty(id);
+        return self.slots[id.index].node.as_mut();
+    }
+
+    pub fn child(&self, parent: NodeId, tag: &str) -> Option<NodeId>{
+        return self.node(parent)?.children.iter().find(|c| self.node(**c).map(|n| n.tag == tag).unwrap_or(false)).copied();
+    }
+
+    pub fn add_child(&mut self, parent: NodeId, child: ObjectNode<E, C>) -> Result<NodeId, String>{
+        self.check_new_child(parent, &child.tag)?;
+        self.mark_dirty(parent);
+        return Ok(self.insert(Some(parent), child));
+    }
+
+    // moves every node of the given tree under parent, the nodes get new ids
+    pub fn add_tree(&mut self, parent: NodeId, mut tree: ObjectNodeTree<E, C>) -> Result<NodeId, String>{
+        self.check_new_child(parent, &tree.root_node().tag)?;
+        self.mark_dirty(parent);
+        let root = tree.root;
+        return Ok(ObjectNodeTree::move_subtree(&mut tree, root, self, Some(parent)));
+    }
+
+    // detaches the node and its descendants, ids pointing into the removed subtree become invalid
+    pub fn remove(&mut self, id: NodeId) -> Result<ObjectNodeTree<E, C>, String>{
+        if id == self.root{
+            return Err("the root node cannot be removed".to_string());
+        }
+        let parent = match self.node(id) {
+            Some(n) => { n.parent.unwrap() }
+            None => { return Err(format!("node {:?} does not exist", id)); }
+        };
+        self.mark_dirty(parent);
+        self.slots[parent.index].node.as_mut().unwrap().children.retain(|c| *c != id);
+        let mut removed = ObjectNodeTree{
+            slots: vec![],
+            free: vec![],
+            root: id,
+        };
+        removed.root = ObjectNodeTree::move_subtree(self, id, &mut removed, None);
+        return Ok(removed);
+    }
+
+    pub fn iter(&self) -> ObjectNodeIter<'_, E, C>{
         return ObjectNodeIter{
-            prior: Some(self),
-            stack: stack
+            tree: self,
+            stack: vec![self.root],
+        };
+    }
+
+    // the tags from the root down to the node joined by '/', the inverse of id_by_path
+    pub fn path(&self, id: NodeId) -> Option<String>{
+        let mut tags = vec![];
+        let mut now = self.node(id)?;
+        while let Some(parent) = now.parent{
+            tags.push(now.tag.as_str());
+            now = self.node(parent).unwrap();
+        }
+        tags.reverse();
+        return Some(tags.join("/"));
+    }
+
+    pub fn id_by_path(&self, path: &str) -> Option<NodeId>{
+        let mut now = self.root;
+        for tag in path.split('/').filter(|t| !t.is_empty()){
+            now = self.child(now, tag)?;
+        }
+        return Some(now);
+    }
+
+    pub fn child_by_path(&self, path: &str) -> Option<&ObjectNode<E, C>>{
+        return self.node(self.id_by_path(path)?);
+    }
+
+    pub fn child_by_path_mut(&mut self, path: &str) -> Option<&mut ObjectNode<E, C>>{
+        let id = self.id_by_path(path)?;
+        return self.node_mut(id);
+    }
+
+    // each segment of the pattern is matched against tags one level down, with * and ? as wildcards
+    pub fn find_ids(&self, pattern: &str) -> Vec<NodeId>{
+        let mut found = vec![self.root];
+        for segment in pattern.split('/').filter(|t| !t.is_empty()){
+            let mut next = vec![];
+            for id in found{
+                for c in &self.node(id).unwrap().children{
+                    if glob_match(segment, &self.node(*c).unwrap().tag){
+                        next.push(*c);
+                    }
+                }
+            }
+            found = next;
+        }
+        return found;
+    }
+
+    pub fn find(&self, pattern: &str) -> Vec<&ObjectNode<E, C>>{
+        return self.find_ids(pattern).into_iter().map(|id| self.node(id).unwrap()).collect();
+    }
+
+    pub fn find_mut(&mut self, pattern: &str) -> Vec<&mut ObjectNode<E, C>>{
+        let ids = self.find_ids(pattern);
+        for id in &ids{
+            self.mark_dirty(*id);
+        }
+        let mut found: Vec<(usize, &mut ObjectNode<E, C>)> = self.slots.iter_mut().filter_map(|s| {
+            let node = s.node.as_mut()?;
+            let order = ids.iter().position(|id| *id == node.id)?;
+            return Some((order, node));
+        }).collect();
+        found.sort_by_key(|(order, _)| *order);
+        return found.into_iter().map(|(_, n)| n).collect();
+    }
+
+    fn check_new_child(&self, parent: NodeId, tag: &str) -> Result<(), String>{
+        if !self.contains(parent){
+            return Err(format!("node {:?} does not exist", parent));
+        }
+        if self.child(parent, tag).is_some(){
+            return Err(format!("node \"{}\" already has a child tagged \"{}\"", self.node(parent).unwrap().tag, tag));
+        }
+        return Ok(());
+    }
+
+    fn mark_dirty(&mut self, id: NodeId){
+        let mut now = self.slots[id.index].node.as_mut().unwrap();
+        now.dirty = true;
+        while let Some(parent) = now.parent{
+            now = self.slots[parent.index].node.as_mut().unwrap();
+            if now.dirty_descendant{
+                break;
+            }
+            now.dirty_descendant = true;
+        }
+    }
+
+    fn insert(&mut self, parent: Option<NodeId>, mut node: ObjectNode<E, C>) -> NodeId{
+        let index = match self.free.pop() {
+            Some(i) => { i }
+            None => {
+                self.slots.push(ObjectNodeSlot{
+                    generation: 0,
+                    node: None,
+                });
+                self.slots.len() - 1
+            }
+        };
+        let id = NodeId{
+            index: index,
+            generation: self.slots[index].generation,
+        };
+        node.id = id;
+        node.parent = parent;
+        node.children = vec![];
+        node.dirty = true;
+        node.dirty_descendant = false;
+        self.slots[index].node = Some(node);
+        if let Some(p) = parent{
+            self.slots[p.index].node.as_mut().unwrap().children.push(id);
+        }
+        return id;
+    }
+
+    fn move_subtree(from: &mut ObjectNodeTree<E, C>, id: NodeId, to: &mut ObjectNodeTree<E, C>, parent: Option<NodeId>) -> NodeId{
+        let mut node = from.slots[id.index].node.take().unwrap();
+        from.slots[id.index].generation += 1;
+        from.free.push(id.index);
+        let children = std::mem::take(&mut node.children);
+        let new_id = to.insert(parent, node);
+        for c in children{
+            ObjectNodeTree::move_subtree(from, c, to, Some(new_id));
+        }
+        return new_id;
+    }
+}
+
+fn glob_match(pattern: &str, s: &str) -> bool{
+    let p: Vec<char> = pattern.chars().collect();
+    let s: Vec<char> = s.chars().collect();
+    let (mut i, mut j) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while j < s.len(){
+        if i < p.len() && (p[i] == '?' || p[i] == s[j]){
+            i += 1;
+            j += 1;
+        }
+        else if i < p.len() && p[i] == '*'{
+            backtrack = Some((i, j));
+            i += 1;
+        }
+        else if let Some((bi, bj)) = backtrack{
+            // let the last * swallow one more character
+            backtrack = Some((bi, bj + 1));
+            i = bi + 1;
+            j = bj + 1;
+        }
+        else{
+            return false;
+        }
+    }
+    while i < p.len() && p[i] == '*'{
+        i += 1;
+    }
+    return i == p.len();
+}
+
+impl<E:AsciaEnvironment> ObjectNode<E, Global>{
+    // to the world, composed as matrices so that it stays exact where position, direction and scale cannot follow
+    pub fn transform(&self) -> Matrix44{
+        return self.world;
+    }
+
+    // the bounds of the node's own polygons and particles, None when it has neither
+    pub fn bounds(&self) -> Option<AABB3D>{
+        return self.bounds;
+    }
+
+    // the bounds of the node and all of its descendants
+    pub fn subtree_bounds(&self) -> Option<AABB3D>{
+        return self.subtree_bounds;
+    }
+
+    pub fn is_visible_to(&self, layer_mask: u32) -> bool{
+        return self.visible && self.layers & layer_mask != 0;
+    }
+
+    pub fn placement(&self) -> Placement{
+        return Placement::new(&self.world);
+    }
+
+    // conversions between the node's own space and the world, a node with a zero scale has no local space and maps everything to its position
+    pub fn point_to_world(&self, p: &Vec3) -> Vec3{
+        return self.transform().transform_point(p);
+    }
+
+    pub fn point_to_local(&self, p: &Vec3) -> Vec3{
+        return self.transform().inverse().map(|m| m.transform_point(p)).unwrap_or_default();
+    }
+
+    pub fn vector_to_world(&self, v: &Vec3) -> Vec3{
+        return self.transform().transform_vector(v);
+    }
+
+    pub fn vector_to_local(&self, v: &Vec3) -> Vec3{
+        return self.transform().inverse().map(|m| m.transform_vector(v)).unwrap_or_default();
+    }
+
+    pub fn normal_to_world(&self, n: &Vec3) -> Vec3{
+        return self.transform().transform_normal(n);
+    }
+
+    // world space copies of the instanced mesh's polygons, for renderers that do not trace instances on their own
+    pub fn mesh_polygons(&self) -> Vec<Polygon<E, Global>>{
+        return match &self.mesh {
+            Some(m) => {
+                let placement = self.placement();
+                m.mesh.polygons().iter().map(|p| placement.polygon(p, m.material_of(p))).collect()
+            }
+            None => { vec![] }
         };
     }
 }
 
+impl<E:AsciaEnvironment> ObjectNodeTree<E, Global>{
+    // what a child of parent inherits, parents are always transformed before their children
+    fn parent_visibility(&self, parent: Option<NodeId>) -> (bool, u32){
+        return match parent.and_then(|p| self.node(p)) {
+            Some(p) => { (p.visible, p.layers) }
+            None => { (true, ALL_LAYERS) }
+        };
+    }
+
+    fn refresh_subtree_bounds(&mut self, id: NodeId){
+        let node = self.node(id).unwrap();
+        let bounds = node.children.iter().filter_map(|c| self.node(*c).and_then(|c| c.subtree_bounds)).fold(node.bounds, |acc, b| match acc {
+            Some(a) => { Some(AABB3D::concat(&a, &b)) }
+            None => { Some(b) }
+        });
+        self.slots[id.index].node.as_mut().unwrap().subtree_bounds = bounds;
+    }
+
+    // ids of the nodes whose own bounds pass the test, whole subtrees are skipped when their bounds fail it
+    pub fn query<F: Fn(&AABB3D) -> bool>(&self, test: &F) -> Vec<NodeId>{
+        let mut found = vec![];
+        let mut stack = vec![self.root];
+        while let Some(id) = stack.pop(){
+            let node = self.node(id).unwrap();
+            if !node.subtree_bounds.map(|b| test(&b)).unwrap_or(false){
+                continue;
+            }
+            if node.bounds.map(|b| test(&b)).unwrap_or(false){
+                found.push(id);
+            }
+            stack.extend(node.children.iter().rev());
+        }
+        return found;
+    }
+
+    // maps points in the space of from to the space of to, None when either is missing or to has a zero scale
+    pub fn relative_transform(&self, from: NodeId, to: NodeId) -> Option<Matrix44>{
+        let from = self.node(from)?;
+        let to = self.node(to)?;
+        return to.transform().inverse().map(|m| m * from.transform());
+    }
+
+    pub fn query_radius(&self, center: &Vec3, radius: f32) -> Vec<String>{
+        return self.query(&|b| b.distance(center) <= radius).into_iter().map(|id| self.path(id).unwrap()).collect();
+    }
+
+    pub fn query_aabb(&self, aabb: &AABB3D) -> Vec<String>{
+        return self.query(&|b| b.intersects(aabb)).into_iter().map(|id| self.path(id).unwrap()).collect();
+    }
+
+    pub fn query_frustum(&self, frustum: &Frustum) -> Vec<String>{
+        return self.query(&|b| frustum.intersects(b)).into_iter().map(|id| self.path(id).unwrap()).collect();
+    }
+
+    pub fn raycast(&self, ray: &Ray) -> Option<RaycastHit<'_, E>>{
+        let mut nearest: Option<RaycastHit<E>> = None;
+        for node in self.iter(){
+            let polygon = ray.project(&node.polygons, &|_|{false}).map(|i| (RaycastTarget::Polygon(i.polygon), i.position, i.normal, i.depth));
+            // particles are drawn facing the camera, so their normal points back along the ray
+            let c_particle = ray.project(&node.c_particles, &|_|{false}).map(|i| (RaycastTarget::CParticle(i.particle), i.position, (ray.direction * -1.0).normalize(), i.depth));
+            let mesh = node.mesh.as_ref().and_then(|m| {
+                let placement = node.placement();
+                let local_ray = placement.inverse_ray(ray)?;
+                return local_ray.project(&m.mesh.polygons, &|_|{false}).map(|i| {
+                    let p = placement.polygon(i.polygon, i.polygon.material.clone());
+                    (RaycastTarget::MeshPolygon(i.polygon), placement.point(&i.position), p.normal_at(&placement.position_on_polygon(&i.position_on_polygon)), i.depth / local_ray.direction.norm() * ray.direction.norm())
+                });
+            });
+            for (target, position, normal, depth) in polygon.into_iter().chain(c_particle).chain(mesh){
+                if nearest.as_ref().map(|n| depth < n.depth).unwrap_or(true){
+                    nearest = Some(RaycastHit{
+                        node: node,
+                        path: String::new(),
+                        target: target,
+                        position: position,
+                        normal: normal,
+                        depth: depth,
+                    });
+                }
+            }
+        }
+        if let Some(hit) = &mut nearest{
+            hit.path = self.path(hit.node.id()).unwrap();
+        }
+        return nearest;
+    }
+}
+
 impl<E:AsciaEnvironment> ObjectNode<E, Local>{
+    // relative to the parent
+    pub fn transform(&self) -> Matrix44{
+        return Matrix44::trs(&self.position, &self.direction, &self.scale);
+    }
+
     pub fn new(tag: &str) -> Self{
         return ObjectNode::from(tag,vec![]);
     }
+
+    pub fn from_mesh(tag: &str, mesh: MeshInstance<E>) -> Self{
+        let mut node = ObjectNode::new(tag);
+        node.mesh = Some(mesh);
+        return node;
+    }
     pub fn from(tag: &str, polygons:Vec<Polygon<E, Local>>) -> Self {
         return ObjectNode{
             tag: tag.to_string(),
             attribute: Rc::new(RefCell::new(None)),
             position: Vec3::default(),
             direction: Quaternion::default(),
+            scale: Vec3{
+                x: 1.0,
+                y: 1.0,
+                z: 1.0,
+            },
             polygons: polygons,
             c_particles: vec![],
-            children: Default::default()
+            mesh: None,
+            visible: true,
+            layers: ALL_LAYERS,
+            world: Matrix44::identity(),
+            id: NodeId{
+                index: usize::MAX,
+                generation: 0,
+            },
+            parent: None,
+            children: vec![],
+            dirty: true,
+            dirty_descendant: false,
+            bounds: None,
+            subtree_bounds: None,
         };
     }
-    pub fn generate_global_nodes(&self) -> ObjectNode<E, Global>{
-        let mut iter = self.iter();
-        let mut stack_global:VecDeque<ObjectNode<E, Global>> = VecDeque::new();
-        while let Some(now) = iter.next(){
-            while stack_global.len() + 1 > iter.stack.len(){
-                let node = stack_global.pop_back();
-                stack_global.back_mut().unwrap().add_child(node.unwrap());
-            }
 
-            let parent_position = if stack_global.is_empty() {
-                Vec3::default()
-            } else {
-                stack_global.back().unwrap().position
-            };
-            let parent_direction = if stack_global.is_empty() {
-                Quaternion::default()
-            } else {
-                stack_global.back().unwrap().direction
-            };
+    fn transform_node(&self, parent_world: &Matrix44, parent_direction: &Quaternion, parent_scale: &Vec3, parent_visibility: (bool, u32)) -> ObjectNode<E, Global>{
+        let world = *parent_world * self.transform();
+        let mut child = ObjectNode{
+            tag: self.tag.clone(),
+            attribute: self.attribute.clone(),
+            position: Vec3{
+                x: world.v4.x,
+                y: world.v4.y,
+                z: world.v4.z,
+            },
+            direction: *parent_direction * self.direction,
+            scale: parent_scale.scale_by(&self.scale),
+            polygons: Vec::with_capacity(self.polygons.len()),
+            c_particles: Vec::with_capacity(self.c_particles.len()),
+            mesh: self.mesh.clone(),
+            visible: parent_visibility.0 && self.visible,
+            layers: parent_visibility.1 & self.layers,
+            world: world,
+            id: self.id,
+            parent: self.parent,
+            children: self.children.clone(),
+            dirty: false,
+            dirty_descendant: false,
+            bounds: None,
+            subtree_bounds: None,
+        };
+        let placement = child.placement();
+        for p in &self.polygons{
+            child.polygons.push(placement.polygon(p, p.material.clone()));
+        }
+        for p in &self.c_particles {
+            child.c_particles.push(CParticle {
+                position: placement.point(&p.position),
+                velocity: p.velocity,
+                c: p.c,
+                threshold: p.threshold,
+                mode: p.mode,
+                material: p.material.clone(),
+                _ph: Default::default(),
+            });
+        }
+        let mesh_bounds = self.mesh.as_ref().and_then(|m| m.mesh.bounds()).map(|b| placement.aabb(&b));
+        child.bounds = child.polygons.iter().map(|p| p.aabb()).chain(child.c_particles.iter().map(|p| p.aabb())).chain(mesh_bounds).reduce(|a, b| AABB3D::concat(&a, &b));
+        child.subtree_bounds = child.bounds;
+        return child;
+    }
+}
+
+impl<E:AsciaEnvironment> ObjectNodeTree<E, Local>{
+    // the global tree shares the ids of the local tree
+    pub fn generate_global_nodes(&self) -> ObjectNodeTree<E, Global>{
+        let mut global = ObjectNodeTree{
+            slots: self.slots.iter().map(|s| ObjectNodeSlot{
+                generation: s.generation,
+                node: None,
+            }).collect(),
+            free: vec![],
+            root: self.root,
+        };
+        self.generate_global_subtree(self.root, &mut global, &Matrix44::identity(), &Quaternion::default(), &Vec3{ x: 1.0, y: 1.0, z: 1.0 });
+        return global;
+    }
 
-            let mut child = ObjectNode{
-                tag: now.tag.clone(),
-                attribute: now.attribute.clone(),
-                position: parent_position + parent_direction.rotate(&now.position),
-                direction: parent_direction * now.direction,
-                polygons: Vec::with_capacity(now.polygons.len()),
-                c_particles: Vec::with_capacity(now.c_particles.len()),
-                children: Default::default(),
-            };
-            for p in &now.polygons{
-                child.polygons.push(Polygon{
-                    poses: Matrix33 {
-                        v1: child.position + child.direction.rotate(&p.poses.v1),
-                        v2: child.position + child.direction.rotate(&p.poses.v2),
-                        v3: child.position + child.direction.rotate(&p.poses.v3),
-                    },
-                    material: p.material.clone(),
-                    _ph: Default::default(),
-                });
+    fn generate_global_subtree(&self, id: NodeId, global: &mut ObjectNodeTree<E, Global>, parent_world: &Matrix44, parent_direction: &Quaternion, parent_scale: &Vec3){
+        let node = self.node(id).unwrap();
+        let g = node.transform_node(parent_world, parent_direction, parent_scale, global.parent_visibility(node.parent));
+        let (world, direction, scale) = (g.world, g.direction, g.scale);
+        global.slots[id.index].node = Some(g);
+        for c in &node.children{
+            self.generate_global_subtree(*c, global, &world, &direction, &scale);
+        }
+        global.refresh_subtree_bounds(id);
+    }
+
+    pub fn update_global_nodes(&mut self, global: &mut ObjectNodeTree<E, Global>){
+        global.root = self.root;
+        global.free.clear();
+        global.slots.truncate(self.slots.len());
+        while global.slots.len() < self.slots.len(){
+            global.slots.push(ObjectNodeSlot{
+                generation: 0,
+                node: None,
+            });
+        }
+        for (l, g) in self.slots.iter().zip(global.slots.iter_mut()){
+            if l.node.is_none() || l.generation != g.generation{
+                g.generation = l.generation;
+                g.node = None;
             }
-            for p in &now.c_particles {
-                child.c_particles.push(CParticle {
-                    position: child.position + child.direction.rotate(&p.position),
-                    velocity: p.velocity,
-                    c: p.c,
-                    threshold: p.threshold,
-                    mode: p.mode,
-                    material: p.material.clone(),
-                    _ph: Default::default(),
-                });
+        }
+        self.update_global_node(self.root, global, &Matrix44::identity(), &Quaternion::default(), &Vec3{ x: 1.0, y: 1.0, z: 1.0 }, false);
+    }
+
+    // re-transforms only the subtrees whose local data or inherited transform have changed since the last update
+    fn update_global_node(&mut self, id: NodeId, global: &mut ObjectNodeTree<E, Global>, parent_world: &Matrix44, parent_direction: &Quaternion, parent_scale: &Vec3, parent_changed: bool){
+        let node = self.slots[id.index].node.as_mut().unwrap();
+        let world = *parent_world * node.transform();
+        let direction = *parent_direction * node.direction;
+        let scale = parent_scale.scale_by(&node.scale);
+        let parent_visibility = global.parent_visibility(node.parent);
+        let visible = parent_visibility.0 && node.visible;
+        let layers = parent_visibility.1 & node.layers;
+        let changed = parent_changed || match global.node(id) {
+            Some(g) => { g.world != world || g.direction != direction || g.scale != scale || g.visible != visible || g.layers != layers }
+            None => { true }
+        };
+
+        if changed || node.dirty{
+            global.slots[id.index].node = Some(node.transform_node(parent_world, parent_direction, parent_scale, parent_visibility));
+        }
+        let visit_children = changed || node.dirty || node.dirty_descendant;
+        node.dirty = false;
+        node.dirty_descendant = false;
+        if visit_children{
+            for c in node.children.clone(){
+                if changed || self.node(c).unwrap().is_dirty() || !global.contains(c){
+                    self.update_global_node(c, global, &world, &direction, &scale, changed);
+                }
+            }
+            global.refresh_subtree_bounds(id);
+        }
+    }
+
+    // moves the node under new_parent, its local transform is recomputed from the global tree so that it stays where it is in the world
+    pub fn reparent(&mut self, id: NodeId, new_parent: NodeId, global: &ObjectNodeTree<E, Global>) -> Result<(), String>{
+        if id == self.root{
+            return Err("the root node cannot be reparented".to_string());
+        }
+        let (tag, old_parent) = match self.node(id) {
+            Some(n) => { (n.tag.clone(), n.parent.unwrap()) }
+            None => { return Err(format!("node {:?} does not exist", id)); }
+        };
+        let mut now = Some(new_parent);
+        while let Some(n) = now{
+            if n == id{
+                return Err(format!("node \"{}\" cannot be moved under its own descendant", tag));
             }
-            stack_global.push_back(child);
+            now = self.node(n).and_then(|n| n.parent);
         }
-        while stack_global.len() > 1{
-            let node = stack_global.pop_back();
-            stack_global.back_mut().unwrap().add_child(node.unwrap());
+        if old_parent == new_parent{
+            return Ok(());
         }
-        return stack_global.pop_back().unwrap();
+        self.check_new_child(new_parent, &tag)?;
+
+        let (g, gp) = match (global.node(id), global.node(new_parent)) {
+            (Some(g), Some(gp)) => { (g, gp) }
+            _ => { return Err("the global tree is out of date, update it before reparenting".to_string()); }
+        };
+        let local = match gp.world.inverse() {
+            Some(m) => { m * g.world }
+            None => { return Err(format!("node \"{}\" has a zero scale, so nothing can keep its placement under it", gp.tag)); }
+        };
+        let position = Vec3{
+            x: local.v4.x,
+            y: local.v4.y,
+            z: local.v4.z,
+        };
+        // the lengths of the local axes are the scale, the signs follow the global scales as long as they agree with the mirroring of the whole
+        let m = local.upper();
+        let sign = |a: f32, b: f32| if a * b < 0.0 { -1.0 } else { 1.0 };
+        let mut signs = Vec3{
+            x: sign(g.scale.x, gp.scale.x),
+            y: sign(g.scale.y, gp.scale.y),
+            z: sign(g.scale.z, gp.scale.z),
+        };
+        if m.det() * signs.x * signs.y * signs.z < 0.0{
+            signs = signs * -1.0;
+        }
+        let scale = Vec3{
+            x: m.v1.norm() * signs.x,
+            y: m.v2.norm() * signs.y,
+            z: m.v3.norm() * signs.z,
+        };
+        let direction = if m.det() == 0.0 {
+            // a flattened node has no axes to read a rotation from, so the composed directions stand in
+            // the direction may carry a uniform scale, so the inverse is the conjugate divided by the squared norm
+            let n2 = gp.direction.norm() * gp.direction.norm();
+            let d = gp.direction.conjugate() * g.direction;
+            Quaternion{
+                vec4: Vec4{
+                    w: d.vec4.w / n2,
+                    x: d.vec4.x / n2,
+                    y: d.vec4.y / n2,
+                    z: d.vec4.z / n2,
+                },
+            }
+        } else {
+            Quaternion::from_rotation_matrix(&Matrix33{
+                v1: m.v1 / scale.x,
+                v2: m.v2 / scale.y,
+                v3: m.v3 / scale.z,
+            })
+        };
+        // a node turned against the axes of a non-uniformly scaled parent would have to be sheared to stay in place
+        let t = Matrix44::trs(&position, &direction, &scale).upper();
+        let error = (t.v1 - m.v1).norm() + (t.v2 - m.v2).norm() + (t.v3 - m.v3).norm();
+        if error > 1e-4 * (m.v1.norm() + m.v2.norm() + m.v3.norm()){
+            return Err(format!("node \"{}\" is turned against the non-uniform scale of \"{}\" and cannot keep its shape under it", tag, gp.tag));
+        }
+
+        self.mark_dirty(old_parent);
+        self.slots[old_parent.index].node.as_mut().unwrap().children.retain(|c| *c != id);
+        self.mark_dirty(new_parent);
+        self.slots[new_parent.index].node.as_mut().unwrap().children.push(id);
+        self.slots[id.index].node.as_mut().unwrap().parent = Some(new_parent);
+        let node = self.node_mut(id).unwrap();
+        node.position = position;
+        node.direction = direction;
+        node.scale = scale;
+        return Ok(());
     }
 }
 
@@ -780,8 +1656,13 @@ mod tests{
     use std::cell::RefCell;
     use std::f32::consts::PI;
     use std::rc::Rc;
-    use crate::ascia::core::{Local, ObjectNode, Polygon, PresetAsciaEnvironment};
-    use crate::ascia::math::{Quaternion, Vec3};
+    use crate::ascia::camera::SimpleCamera;
+    use crate::ascia::color::ColorRGBf32;
+    use crate::ascia::core::{ALL_LAYERS, AsciaEngine, Camera, CParticle, CParticleMode, Global, Local, Material, Mesh, MeshInstance, NodeId, ObjectNode, ObjectNodeTree, Placement, Polygon, PresetAsciaEnvironment, PresetPolygonMaterial, Ray, RaycastTarget, TexturedMaterial};
+    use crate::ascia::math::{AABB3D, Frustum, Matrix44, Quaternion, Vec2, Vec3};
+    use crate::ascia::primitives::PrimitiveGenerator;
+    use crate::ascia::util::smooth_normals;
+    use crate::ascia::texture::{Texture, TextureFilter};
 
     #[test]
     fn test_generate_global_nodes(){
@@ -817,11 +1698,16 @@ mod tests{
             y: -2.0,
             z: 3.0,
         };
-        let mut a: ObjectNode<PresetAsciaEnvironment, Local> = ObjectNode{
+        let a: ObjectNode<PresetAsciaEnvironment, Local> = ObjectNode{
             tag: "a".to_string(),
             attribute: Rc::new(RefCell::new(None)),
             position: a_pos_local,
             direction: a_dir_local,
+            scale: Vec3{
+                x: 1.0,
+                y: 1.0,
+                z: 1.0,
+            },
             polygons: vec![
                 Polygon::new(&Vec3{
                         x: 0.0,
@@ -840,13 +1726,31 @@ mod tests{
                     })
             ],
             c_particles: vec![],
-            children: Default::default(),
+            mesh: None,
+            visible: true,
+            layers: ALL_LAYERS,
+            world: Matrix44::identity(),
+            id: NodeId{
+                index: 0,
+                generation: 0,
+            },
+            parent: None,
+            children: vec![],
+            dirty: true,
+            dirty_descendant: false,
+            bounds: None,
+            subtree_bounds: None,
         };
         let b: ObjectNode<PresetAsciaEnvironment, Local> = ObjectNode{
             tag: "b".to_string(),
             attribute: Rc::new(RefCell::new(None)),
             position: b_pos_local,
             direction: Default::default(),
+            scale: Vec3{
+                x: 1.0,
+                y: 1.0,
+                z: 1.0,
+            },
             polygons: vec![
                 Polygon::new(&Vec3{
                             x: 0.0,
@@ -865,13 +1769,31 @@ mod tests{
                         })
             ],
             c_particles: vec![],
-            children: Default::default(),
+            mesh: None,
+            visible: true,
+            layers: ALL_LAYERS,
+            world: Matrix44::identity(),
+            id: NodeId{
+                index: 0,
+                generation: 0,
+            },
+            parent: None,
+            children: vec![],
+            dirty: true,
+            dirty_descendant: false,
+            bounds: None,
+            subtree_bounds: None,
         };
-        let mut c: ObjectNode<PresetAsciaEnvironment,Local> = ObjectNode{
+        let c: ObjectNode<PresetAsciaEnvironment,Local> = ObjectNode{
             tag: "c".to_string(),
             attribute: Rc::new(RefCell::new(None)),
             position: c_pos_local,
             direction: c_dir_local,
+            scale: Vec3{
+                x: 1.0,
+                y: 1.0,
+                z: 1.0,
+            },
             polygons: vec![
                 Polygon::new(
                     &Vec3{
@@ -891,13 +1813,31 @@ mod tests{
                         })
             ],
             c_particles: vec![],
-            children: Default::default(),
+            mesh: None,
+            visible: true,
+            layers: ALL_LAYERS,
+            world: Matrix44::identity(),
+            id: NodeId{
+                index: 0,
+                generation: 0,
+            },
+            parent: None,
+            children: vec![],
+            dirty: true,
+            dirty_descendant: false,
+            bounds: None,
+            subtree_bounds: None,
         };
         let d: ObjectNode<PresetAsciaEnvironment, Local> = ObjectNode{
             tag: "d".to_string(),
             attribute: Rc::new(RefCell::new(None)),
             position: d_pos_local,
             direction: Default::default(),
+            scale: Vec3{
+                x: 1.0,
+                y: 1.0,
+                z: 1.0,
+            },
             polygons: vec![
                 Polygon::new(
                     &Vec3{
@@ -917,20 +1857,585 @@ mod tests{
                     })
             ],
             c_particles: vec![],
-            children: Default::default(),
+            mesh: None,
+            visible: true,
+            layers: ALL_LAYERS,
+            world: Matrix44::identity(),
+            id: NodeId{
+                index: 0,
+                generation: 0,
+            },
+            parent: None,
+            children: vec![],
+            dirty: true,
+            dirty_descendant: false,
+            bounds: None,
+            subtree_bounds: None,
         };
-        a.add_child(b);
-        c.add_child(d);
-        a.add_child(c);
-        let a_global = a.generate_global_nodes();
-        let b_global = a_global.children.get(&"b".to_string()).unwrap();
-        let c_global = a_global.children.get(&"c".to_string()).unwrap();
-        let d_global = c_global.children.get(&"d".to_string()).unwrap();
-        assert_eq!(b_global.position, a_pos_local + a_dir_local.rotate(&b_pos_local));
+        let mut tree = ObjectNodeTree::new(a);
+        tree.add_child(tree.root(), b).unwrap();
+        let c_id = tree.add_child(tree.root(), c).unwrap();
+        tree.add_child(c_id, d).unwrap();
+        let a_global = tree.generate_global_nodes();
+        let b_global = a_global.child_by_path("b").unwrap();
+        let c_global = a_global.node(c_id).unwrap();
+        let d_global = a_global.child_by_path("c/d").unwrap();
+        // positions come out of the composed matrices, so they agree up to rounding
+        let close = |p: Vec3, q: Vec3| (p - q).norm() < 1e-4;
+        assert!(close(b_global.position, a_pos_local + a_dir_local.rotate(&b_pos_local)));
         assert_eq!(b_global.direction, a_dir_local);
-        assert_eq!(c_global.position, a_pos_local + a_dir_local.rotate(&c_pos_local));
+        assert!(close(c_global.position, a_pos_local + a_dir_local.rotate(&c_pos_local)));
         assert_eq!(c_global.direction,  a_dir_local * c_dir_local);
-        assert_eq!(d_global.position, c_global.position + c_global.direction.rotate(&d_pos_local));
+        assert!(close(d_global.position, c_global.position + c_global.direction.rotate(&d_pos_local)));
+    }
+
+    #[test]
+    fn test_generate_global_nodes_scale(){
+        let mut a: ObjectNode<PresetAsciaEnvironment, Local> = ObjectNode::new("a");
+        a.scale = Vec3{
+            x: 2.0,
+            y: 3.0,
+            z: 1.0,
+        };
+        let mut b: ObjectNode<PresetAsciaEnvironment, Local> = ObjectNode::from("b", vec![
+            Polygon::new(&Vec3{
+                    x: 0.0,
+                    y: 0.0,
+                    z: 0.0,
+                },
+                &Vec3{
+                    x: 1.0,
+                    y: 0.0,
+                    z: 0.0,
+                },
+                &Vec3{
+                    x: 0.0,
+                    y: 1.0,
+                    z: 0.0,
+                })
+        ]);
+        b.position = Vec3{
+            x: 1.0,
+            y: 1.0,
+            z: 0.0,
+        };
+        b.scale = Vec3{
+            x: 1.0,
+            y: 1.0,
+            z: -1.0,
+        };
+        let mut tree = ObjectNodeTree::new(a);
+        tree.add_child(tree.root(), b).unwrap();
+        let a_global = tree.generate_global_nodes();
+        let b_global = a_global.child_by_path("b").unwrap();
+        assert_eq!(b_global.position, Vec3{ x: 2.0, y: 3.0, z: 0.0 });
+        assert_eq!(b_global.scale, Vec3{ x: 2.0, y: 3.0, z: -1.0 });
+        let p = &b_global.polygons[0].poses;
+        assert_eq!(p.v2, Vec3{ x: 2.0, y: 6.0, z: 0.0 });
+        assert_eq!(p.v3, Vec3{ x: 4.0, y: 3.0, z: 0.0 });
+        // mirrored through z, so the normal of the xy triangle has to point to -z after flipping the winding
+        assert!(((p.v2 - p.v1) ^ (p.v3 - p.v1)).z < 0.0);
+    }
+
+    #[test]
+    fn test_generate_global_nodes_rotated_under_scale(){
+        let mut a: ObjectNode<PresetAsciaEnvironment, Local> = ObjectNode::new("a");
+        a.scale = Vec3{ x: 2.0, y: 1.0, z: 1.0 };
+        let mut b: ObjectNode<PresetAsciaEnvironment, Local> = ObjectNode::from("b", vec![
+            Polygon::new(&Vec3{ x: 0.0, y: 0.0, z: 0.0 }, &Vec3{ x: 1.0, y: 0.0, z: 0.0 }, &Vec3{ x: 0.0, y: 1.0, z: 0.0 })
+        ]);
+        b.direction = Quaternion::new(&Vec3{ x: 0.0, y: 0.0, z: 1.0 }, PI / 2.0, 1.0);
+        let mut tree = ObjectNodeTree::new(a);
+        let b = tree.add_child(tree.root(), b).unwrap();
+        let close = |p: Vec3, q: Vec3| (p - q).norm() < 1e-5;
+
+        // b turns its x axis onto y before a stretches x, so its x axis keeps length 1 and its y axis doubles
+        let mut global = tree.generate_global_nodes();
+        let p = global.node(b).unwrap().polygons[0].poses;
+        assert!(close(p.v2, Vec3{ x: 0.0, y: 1.0, z: 0.0 }));
+        assert!(close(p.v3, Vec3{ x: -2.0, y: 0.0, z: 0.0 }));
+
+        tree.node_mut(b).unwrap().direction = Quaternion::new(&Vec3{ x: 0.0, y: 0.0, z: 1.0 }, PI / 4.0, 1.0);
+        tree.update_global_nodes(&mut global);
+        let p = global.node(b).unwrap().polygons[0].poses;
+        let h = f32::sqrt(0.5);
+        assert!(close(p.v2, Vec3{ x: 2.0 * h, y: h, z: 0.0 }));
+        assert!(close(p.v3, Vec3{ x: -2.0 * h, y: h, z: 0.0 }));
+    }
+
+    #[test]
+    fn test_find_by_path(){
+        let mut root: ObjectNodeTree<PresetAsciaEnvironment, Local> = ObjectNodeTree::new(ObjectNode::new("root"));
+        let container = root.add_child(root.root(), ObjectNode::new("container")).unwrap();
+        for i in [2, 0, 1]{
+            root.add_child(container, ObjectNode::new(&format!("teapot {}", i))).unwrap();
+        }
+        root.add_child(container, ObjectNode::new("light")).unwrap();
+        root.add_child(root.root(), ObjectNode::new("teapot 9")).unwrap();
+
+        assert_eq!(root.child_by_path("container/teapot 1").unwrap().tag, "teapot 1");
+        assert!(root.child_by_path("container/teapot 7").is_none());
+        assert_eq!(root.child_by_path("").unwrap().tag, "root");
+        assert_eq!(root.id_by_path("/container/"), Some(container));
+
+        let tags: Vec<&str> = root.find("container/teapot *").iter().map(|n| n.tag.as_str()).collect();
+        assert_eq!(tags, vec!["teapot 2", "teapot 0", "teapot 1"]);
+        assert_eq!(root.find("*/l?ght").len(), 1);
+        assert_eq!(root.find("teapot*").len(), 1);
+
+        let mut global = root.generate_global_nodes();
+        root.update_global_nodes(&mut global);
+        let tags: Vec<String> = root.find_mut("container/teapot *").into_iter().map(|n| {
+            n.position.y = 1.0;
+            return n.tag.clone();
+        }).collect();
+        assert_eq!(tags, vec!["teapot 2", "teapot 0", "teapot 1"]);
+        assert!(root.root_node().is_dirty());
+        root.update_global_nodes(&mut global);
+        assert_eq!(global.find("container/*").iter().filter(|n| n.position.y == 1.0).count(), 3);
+        root.child_by_path_mut("container/light").unwrap().position.x = 2.0;
+        assert_eq!(root.node(root.child(container, "light").unwrap()).unwrap().position.x, 2.0);
+    }
+
+    #[test]
+    fn test_node_tree(){
+        let mut tree: ObjectNodeTree<PresetAsciaEnvironment, Local> = ObjectNodeTree::new(ObjectNode::new("root"));
+        let root = tree.root();
+        let a = tree.add_child(root, ObjectNode::new("a")).unwrap();
+        let b = tree.add_child(root, ObjectNode::new("b")).unwrap();
+        let a1 = tree.add_child(a, ObjectNode::new("a1")).unwrap();
+        assert!(tree.add_child(root, ObjectNode::new("a")).err().unwrap().contains("already has a child tagged \"a\""));
+        assert_eq!(tree.node(a1).unwrap().parent(), Some(a));
+        assert_eq!(tree.node(root).unwrap().children(), &[a, b]);
+
+        let tags: Vec<&str> = tree.iter().map(|n| n.tag.as_str()).collect();
+        assert_eq!(tags, vec!["root", "a", "a1", "b"]);
+
+        let removed = tree.remove(a).unwrap();
+        assert!(!tree.contains(a) && !tree.contains(a1));
+        assert!(tree.remove(a).is_err());
+        assert!(tree.remove(root).is_err());
+        assert_eq!(removed.iter().count(), 2);
+        assert_eq!(removed.child_by_path("a1").unwrap().tag, "a1");
+
+        // the freed slot is reused, but the old handle must not reach the new node
+        let c = tree.add_child(b, ObjectNode::new("c")).unwrap();
+        assert!(tree.node(a).is_none() && tree.node(a1).is_none());
+        assert_eq!(tree.node(c).unwrap().tag, "c");
+        assert_eq!(tree.node(b).unwrap().tag, "b");
+
+        let moved = tree.add_tree(root, removed).unwrap();
+        assert_eq!(tree.child_by_path("a/a1").unwrap().parent(), Some(moved));
+        let tags: Vec<&str> = tree.iter().map(|n| n.tag.as_str()).collect();
+        assert_eq!(tags, vec!["root", "b", "c", "a", "a1"]);
+
+        let global = tree.generate_global_nodes();
+        assert_eq!(global.node(c).unwrap().tag, "c");
+        assert_eq!(global.node(moved).unwrap().children().len(), 1);
+    }
+
+    #[test]
+    fn test_reparent(){
+        let triangle = || Polygon::new(&Vec3{ x: 0.0, y: 0.0, z: 0.0 }, &Vec3{ x: 1.0, y: 0.0, z: 0.0 }, &Vec3{ x: 0.0, y: 1.0, z: 0.0 });
+        let mut tree: ObjectNodeTree<PresetAsciaEnvironment, Local> = ObjectNodeTree::new(ObjectNode::new("root"));
+        let mut a = ObjectNode::new("a");
+        a.position = Vec3{ x: 1.0, y: 2.0, z: 3.0 };
+        a.direction = Quaternion::new(&Vec3{ x: 0.0, y: 1.0, z: 0.0 }, PI / 3.0, 1.0);
+        a.scale = Vec3{ x: 2.0, y: 2.0, z: 2.0 };
+        let a = tree.add_child(tree.root(), a).unwrap();
+        let mut b = ObjectNode::new("b");
+        b.position = Vec3{ x: -4.0, y: 0.0, z: 1.0 };
+        b.direction = Quaternion::new(&Vec3{ x: 1.0, y: 0.0, z: 1.0 }, PI / 5.0, 1.0);
+        b.scale = Vec3{ x: 1.5, y: 1.5, z: -1.5 };
+        let b = tree.add_child(tree.root(), b).unwrap();
+        let mut stretched = ObjectNode::new("stretched");
+        stretched.scale = Vec3{ x: 1.0, y: 3.0, z: 0.5 };
+        let stretched = tree.add_child(tree.root(), stretched).unwrap();
+        let mut c = ObjectNode::from("c", vec![triangle()]);
+        c.position = Vec3{ x: 0.0, y: 1.0, z: -2.0 };
+        c.direction = Quaternion::new(&Vec3{ x: 0.0, y: 0.0, z: 1.0 }, PI / 4.0, 1.0);
+        let c = tree.add_child(a, c).unwrap();
+
+        let mut global = tree.generate_global_nodes();
+        let before = tree.generate_global_nodes();
+        assert!(tree.reparent(c, stretched, &global).err().unwrap().contains("non-uniform scale"));
+        tree.reparent(c, b, &global).unwrap();
+        assert_eq!(tree.node(c).unwrap().parent(), Some(b));
+        assert!(tree.node(a).unwrap().children().is_empty());
+        assert!(tree.child_by_path("b/c").is_some());
+
+        tree.update_global_nodes(&mut global);
+        let (old, new) = (before.node(c).unwrap(), global.node(c).unwrap());
+        let close = |p: Vec3, q: Vec3| (p - q).norm() < 1e-4;
+        assert!(close(old.position, new.position));
+        assert!(close(old.scale, new.scale));
+        assert!(close(old.polygons[0].poses.v2, new.polygons[0].poses.v2));
+        assert!(close(old.polygons[0].poses.v3, new.polygons[0].poses.v3));
+
+        assert!(tree.reparent(b, c, &global).err().unwrap().contains("own descendant"));
+        assert!(tree.reparent(tree.root(), a, &global).is_err());
+        tree.add_child(a, ObjectNode::new("c")).unwrap();
+        assert!(tree.reparent(c, a, &global).err().unwrap().contains("already has a child"));
+    }
+
+    #[test]
+    fn test_raycast(){
+        let mut tree: ObjectNodeTree<PresetAsciaEnvironment, Local> = ObjectNodeTree::new(ObjectNode::new("root"));
+        let camera = tree.add_child(tree.root(), ObjectNode::new("camera")).unwrap();
+        let container = tree.add_child(tree.root(), ObjectNode::new("container")).unwrap();
+        let mut cube = ObjectNode::from("cube", PrimitiveGenerator::cube(10.0, PresetPolygonMaterial::default()));
+        cube.position.x = 50.0;
+        tree.add_child(container, cube).unwrap();
+        let mut dust = ObjectNode::new("dust");
+        dust.c_particles.push(CParticle{
+            position: Vec3{ x: 30.0, y: 0.0, z: 30.0 },
+            velocity: Default::default(),
+            c: '*',
+            threshold: 1.0,
+            mode: CParticleMode::SPHERE,
+            material: Default::default(),
+            _ph: Default::default(),
+        });
+        tree.add_child(tree.root(), dust).unwrap();
+        let global = tree.generate_global_nodes();
+
+        let camera_node = global.node(camera).unwrap();
+        let ray = SimpleCamera::<PresetAsciaEnvironment>::default().screen_ray(camera_node, 19, 10, 40, 20);
+        let hit = global.raycast(&ray).unwrap();
+        assert_eq!(hit.path, "container/cube");
+        assert_eq!(hit.node.tag, "cube");
+        assert!(matches!(hit.target, RaycastTarget::Polygon(_)));
+        assert!(f32::abs(hit.position.x - 45.0) < 1e-4);
+        assert!((hit.normal - Vec3{ x: -1.0, y: 0.0, z: 0.0 }).norm() < 1e-4);
+        assert!(hit.depth > 45.0 && hit.depth < 46.0);
+
+        let hit = global.raycast(&Ray{
+            position: Default::default(),
+            direction: Vec3{ x: 1.0, y: 0.0, z: 1.0 },
+        }).unwrap();
+        assert_eq!(hit.path, "dust");
+        assert!(matches!(hit.target, RaycastTarget::CParticle(p) if p.c == '*'));
+        assert!(global.raycast(&Ray{
+            position: Default::default(),
+            direction: Vec3{ x: -1.0, y: 0.0, z: 0.0 },
+        }).is_none());
+    }
+
+    #[test]
+    fn test_spatial_queries(){
+        let mut tree: ObjectNodeTree<PresetAsciaEnvironment, Local> = ObjectNodeTree::new(ObjectNode::new("root"));
+        let group = tree.add_child(tree.root(), ObjectNode::new("group")).unwrap();
+        for (i, x) in [10.0, 60.0, -40.0].iter().enumerate(){
+            let mut cube = ObjectNode::from(&format!("cube {}", i), PrimitiveGenerator::cube(2.0, PresetPolygonMaterial::default()));
+            cube.position.x = *x;
+            tree.add_child(group, cube).unwrap();
+        }
+        let mut dust = ObjectNode::new("dust");
+        dust.position.z = 30.0;
+        dust.c_particles.push(CParticle{
+            position: Default::default(),
+            velocity: Default::default(),
+            c: '*',
+            threshold: 1.0,
+            mode: CParticleMode::SPHERE,
+            material: Default::default(),
+            _ph: Default::default(),
+        });
+        let dust = tree.add_child(tree.root(), dust).unwrap();
+        let mut global = tree.generate_global_nodes();
+
+        assert!(global.root_node().bounds().is_none());
+        let b = global.node(group).unwrap().subtree_bounds().unwrap();
+        assert_eq!((b.min().x, b.max().x), (-41.0, 61.0));
+        assert_eq!(global.query_radius(&Vec3::default(), 20.0), vec!["group/cube 0"]);
+        assert_eq!(global.query_radius(&Vec3::default(), 40.0), vec!["group/cube 0", "group/cube 2", "dust"]);
+        assert_eq!(global.query_aabb(&AABB3D::generate_2(&Vec3{ x: -1.0, y: -1.0, z: 28.0 }, &Vec3{ x: 1.0, y: 1.0, z: 29.5 })), vec!["dust"]);
+
+        let frustum = Frustum::new(&Vec3::default(), &Quaternion::default(), (PI / 2.0, PI / 2.0));
+        assert_eq!(global.query_frustum(&frustum), vec!["group/cube 0", "group/cube 1"]);
+        let behind = Frustum::new(&Vec3::default(), &Quaternion::new(&Vec3{ x: 0.0, y: 1.0, z: 0.0 }, PI, 1.0), (PI / 2.0, PI / 2.0));
+        assert_eq!(global.query_frustum(&behind), vec!["group/cube 2"]);
+
+        // bounds follow incremental updates
+        tree.node_mut(dust).unwrap().position.z = 300.0;
+        tree.update_global_nodes(&mut global);
+        assert_eq!(global.root_node().subtree_bounds().unwrap().max().z, 301.0);
+        assert_eq!(global.query_radius(&Vec3::default(), 40.0), vec!["group/cube 0", "group/cube 2"]);
+    }
+
+    #[test]
+    fn test_update_global_nodes(){
+        let triangle = || Polygon::new(&Vec3{ x: 0.0, y: 0.0, z: 0.0 }, &Vec3{ x: 1.0, y: 0.0, z: 0.0 }, &Vec3{ x: 0.0, y: 1.0, z: 0.0 });
+        let mut root: ObjectNodeTree<PresetAsciaEnvironment, Local> = ObjectNodeTree::new(ObjectNode::new("root"));
+        let container = root.add_child(root.root(), ObjectNode::from("container", vec![triangle()])).unwrap();
+        let inner = root.add_child(container, ObjectNode::from("inner", vec![triangle()])).unwrap();
+        let stat = root.add_child(root.root(), ObjectNode::from("static", vec![triangle()])).unwrap();
+
+        let mut global = root.generate_global_nodes();
+        root.update_global_nodes(&mut global);
+        assert!(!root.root_node().is_dirty());
+        let static_polygons = global.node(stat).unwrap().polygons.as_ptr();
+
+        root.node_mut(container).unwrap().direction = Quaternion::new(&Vec3{ x: 0.0, y: 1.0, z: 0.0 }, PI / 2.0, 1.0);
+        root.remove(inner).unwrap();
+        let added = root.add_child(container, ObjectNode::from("added", vec![triangle()])).unwrap();
+        assert!(root.root_node().is_dirty());
+        root.update_global_nodes(&mut global);
+
+        let expected = root.generate_global_nodes();
+        assert_eq!(global.node(added).unwrap().polygons[0].poses.v2, expected.node(added).unwrap().polygons[0].poses.v2);
+        assert!(global.node(inner).is_none());
+        assert!(global.child_by_path("container/inner").is_none());
+        assert_eq!(global.node(stat).unwrap().polygons.as_ptr(), static_polygons);
+
+        root.root_node_mut().position.x = 5.0;
+        root.update_global_nodes(&mut global);
+        assert_eq!(global.node(stat).unwrap().polygons[0].poses.v1.x, 5.0);
+    }
+
+    #[test]
+    fn test_mesh_instancing(){
+        let mesh = Rc::new(Mesh::new(PrimitiveGenerator::cube(10.0, PresetPolygonMaterial::default())));
+        let mut tree: ObjectNodeTree<PresetAsciaEnvironment, Local> = ObjectNodeTree::new(ObjectNode::new("root"));
+        let mut flat = ObjectNode::from("flat", PrimitiveGenerator::cube(10.0, PresetPolygonMaterial::default()));
+        let mut instance = ObjectNode::from_mesh("instance", MeshInstance::new(mesh.clone()));
+        for n in [&mut flat, &mut instance]{
+            n.position = Vec3{ x: 50.0, y: 3.0, z: -2.0 };
+            n.direction = Quaternion::new(&Vec3{ x: 0.0, y: 1.0, z: 0.0 }, PI / 6.0, 1.0);
+            n.scale = Vec3{ x: 2.0, y: -1.0, z: 1.0 };
+        }
+        let flat = tree.add_child(tree.root(), flat).unwrap();
+        let instance = tree.add_child(tree.root(), instance).unwrap();
+        let mut tinted = MeshInstance::new(mesh.clone());
+        tinted.material = Some(PresetPolygonMaterial::FlatMaterial(Default::default()));
+        let mut tinted = ObjectNode::from_mesh("tinted", tinted);
+        tinted.position.z = 100.0;
+        let tinted = tree.add_child(tree.root(), tinted).unwrap();
+        let global = tree.generate_global_nodes();
+        assert_eq!(Rc::strong_count(&mesh), 5);
+
+        let (f, i) = (global.node(flat).unwrap(), global.node(instance).unwrap());
+        assert!(i.polygons.is_empty());
+        assert_eq!(i.mesh_polygons().len(), f.polygons.len());
+        for (a, b) in i.mesh_polygons().iter().zip(f.polygons.iter()){
+            assert!((a.poses.v1 - b.poses.v1).norm() < 1e-4 && (a.poses.v2 - b.poses.v2).norm() < 1e-4 && (a.poses.v3 - b.poses.v3).norm() < 1e-4);
+        }
+        let (fb, ib) = (f.bounds().unwrap(), i.bounds().unwrap());
+        assert!((fb.min() - ib.min()).norm() < 1e-4 && (fb.max() - ib.max()).norm() < 1e-4);
+        assert!(matches!(global.node(tinted).unwrap().mesh_polygons()[0].material, PresetPolygonMaterial::FlatMaterial(_)));
+
+        let ray = Ray{
+            position: Vec3{ x: 0.0, y: 3.0, z: -2.0 },
+            direction: Vec3{ x: 2.0, y: 0.0, z: 0.0 },
+        };
+        let f_hit = ray.project(&f.polygons, &|_|{false}).unwrap();
+        let hit = global.raycast(&ray).unwrap();
+        assert!(matches!(hit.target, RaycastTarget::MeshPolygon(_) | RaycastTarget::Polygon(_)));
+        assert!(f32::abs(hit.depth - f_hit.depth) < 1e-3);
+        assert!((hit.position - f_hit.position).norm() < 1e-3);
+        assert!(f32::abs(f32::abs(hit.normal * f_hit.normal) - 1.0) < 1e-4);
+
+        tree.remove(flat).unwrap();
+        let global = tree.generate_global_nodes();
+        let hit = global.raycast(&ray).unwrap();
+        assert_eq!(hit.path, "instance");
+        assert!(matches!(hit.target, RaycastTarget::MeshPolygon(_)));
+        assert!(f32::abs(hit.depth - f_hit.depth) < 1e-3);
+    }
+
+    #[test]
+    fn test_visibility_and_layers(){
+        let mut root: ObjectNodeTree<PresetAsciaEnvironment, Local> = ObjectNodeTree::new(ObjectNode::new("root"));
+        let mut hud = ObjectNode::new("hud");
+        hud.layers = 0b10;
+        let hud = root.add_child(root.root(), hud).unwrap();
+        let mut gizmo = ObjectNode::new("gizmo");
+        gizmo.layers = 0b110;
+        let gizmo = root.add_child(hud, gizmo).unwrap();
+        let label = root.add_child(gizmo, ObjectNode::new("label")).unwrap();
+        let world = root.add_child(root.root(), ObjectNode::new("world")).unwrap();
+
+        let mut global = root.generate_global_nodes();
+        assert_eq!(global.node(gizmo).unwrap().layers, 0b10);
+        assert_eq!(global.node(label).unwrap().layers, 0b10);
+        assert_eq!(global.node(world).unwrap().layers, ALL_LAYERS);
+        assert!(global.node(label).unwrap().is_visible_to(0b10));
+        assert!(!global.node(label).unwrap().is_visible_to(!0b10));
+        assert!(global.node(world).unwrap().is_visible_to(!0b10));
+
+        root.node_mut(hud).unwrap().visible = false;
+        root.update_global_nodes(&mut global);
+        assert!(!global.node(label).unwrap().visible);
+        assert!(!global.node(label).unwrap().is_visible_to(ALL_LAYERS));
+        assert!(global.node(world).unwrap().visible);
+
+        root.node_mut(hud).unwrap().visible = true;
+        root.node_mut(hud).unwrap().layers = 0b100;
+        root.update_global_nodes(&mut global);
+        assert!(global.node(label).unwrap().visible);
+        assert_eq!(global.node(label).unwrap().layers, 0b100);
+    }
+
+    #[test]
+    fn test_smooth_normals(){
+        let mut polygons: Vec<Polygon<PresetAsciaEnvironment, Local>> = PrimitiveGenerator::cube(2.0, PresetPolygonMaterial::default());
+        // the edges of a cube are right angles, so they stay hard below that
+        smooth_normals(&mut polygons, PI / 3.0);
+        for p in &polygons{
+            let n = p.normals.unwrap();
+            for v in [n.v1, n.v2, n.v3]{
+                assert!((v - p.normal()).norm() < 1e-5);
+            }
+        }
+        // above it every corner averages its three faces and points along the diagonal
+        smooth_normals(&mut polygons, PI * 0.75);
+        for p in &polygons{
+            let n = p.normals.unwrap();
+            assert!(f32::abs(f32::abs(n.v1 * p.poses.v1.normalize()) - 1.0) < 1e-5);
+        }
+
+        let p = &polygons[0];
+        let center = (p.poses.v1 + p.poses.v2 + p.poses.v3) / 3.0;
+        let ray = Ray{
+            position: center * 3.0,
+            direction: center * -1.0,
+        };
+        let i = ray.project(&polygons, &|_|{false}).unwrap();
+        assert!((i.normal - i.polygon.normal_at(&i.position_on_polygon)).norm() < 1e-6);
+        assert!(f32::abs(i.normal * i.polygon.normal()) < 0.99);
+
+        // a mirrored copy swaps v2 and v3, the same point on it has the same normal in the world
+        let placement = Placement::trs(&Vec3::default(), &Quaternion::default(), &Vec3{ x: -1.0, y: 2.0, z: 1.0 });
+        let copy = placement.polygon(p, p.material.clone());
+        let at = Vec2{ x: 0.2, y: 0.5 };
+        assert!((copy.normal_at(&placement.position_on_polygon(&at)) - placement.normal(&p.normal_at(&at))).norm() < 1e-5);
+    }
+
+    #[test]
+    fn test_textured_material(){
+        let red = ColorRGBf32{ r: 1.0, g: 0.0, b: 0.0 };
+        let blue = ColorRGBf32{ r: 0.0, g: 0.0, b: 1.0 };
+        let mut material = TexturedMaterial::new(Rc::new(Texture::new(2, 1, vec![red, blue]).unwrap()));
+        material.filter = TextureFilter::Nearest;
+        material.lit = false;
+        // u runs along +z and v along +y
+        let mut polygon: Polygon<PresetAsciaEnvironment, Global> = Polygon::new(&Vec3{ x: 0.0, y: 0.0, z: 0.0 }, &Vec3{ x: 0.0, y: 0.0, z: 1.0 }, &Vec3{ x: 0.0, y: 1.0, z: 0.0 });
+        polygon.uvs = Some([Vec2{ x: 0.0, y: 0.0 }, Vec2{ x: 1.0, y: 0.0 }, Vec2{ x: 0.0, y: 1.0 }]);
+        polygon.material = PresetPolygonMaterial::TexturedMaterial(material.clone());
+
+        let engine = AsciaEngine::<PresetAsciaEnvironment>::new(4, 4);
+        let camera = SimpleCamera::<PresetAsciaEnvironment>::default();
+        let color_at = |polygon: &Polygon<PresetAsciaEnvironment, Global>, z: f32| {
+            let i = Ray{
+                position: Vec3{ x: -1.0, y: 0.2, z: z },
+                direction: Vec3{ x: 1.0, y: 0.0, z: 0.0 },
+            }.project(polygon, &|_|{false}).unwrap();
+            return <_ as Material<PresetAsciaEnvironment, SimpleCamera<PresetAsciaEnvironment>, Polygon<PresetAsciaEnvironment, Global>>>::calc_color(&&material, &i, &engine, &camera, engine.genesis_global.root_node(), &vec![]).0;
+        };
+        assert_eq!((color_at(&polygon, 0.2).r, color_at(&polygon, 0.2).b), (1.0, 0.0));
+        assert_eq!((color_at(&polygon, 0.7).r, color_at(&polygon, 0.7).b), (0.0, 1.0));
+
+        // without uvs the whole polygon takes the average
+        polygon.uvs = None;
+        assert_eq!((color_at(&polygon, 0.7).r, color_at(&polygon, 0.7).b), (0.5, 0.5));
+
+        // a mirrored copy keeps the uvs on the same points
+        let mut local: Polygon<PresetAsciaEnvironment, Local> = Polygon::new(&Vec3{ x: 0.0, y: 0.0, z: 0.0 }, &Vec3{ x: 0.0, y: 0.0, z: 1.0 }, &Vec3{ x: 0.0, y: 1.0, z: 0.0 });
+        local.uvs = Some([Vec2{ x: 0.0, y: 0.0 }, Vec2{ x: 1.0, y: 0.0 }, Vec2{ x: 0.0, y: 1.0 }]);
+        let placement = Placement::trs(&Vec3::default(), &Quaternion::default(), &Vec3{ x: 1.0, y: 1.0, z: -1.0 });
+        let copy = placement.polygon(&local, local.material.clone());
+        let at = Vec2{ x: 0.7, y: 0.1 };
+        let (a, b) = (copy.uv_at(&placement.position_on_polygon(&at)).unwrap(), local.uv_at(&at).unwrap());
+        assert!(f32::abs(a.x - b.x) < 1e-6 && f32::abs(a.y - b.y) < 1e-6);
+    }
+
+    #[test]
+    fn test_world_local_conversion(){
+        let mut root: ObjectNodeTree<PresetAsciaEnvironment, Local> = ObjectNodeTree::new(ObjectNode::new("root"));
+        let mut arm = ObjectNode::new("arm");
+        arm.position = Vec3{
+            x: 1.0,
+            y: 2.0,
+            z: 3.0,
+        };
+        arm.direction = Quaternion::new(&Vec3{
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        }, PI / 2.0, 1.0);
+        arm.scale = Vec3{
+            x: 2.0,
+            y: 1.0,
+            z: 1.0,
+        };
+        let arm = root.add_child(root.root(), arm).unwrap();
+        let mut hand = ObjectNode::new("hand");
+        hand.position = Vec3{
+            x: 1.0,
+            y: 0.0,
+            z: 0.0,
+        };
+        let hand = root.add_child(arm, hand).unwrap();
+        let mut finger = ObjectNode::new("finger");
+        finger.position = Vec3{
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        };
+        finger.direction = Quaternion::new(&Vec3{
+            x: 0.0,
+            y: 0.0,
+            z: 1.0,
+        }, PI / 3.0, 1.0);
+        let finger_local = finger.transform();
+        let finger = root.add_child(arm, finger).unwrap();
+        let global = root.generate_global_nodes();
+        let (arm_node, hand_node) = (global.node(arm).unwrap(), global.node(hand).unwrap());
+
+        // the origin of a node is its global position and the transform agrees with the engine's placement
+        assert!((arm_node.point_to_world(&Vec3::default()) - arm_node.position).norm() < 1e-5);
+        let p = Vec3{
+            x: 0.5,
+            y: -1.0,
+            z: 2.0,
+        };
+        assert!((arm_node.point_to_world(&p) - arm_node.placement().point(&p)).norm() < 1e-5);
+        assert!((arm_node.point_to_local(&arm_node.point_to_world(&p)) - p).norm() < 1e-5);
+        assert!((arm_node.vector_to_local(&arm_node.vector_to_world(&p)) - p).norm() < 1e-5);
+
+        // the finger is turned inside the arm's stretch, so its transform is the arm's applied after its own
+        let q = Vec3{
+            x: 1.0,
+            y: 0.5,
+            z: 0.0,
+        };
+        assert!((global.node(finger).unwrap().point_to_world(&q) - arm_node.point_to_world(&finger_local.transform_point(&q))).norm() < 1e-5);
+
+        // in the arm's space the hand is where it was placed, in the world the stretched +x of the arm is turned to -z
+        let m = global.relative_transform(hand, arm).unwrap();
+        assert!((m.transform_point(&Vec3::default()) - Vec3{
+            x: 1.0,
+            y: 0.0,
+            z: 0.0,
+        }).norm() < 1e-5);
+        assert!((hand_node.position - Vec3{
+            x: 1.0,
+            y: 2.0,
+            z: 1.0,
+        }).norm() < 1e-5);
+
+        // a normal stays perpendicular to the surface under the non-uniform scale
+        let n = arm_node.normal_to_world(&Vec3{
+            x: 1.0,
+            y: 1.0,
+            z: 0.0,
+        });
+        let tangent = arm_node.vector_to_world(&Vec3{
+            x: 1.0,
+            y: -1.0,
+            z: 0.0,
+        });
+        assert!(f32::abs(n * tangent) < 1e-5 && f32::abs(n.norm() - 1.0) < 1e-5);
     }
 }
 
@@ -1013,28 +2518,39 @@ impl<'a> ViewportStdout<'a> {
     }
 }
 
+pub enum RaycastTarget<'a, E: AsciaEnvironment + ?Sized + 'static>{
+    Polygon(&'a Polygon<E, Global>),
+    // a polygon of the node's instanced mesh, in the mesh's own space
+    MeshPolygon(&'a Polygon<E, Local>),
+    CParticle(&'a CParticle<E, Global>),
+}
+
+pub struct RaycastHit<'a, E: AsciaEnvironment + ?Sized + 'static>{
+    pub node: &'a ObjectNode<E, Global>,
+    pub path: String,
+    pub target: RaycastTarget<'a, E>,
+    pub position: Vec3,
+    pub normal: Vec3,
+    pub depth: f32,
+}
+
 pub struct AsciaEngine<E: AsciaEnvironment + ?Sized>{
-    pub genesis_local: ObjectNode<E, Local>,
-    pub genesis_global: ObjectNode<E, Global>,
+    pub genesis_local: ObjectNodeTree<E, Local>,
+    pub genesis_global: ObjectNodeTree<E, Global>,
     pub viewport: RefCell<Box<dyn Viewport>>,
+    pub animator: Animator,
     engine_time:Duration,
     engine_started:Instant
 }
 
 impl<E: AsciaEnvironment> AsciaEngine<E>{
     pub fn new(width:usize,height:usize) -> Self{
+        let genesis_local = ObjectNodeTree::new(ObjectNode::new("genesis"));
         return AsciaEngine{
-            genesis_local: ObjectNode::new("genesis"),
-            genesis_global: ObjectNode{
-                tag: "genesis".to_string(),
-                attribute: Rc::new(RefCell::new(None)),
-                position: Default::default(),
-                direction: Default::default(),
-                polygons: vec![],
-                c_particles: vec![],
-                children: Default::default(),
-            },
+            genesis_global: genesis_local.generate_global_nodes(),
+            genesis_local: genesis_local,
             viewport: RefCell::new(Box::new(ViewportStdout::new(width, height))),
+            animator: Animator::new(),
             engine_time:Duration::ZERO,
             engine_started:Instant::now()
         }
@@ -1067,7 +2583,35 @@ impl<E: AsciaEnvironment> AsciaEngine<E>{
         self.engine_time = dur.clone();
     }
     
+    // the animations are sampled at the current engine time first, so that they are reflected in the global nodes
     pub fn update_global_nodes(&mut self){
-        self.genesis_global = self.genesis_local.generate_global_nodes();
+        self.animator.apply(self.engine_time, &mut self.genesis_local);
+        self.genesis_local.update_global_nodes(&mut self.genesis_global);
+    }
+
+    pub fn play_animation(&mut self, clip: Rc<AnimationClip>, mode: AnimationMode) -> &mut AnimationState{
+        return self.animator.play(clip, mode, self.engine_time);
+    }
+
+    pub fn reparent(&mut self, id: NodeId, new_parent: NodeId) -> Result<(), String>{
+        self.update_global_nodes();
+        return self.genesis_local.reparent(id, new_parent, &self.genesis_global);
+    }
+
+    // the nearest polygon or particle of genesis_global hit by the ray, as of the last update of the global nodes
+    pub fn raycast(&self, ray: &Ray) -> Option<RaycastHit<'_, E>>{
+        return self.genesis_global.raycast(ray);
+    }
+
+    // casts the ray the camera on camera_node renders into cell (x, y) of the viewport
+    pub fn raycast_screen(&self, camera_node: &ObjectNode<E, Global>, x: usize, y: usize) -> Result<Option<RaycastHit<'_, E>>, String>{
+        if let Some(a) = &*camera_node.attribute.borrow(){
+            if let Some(camera) = a.camera(){
+                let width = self.viewport.borrow().width();
+                let height = self.viewport.borrow().height();
+                return Ok(self.raycast(&camera.screen_ray(camera_node, x, y, width, height)));
+            }
+        }
+        return Err("given node does not contain camera attribute".to_string());
     }
 }
@@ -0,0 +1,10 @@
+pub mod obj;
+pub mod tris;
+pub mod bpt;
+pub mod stl;
+pub mod ply;
+pub mod pnm;
+#[cfg(feature = "gltf")]
+pub mod gltf;
+#[cfg(feature = "export")]
+pub mod scene;
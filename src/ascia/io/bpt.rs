@@ -0,0 +1,103 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::str::FromStr;
+use crate::ascia::bezier::BezierPatch;
+use crate::ascia::io::tris::parse_vertex;
+use crate::ascia::math::Vec3;
+
+// the patch count, then for each patch its degrees "3 3" followed by 16 control points a line, row by row
+// https://users.cs.utah.edu/~dejohnso/models/teapot.html
+pub fn load_bpt<P: AsRef<Path> + ?Sized>(path: &P) -> Result<Vec<BezierPatch>, String>{
+    if let Ok(f) = File::open(path){
+        return parse_bpt(BufReader::new(f));
+    }
+    return Err(format!("could not open file: {:?}", path.as_ref()));
+}
+
+pub fn parse_bpt<R: BufRead>(reader: R) -> Result<Vec<BezierPatch>, String>{
+    let mut lines = reader.lines().enumerate().filter(|(_, l)| {
+        match l {
+            Ok(s) => { !s.trim().is_empty() }
+            Err(_) => { true }
+        }
+    });
+
+    let num_patch = match lines.next() {
+        Some((n, Ok(s))) => {
+            match usize::from_str(s.trim()) {
+                Ok(c) => { c }
+                Err(_) => { return Err(format!("line {}: could not parse \"{}\" as a patch count", n + 1, s.trim())); }
+            }
+        }
+        Some((n, Err(e))) => { return Err(format!("line {}: {}", n + 1, e)); }
+        None => { return Err("file is empty".to_string()); }
+    };
+
+    let truncated = |i: usize| format!("file is truncated: {} patches are declared but only {} are complete", num_patch, i);
+    // the count comes from the file, so it does not size the allocation
+    let mut patches = vec![];
+    for i in 0..num_patch{
+        match lines.next() {
+            Some((n, Ok(s))) => {
+                let degrees: Vec<&str> = s.split_whitespace().collect();
+                if degrees != ["3", "3"]{
+                    return Err(format!("line {}: only bicubic patches are supported, found degrees \"{}\"", n + 1, s.trim()));
+                }
+            }
+            Some((n, Err(e))) => { return Err(format!("line {}: {}", n + 1, e)); }
+            None => { return Err(truncated(i)); }
+        }
+        let mut points = [[Vec3::default(); 4]; 4];
+        for p in points.iter_mut().flatten(){
+            *p = match lines.next() {
+                Some((n, Ok(s))) => { parse_vertex(&s, n + 1)? }
+                Some((n, Err(e))) => { return Err(format!("line {}: {}", n + 1, e)); }
+                None => { return Err(truncated(i)); }
+            };
+        }
+        patches.push(BezierPatch{
+            points: points,
+        });
+    }
+
+    if let Some((n, _)) = lines.next(){
+        return Err(format!("line {}: {} patches are declared but the file continues", n + 1, num_patch));
+    }
+    return Ok(patches);
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::ascia::io::bpt::parse_bpt;
+    use crate::ascia::math::Vec3;
+
+    fn patch_text(degrees: &str) -> String{
+        let mut s = format!("{}\n", degrees);
+        for i in 0..4{
+            for j in 0..4{
+                s.push_str(&format!("{} {} 0.5\n", j, i));
+            }
+        }
+        return s;
+    }
+
+    #[test]
+    fn test_parse_bpt(){
+        let text = format!("2\n{}\n{}", patch_text("3 3"), patch_text(" 3  3 "));
+        let patches = parse_bpt(text.as_bytes()).unwrap();
+        assert_eq!(patches.len(), 2);
+        assert_eq!(patches[1].points[0][3], Vec3{ x: 3.0, y: 0.0, z: 0.5 });
+        assert_eq!(patches[1].points[2][1], Vec3{ x: 1.0, y: 2.0, z: 0.5 });
+    }
+
+    #[test]
+    fn test_parse_bpt_validation(){
+        assert!(parse_bpt(format!("1\n{}", patch_text("2 3")).as_bytes()).err().unwrap().starts_with("line 2: only bicubic"));
+        assert!(parse_bpt(format!("2\n{}", patch_text("3 3")).as_bytes()).err().unwrap().contains("truncated"));
+        assert!(parse_bpt(format!("1\n{}0 0 0\n", patch_text("3 3")).as_bytes()).err().unwrap().starts_with("line 19:"));
+        assert!(parse_bpt(format!("1\n{}", patch_text("3 3").replace("2 1 0.5", "2 1")).as_bytes()).err().unwrap().starts_with("line 9:"));
+        assert!(parse_bpt("".as_bytes()).is_err());
+        assert!(parse_bpt("18446744073709551615\n".as_bytes()).err().unwrap().contains("truncated"));
+    }
+}
@@ -0,0 +1,631 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use serde_json::Value;
+use crate::ascia::color::ColorRGBf32;
+use crate::ascia::core::{AsciaEnvironment, LambertMaterial, Local, NodeId, ObjectNode, ObjectNodeAttributeDispatcher, ObjectNodeTree, Polygon, PresetLight, PresetObjectNodeAttributeDispatcher, PresetPolygonMaterial};
+use crate::ascia::lights::PointLight;
+use crate::ascia::math::{Matrix33, Quaternion, Vec2, Vec3, Vec4};
+
+const GLB_MAGIC: u32 = 0x46546C67;
+const GLB_CHUNK_JSON: u32 = 0x4E4F534A;
+const GLB_CHUNK_BIN: u32 = 0x004E4942;
+
+pub fn load_gltf<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, Lights=PresetLight, ObjectNodeAttributes=PresetObjectNodeAttributeDispatcher<E>>, P: AsRef<Path> + ?Sized>(path: &P, tag: &str) -> Result<ObjectNodeTree<E, Local>, String>{
+    let mut buf = vec![];
+    if let Ok(mut f) = File::open(path){
+        if let Err(e) = f.read_to_end(&mut buf){
+            return Err(e.to_string());
+        }
+        let base_dir = path.as_ref().parent().unwrap_or(Path::new(""));
+        return parse_gltf(&buf, base_dir, tag);
+    }
+    return Err(format!("could not open file: {:?}", path.as_ref()));
+}
+
+// accepts both .gltf (json) and .glb (binary container), external buffers are resolved relative to base_dir
+pub fn parse_gltf<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, Lights=PresetLight, ObjectNodeAttributes=PresetObjectNodeAttributeDispatcher<E>>>(data: &[u8], base_dir: &Path, tag: &str) -> Result<ObjectNodeTree<E, Local>, String>{
+    let (json, bin) = if data.len() >= 4 && read_u32(data, 0) == GLB_MAGIC {
+        split_glb(data)?
+    } else {
+        (data, None)
+    };
+    let doc: Value = match serde_json::from_slice(json) {
+        Ok(v) => { v }
+        Err(e) => { return Err(format!("invalid gltf json: {}", e)); }
+    };
+
+    let mut buffers = vec![];
+    for (i, b) in array(&doc, "buffers").iter().enumerate(){
+        buffers.push(load_buffer(b, i, base_dir, bin)?);
+    }
+    let loader = GltfLoader{
+        doc: &doc,
+        buffers: buffers,
+    };
+
+    let nodes = array(&doc, "nodes");
+    let roots: Vec<usize> = match doc.get("scenes").and_then(|s| s.get(doc.get("scene").and_then(|s| s.as_u64()).unwrap_or(0) as usize)) {
+        Some(scene) => {
+            array(scene, "nodes").iter().filter_map(|n| n.as_u64()).map(|n| n as usize).collect()
+        }
+        None => {
+            // without scenes every node that is nobody's child is a root
+            let mut children = HashSet::new();
+            for n in nodes{
+                for c in array(n, "children"){
+                    if let Some(c) = c.as_u64(){
+                        children.insert(c as usize);
+                    }
+                }
+            }
+            (0..nodes.len()).filter(|i| !children.contains(i)).collect()
+        }
+    };
+
+    let mut tree = ObjectNodeTree::new(ObjectNode::new(tag));
+    let root = tree.root();
+    let mut visited = HashSet::new();
+    for i in roots{
+        loader.node(i, &mut visited, &mut tree, root)?;
+    }
+    return Ok(tree);
+}
+
+// the byte size of a component, how it is read and what it is divided by when normalized
+type ComponentType = (usize, fn(&[u8]) -> f64, f64);
+
+struct GltfLoader<'a>{
+    doc: &'a Value,
+    buffers: Vec<Vec<u8>>,
+}
+
+impl<'a> GltfLoader<'a>{
+    fn node<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, Lights=PresetLight, ObjectNodeAttributes=PresetObjectNodeAttributeDispatcher<E>>>(&self, index: usize, visited: &mut HashSet<usize>, tree: &mut ObjectNodeTree<E, Local>, parent: NodeId) -> Result<(), String>{
+        let n = match self.doc.get("nodes").and_then(|n| n.get(index)) {
+            Some(n) => { n }
+            None => { return Err(format!("node {} does not exist", index)); }
+        };
+        if !visited.insert(index){
+            return Err(format!("node {} appears more than once in the hierarchy", index));
+        }
+        let tag = match n.get("name").and_then(|s| s.as_str()) {
+            Some(s) if !s.is_empty() => { s.to_string() }
+            _ => { format!("node {}", index) }
+        };
+        let mut node = ObjectNode::new(&tag);
+
+        if let Some(m) = floats(n, "matrix", 16){
+            // column major, assumes the matrix has no shear
+            node.position = Vec3{
+                x: m[12],
+                y: m[13],
+                z: m[14],
+            };
+            let columns = [0, 4, 8].map(|c| Vec3{ x: m[c], y: m[c + 1], z: m[c + 2] });
+            node.scale = Vec3{
+                x: columns[0].norm(),
+                y: columns[1].norm(),
+                z: columns[2].norm(),
+            };
+            if (columns[0] ^ columns[1]) * columns[2] < 0.0{
+                node.scale.x = -node.scale.x;
+            }
+            let axes = [columns[0] / node.scale.x, columns[1] / node.scale.y, columns[2] / node.scale.z];
+            node.direction = Quaternion::from_rotation_matrix(&Matrix33{
+                v1: axes[0],
+                v2: axes[1],
+                v3: axes[2],
+            });
+        }
+        if let Some(t) = floats(n, "translation", 3){
+            node.position = Vec3{
+                x: t[0],
+                y: t[1],
+                z: t[2],
+            };
+        }
+        if let Some(r) = floats(n, "rotation", 4){
+            node.direction = Quaternion{
+                vec4: Vec4{
+                    w: r[3],
+                    x: r[0],
+                    y: r[1],
+                    z: r[2],
+                },
+            };
+        }
+
+        if let Some(s) = floats(n, "scale", 3){
+            node.scale = Vec3{
+                x: s[0],
+                y: s[1],
+                z: s[2],
+            };
+        }
+
+        if let Some(mesh) = n.get("mesh").and_then(|m| m.as_u64()){
+            node.polygons = self.mesh(mesh as usize)?;
+        }
+
+        if let Some(light) = n.get("extensions").and_then(|e| e.get("KHR_lights_punctual")).and_then(|l| l.get("light")).and_then(|l| l.as_u64()){
+            let l = match self.doc.get("extensions").and_then(|e| e.get("KHR_lights_punctual")).and_then(|l| l.get("lights")).and_then(|l| l.get(light as usize)) {
+                Some(l) => { l }
+                None => { return Err(format!("node {} refers to light {} which does not exist", index, light)); }
+            };
+            if l.get("type").and_then(|t| t.as_str()) == Some("point"){
+                let color = floats(l, "color", 3).unwrap_or(vec![1.0, 1.0, 1.0]);
+                node.attribute = PresetObjectNodeAttributeDispatcher::from(PointLight{
+                    color: ColorRGBf32{
+                        r: color[0],
+                        g: color[1],
+                        b: color[2],
+                    },
+                    power: l.get("intensity").and_then(|i| i.as_f64()).unwrap_or(1.0) as f32,
+                }).make_shared();
+            }
+        }
+
+        let id = add_unique_child(tree, parent, node, index)?;
+        for c in array(n, "children"){
+            let c = match c.as_u64() {
+                Some(c) => { c as usize }
+                None => { return Err(format!("node {} has an invalid child index", index)); }
+            };
+            self.node(c, visited, tree, id)?;
+        }
+        return Ok(());
+    }
+
+    fn mesh<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial>>(&self, index: usize) -> Result<Vec<Polygon<E, Local>>, String>{
+        let mesh = match self.doc.get("meshes").and_then(|m| m.get(index)) {
+            Some(m) => { m }
+            None => { return Err(format!("mesh {} does not exist", index)); }
+        };
+        let mut polygons = vec![];
+        for p in array(mesh, "primitives"){
+            let position = match p.get("attributes").and_then(|a| a.get("POSITION")).and_then(|a| a.as_u64()) {
+                Some(a) => { a as usize }
+                None => { return Err(format!("mesh {} has a primitive without POSITION", index)); }
+            };
+            let (values, components) = self.accessor(position)?;
+            if components != 3{
+                return Err(format!("mesh {}: POSITION must be VEC3", index));
+            }
+            let vertices: Vec<Vec3> = values.chunks(3).map(|v| Vec3{ x: v[0] as f32, y: v[1] as f32, z: v[2] as f32 }).collect();
+            let normals: Option<Vec<Vec3>> = match p.get("attributes").and_then(|a| a.get("NORMAL")).and_then(|a| a.as_u64()) {
+                Some(a) => {
+                    let (values, components) = self.accessor(a as usize)?;
+                    if components != 3 || values.len() != vertices.len() * 3{
+                        return Err(format!("mesh {}: NORMAL must be VEC3 with one normal per vertex", index));
+                    }
+                    Some(values.chunks(3).map(|v| Vec3{ x: v[0] as f32, y: v[1] as f32, z: v[2] as f32 }).collect())
+                }
+                None => { None }
+            };
+            // gltf puts the uv origin at the upper left of the image while textures put it at the lower left
+            let uvs: Option<Vec<Vec2>> = match p.get("attributes").and_then(|a| a.get("TEXCOORD_0")).and_then(|a| a.as_u64()) {
+                Some(a) => {
+                    let (values, components) = self.accessor(a as usize)?;
+                    if components != 2 || values.len() != vertices.len() * 2{
+                        return Err(format!("mesh {}: TEXCOORD_0 must be VEC2 with one coordinate per vertex", index));
+                    }
+                    Some(values.chunks(2).map(|v| Vec2{ x: v[0] as f32, y: 1.0 - v[1] as f32 }).collect())
+                }
+                None => { None }
+            };
+            let indices: Vec<usize> = match p.get("indices").and_then(|i| i.as_u64()) {
+                Some(i) => { self.accessor(i as usize)?.0.iter().map(|i| *i as usize).collect() }
+                None => { (0..vertices.len()).collect() }
+            };
+            if let Some(i) = indices.iter().find(|i| **i >= vertices.len()){
+                return Err(format!("mesh {} refers to vertex {} but only {} vertices exist", index, i, vertices.len()));
+            }
+
+            let material = match p.get("material").and_then(|m| m.as_u64()) {
+                Some(m) => { self.material(m as usize)? }
+                None => { PresetPolygonMaterial::default() }
+            };
+            let triangles: Vec<[usize; 3]> = match p.get("mode").and_then(|m| m.as_u64()).unwrap_or(4) {
+                4 => { indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect() }
+                5 => {
+                    (2..indices.len()).map(|i| {
+                        if i % 2 == 0 { [indices[i - 2], indices[i - 1], indices[i]] } else { [indices[i - 1], indices[i - 2], indices[i]] }
+                    }).collect()
+                }
+                6 => { (2..indices.len()).map(|i| [indices[0], indices[i - 1], indices[i]]).collect() }
+                m => { return Err(format!("mesh {}: primitive mode {} is not supported", index, m)); }
+            };
+            for t in triangles{
+                let mut polygon = Polygon::new(&vertices[t[0]], &vertices[t[1]], &vertices[t[2]]);
+                polygon.material = material.clone();
+                polygon.normals = normals.as_ref().map(|n| Matrix33{
+                    v1: n[t[0]],
+                    v2: n[t[1]],
+                    v3: n[t[2]],
+                });
+                polygon.uvs = uvs.as_ref().map(|uv| [uv[t[0]], uv[t[1]], uv[t[2]]]);
+                polygons.push(polygon);
+            }
+        }
+        return Ok(polygons);
+    }
+
+    fn material(&self, index: usize) -> Result<PresetPolygonMaterial, String>{
+        let m = match self.doc.get("materials").and_then(|m| m.get(index)) {
+            Some(m) => { m }
+            None => { return Err(format!("material {} does not exist", index)); }
+        };
+        let color = m.get("pbrMetallicRoughness").and_then(|p| floats(p, "baseColorFactor", 4)).unwrap_or(vec![1.0, 1.0, 1.0, 1.0]);
+        return Ok(PresetPolygonMaterial::LambertMaterial(LambertMaterial{
+            color: ColorRGBf32{
+                r: color[0],
+                g: color[1],
+                b: color[2],
+            },
+            priority: 0,
+        }));
+    }
+
+    // returns the accessor's elements flattened, with the number of components per element
+    fn accessor(&self, index: usize) -> Result<(Vec<f64>, usize), String>{
+        let a = match self.doc.get("accessors").and_then(|a| a.get(index)) {
+            Some(a) => { a }
+            None => { return Err(format!("accessor {} does not exist", index)); }
+        };
+        if a.get("sparse").is_some(){
+            return Err(format!("accessor {}: sparse accessors are not supported", index));
+        }
+        let components = match a.get("type").and_then(|t| t.as_str()) {
+            Some("SCALAR") => { 1 }
+            Some("VEC2") => { 2 }
+            Some("VEC3") => { 3 }
+            Some("VEC4") => { 4 }
+            t => { return Err(format!("accessor {}: unsupported type {:?}", index, t)); }
+        };
+        let (size, read, max): ComponentType = match a.get("componentType").and_then(|t| t.as_u64()) {
+            Some(5120) => { (1, |b| b[0] as i8 as f64, i8::MAX as f64) }
+            Some(5121) => { (1, |b| b[0] as f64, u8::MAX as f64) }
+            Some(5122) => { (2, |b| i16::from_le_bytes([b[0], b[1]]) as f64, i16::MAX as f64) }
+            Some(5123) => { (2, |b| u16::from_le_bytes([b[0], b[1]]) as f64, u16::MAX as f64) }
+            Some(5125) => { (4, |b| read_u32(b, 0) as f64, u32::MAX as f64) }
+            Some(5126) => { (4, |b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64, 1.0) }
+            t => { return Err(format!("accessor {}: unsupported component type {:?}", index, t)); }
+        };
+        let normalized = a.get("normalized").and_then(|n| n.as_bool()).unwrap_or(false);
+        let count = a.get("count").and_then(|c| c.as_u64()).unwrap_or(0) as usize;
+        let length = match count.checked_mul(components) {
+            Some(l) => { l }
+            None => { return Err(format!("accessor {}: {} elements are too many", index, count)); }
+        };
+
+        let view = match a.get("bufferView").and_then(|v| v.as_u64()) {
+            Some(v) => { v as usize }
+            None => {
+                // the zeros stand in for data, a file cannot ask for more of them than it carries bytes
+                if length > self.buffers.iter().map(|b| b.len()).sum::<usize>(){
+                    return Err(format!("accessor {}: {} elements without a buffer view are more than the buffers hold", index, count));
+                }
+                return Ok((vec![0.0; length], components));
+            }
+        };
+        let v = match self.doc.get("bufferViews").and_then(|v| v.get(view)) {
+            Some(v) => { v }
+            None => { return Err(format!("accessor {} refers to buffer view {} which does not exist", index, view)); }
+        };
+        let buffer = match v.get("buffer").and_then(|b| b.as_u64()).and_then(|b| self.buffers.get(b as usize)) {
+            Some(b) => { b }
+            None => { return Err(format!("buffer view {} refers to a buffer which does not exist", view)); }
+        };
+        let view_offset = v.get("byteOffset").and_then(|o| o.as_u64()).unwrap_or(0) as usize;
+        let view_length = v.get("byteLength").and_then(|o| o.as_u64()).unwrap_or(0) as usize;
+        let view_end = match view_offset.checked_add(view_length) {
+            Some(e) if e <= buffer.len() => { e }
+            _ => { return Err(format!("buffer view {} is out of the buffer's range", view)); }
+        };
+        let data = &buffer[view_offset..view_end];
+        let offset = a.get("byteOffset").and_then(|o| o.as_u64()).unwrap_or(0) as usize;
+        let stride = v.get("byteStride").and_then(|s| s.as_u64()).map(|s| s as usize).unwrap_or(size * components);
+
+        // the last element has to end inside the view before anything is allocated
+        if count > 0{
+            let end = (count - 1).checked_mul(stride).and_then(|o| o.checked_add(offset)).and_then(|o| o.checked_add(size * components));
+            if end.map(|e| e > data.len()).unwrap_or(true){
+                return Err(format!("accessor {} reads past the end of buffer view {}", index, view));
+            }
+        }
+        let mut values = Vec::with_capacity(length);
+        for i in 0..count{
+            for c in 0..components{
+                let value = read(&data[offset + i * stride + c * size..]);
+                values.push(if normalized { f64::max(value / max, -1.0) } else { value });
+            }
+        }
+        return Ok((values, components));
+    }
+}
+
+fn split_glb(data: &[u8]) -> Result<(&[u8], Option<&[u8]>), String>{
+    if data.len() < 12{
+        return Err("glb is truncated: the header needs 12 bytes".to_string());
+    }
+    if read_u32(data, 4) != 2{
+        return Err(format!("unsupported glb version {}", read_u32(data, 4)));
+    }
+    let length = usize::min(read_u32(data, 8) as usize, data.len());
+    let mut json = None;
+    let mut bin = None;
+    let mut offset = 12;
+    while offset + 8 <= length{
+        let chunk_length = read_u32(data, offset) as usize;
+        let chunk_type = read_u32(data, offset + 4);
+        let start = offset + 8;
+        if start + chunk_length > length{
+            return Err(format!("glb is truncated: chunk at byte {} needs {} bytes", offset, chunk_length));
+        }
+        match chunk_type {
+            GLB_CHUNK_JSON if json.is_none() => { json = Some(&data[start..(start + chunk_length)]); }
+            GLB_CHUNK_BIN if bin.is_none() => { bin = Some(&data[start..(start + chunk_length)]); }
+            _ => {}
+        }
+        offset = start + chunk_length;
+    }
+    return match json {
+        Some(j) => { Ok((j, bin)) }
+        None => { Err("glb does not contain a json chunk".to_string()) }
+    };
+}
+
+fn load_buffer(b: &Value, index: usize, base_dir: &Path, bin: Option<&[u8]>) -> Result<Vec<u8>, String>{
+    let data = match b.get("uri").and_then(|u| u.as_str()) {
+        Some(uri) if uri.starts_with("data:") => {
+            match uri.find(";base64,") {
+                Some(i) => { decode_base64(&uri[(i + 8)..]).ok_or(format!("buffer {}: invalid base64 data", index))? }
+                None => { return Err(format!("buffer {}: only base64 data uris are supported", index)); }
+            }
+        }
+        Some(uri) => {
+            if uri.contains("://"){
+                return Err(format!("buffer {}: only local files are supported, found \"{}\"", index, uri));
+            }
+            let path = base_dir.join(decode_percent(uri));
+            let mut buf = vec![];
+            match File::open(&path) {
+                Ok(mut f) => {
+                    if let Err(e) = f.read_to_end(&mut buf){
+                        return Err(e.to_string());
+                    }
+                }
+                Err(_) => { return Err(format!("could not open file: {:?}", path)); }
+            }
+            buf
+        }
+        None => {
+            match bin {
+                Some(b) if index == 0 => { b.to_vec() }
+                _ => { return Err(format!("buffer {} has no uri", index)); }
+            }
+        }
+    };
+    let length = b.get("byteLength").and_then(|l| l.as_u64()).unwrap_or(0) as usize;
+    if data.len() < length{
+        return Err(format!("buffer {} is truncated: {} bytes are declared but only {} exist", index, length, data.len()));
+    }
+    return Ok(data);
+}
+
+fn add_unique_child<E: AsciaEnvironment>(tree: &mut ObjectNodeTree<E, Local>, parent: NodeId, mut child: ObjectNode<E, Local>, index: usize) -> Result<NodeId, String>{
+    if tree.child(parent, &child.tag).is_some(){
+        child.tag = format!("{} {}", child.tag, index);
+    }
+    return tree.add_child(parent, child);
+}
+
+fn array<'a>(v: &'a Value, key: &str) -> &'a [Value]{
+    return v.get(key).and_then(|a| a.as_array()).map(|a| a.as_slice()).unwrap_or(&[]);
+}
+
+fn floats(v: &Value, key: &str, n: usize) -> Option<Vec<f32>>{
+    let a = v.get(key)?.as_array()?;
+    if a.len() != n{
+        return None;
+    }
+    return a.iter().map(|f| f.as_f64().map(|f| f as f32)).collect();
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32{
+    return u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]);
+}
+
+fn decode_base64(s: &str) -> Option<Vec<u8>>{
+    let mut out = Vec::with_capacity(s.len() / 4 * 3);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for c in s.bytes(){
+        let v = match c {
+            b'A'..=b'Z' => { c - b'A' }
+            b'a'..=b'z' => { c - b'a' + 26 }
+            b'0'..=b'9' => { c - b'0' + 52 }
+            b'+' | b'-' => { 62 }
+            b'/' | b'_' => { 63 }
+            b'=' => { break; }
+            _ => { return None; }
+        };
+        acc = (acc << 6) | v as u32;
+        bits += 6;
+        if bits >= 8{
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    return Some(out);
+}
+
+fn decode_percent(s: &str) -> String{
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len(){
+        if bytes[i] == b'%' && i + 2 < bytes.len(){
+            if let Ok(b) = u8::from_str_radix(&s[(i + 1)..(i + 3)], 16){
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    return String::from_utf8_lossy(&out).to_string();
+}
+
+#[cfg(test)]
+mod tests{
+    use std::path::Path;
+    use crate::ascia::core::{ObjectNodeAttributeDispatcher, PresetAsciaEnvironment, PresetPolygonMaterial};
+    use crate::ascia::io::gltf::parse_gltf;
+
+    const TRIANGLE: &str = r#"{
+        "asset": { "version": "2.0" },
+        "scene": 0,
+        "scenes": [ { "nodes": [0] } ],
+        "nodes": [
+            { "name": "body", "translation": [1.0, 2.0, 3.0], "children": [1, 2, 3] },
+            { "name": "mesh", "mesh": 0, "rotation": [0.0, 0.0, 0.7071068, 0.7071068] },
+            { "name": "mesh", "mesh": 0, "scale": [2.0, 2.0, 2.0] },
+            { "extensions": { "KHR_lights_punctual": { "light": 0 } } }
+        ],
+        "meshes": [ { "primitives": [ { "attributes": { "POSITION": 0 }, "indices": 1, "material": 0 } ] } ],
+        "materials": [ { "pbrMetallicRoughness": { "baseColorFactor": [1.0, 0.5, 0.0, 1.0] } } ],
+        "extensions": { "KHR_lights_punctual": { "lights": [ { "type": "point", "color": [1.0, 0.0, 0.0], "intensity": 2.0 } ] } },
+        "accessors": [
+            { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" },
+            { "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }
+        ],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+            { "buffer": 0, "byteOffset": 36, "byteLength": 6 }
+        ],
+        "buffers": [ { "byteLength": 44, "uri": "URI" } ]
+    }"#;
+
+    const DATA_URI: &str = "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAABAAIAAAA=";
+
+    #[test]
+    fn test_parse_gltf(){
+        let json = TRIANGLE.replace("URI", DATA_URI);
+        let tree = parse_gltf::<PresetAsciaEnvironment>(json.as_bytes(), Path::new(""), "scene").unwrap();
+        let body = tree.child_by_path("body").unwrap();
+        assert_eq!(body.position.z, 3.0);
+        assert_eq!(body.children().len(), 3);
+        let mesh = tree.child_by_path("body/mesh").unwrap();
+        assert_eq!(mesh.polygons.len(), 1);
+        assert_eq!(mesh.polygons[0].poses.v2.x, 1.0);
+        assert!(f32::abs(mesh.direction.vec4.z - 0.7071068) < 1e-6);
+        assert!(matches!(mesh.polygons[0].material, PresetPolygonMaterial::LambertMaterial(m) if m.color.g == 0.5));
+        assert_eq!(tree.child_by_path("body/mesh 2").unwrap().scale.y, 2.0);
+        assert!(tree.child_by_path("body/node 3").unwrap().attribute.borrow().as_ref().unwrap().light().is_some());
+        assert!(mesh.polygons[0].normals.is_none());
+    }
+
+    #[test]
+    fn test_parse_gltf_matrix(){
+        // the same quarter turn around z as the rotation of the first mesh node, moved by (4, 5, 6)
+        let json = TRIANGLE.replace("URI", DATA_URI).replace("\"rotation\": [0.0, 0.0, 0.7071068, 0.7071068]", "\"matrix\": [0.0, 1.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 4.0, 5.0, 6.0, 1.0]");
+        let tree = parse_gltf::<PresetAsciaEnvironment>(json.as_bytes(), Path::new(""), "scene").unwrap();
+        let mesh = tree.child_by_path("body/mesh").unwrap();
+        assert_eq!(mesh.position.y, 5.0);
+        assert!(f32::abs(mesh.direction.vec4.z - 0.7071068) < 1e-6);
+        assert!(f32::abs(mesh.direction.vec4.w - 0.7071068) < 1e-6);
+    }
+
+    #[test]
+    fn test_parse_gltf_normals(){
+        // the positions are reused as normals
+        let json = TRIANGLE.replace("URI", DATA_URI).replace("{ \"POSITION\": 0 }", "{ \"POSITION\": 0, \"NORMAL\": 0 }");
+        let tree = parse_gltf::<PresetAsciaEnvironment>(json.as_bytes(), Path::new(""), "scene").unwrap();
+        let normals = tree.child_by_path("body/mesh").unwrap().polygons[0].normals.unwrap();
+        assert_eq!(normals.v2.x, 1.0);
+        assert_eq!(normals.v3.y, 1.0);
+        let json = TRIANGLE.replace("URI", DATA_URI).replace("{ \"POSITION\": 0 }", "{ \"POSITION\": 0, \"NORMAL\": 1 }");
+        assert!(parse_gltf::<PresetAsciaEnvironment>(json.as_bytes(), Path::new(""), "scene").is_err());
+    }
+
+    #[test]
+    fn test_parse_gltf_uvs(){
+        // an accessor without a buffer view reads as zeros, which is the upper left of the image
+        let json = TRIANGLE.replace("URI", DATA_URI)
+            .replace("{ \"POSITION\": 0 }", "{ \"POSITION\": 0, \"TEXCOORD_0\": 2 }")
+            .replace("\"type\": \"SCALAR\" }", "\"type\": \"SCALAR\" },\n            { \"componentType\": 5126, \"count\": 3, \"type\": \"VEC2\" }");
+        let tree = parse_gltf::<PresetAsciaEnvironment>(json.as_bytes(), Path::new(""), "scene").unwrap();
+        let uvs = tree.child_by_path("body/mesh").unwrap().polygons[0].uvs.unwrap();
+        assert_eq!((uvs[0].x, uvs[0].y), (0.0, 1.0));
+        let json = TRIANGLE.replace("URI", DATA_URI).replace("{ \"POSITION\": 0 }", "{ \"POSITION\": 0, \"TEXCOORD_0\": 0 }");
+        assert!(parse_gltf::<PresetAsciaEnvironment>(json.as_bytes(), Path::new(""), "scene").is_err());
+    }
+
+    #[test]
+    fn test_parse_gltf_normalized_uvs(){
+        // from byte 12 on the buffer holds 00 00 80 3f and then zeros, the 1.0 that starts the second position
+        let uvs = |component_type: u32| {
+            let json = TRIANGLE.replace("URI", DATA_URI)
+                .replace("{ \"POSITION\": 0 }", "{ \"POSITION\": 0, \"TEXCOORD_0\": 2 }")
+                .replace("\"type\": \"SCALAR\" }", &format!("\"type\": \"SCALAR\" }},\n            {{ \"bufferView\": 0, \"byteOffset\": 12, \"componentType\": {}, \"normalized\": true, \"count\": 3, \"type\": \"VEC2\" }}", component_type));
+            let tree = parse_gltf::<PresetAsciaEnvironment>(json.as_bytes(), Path::new(""), "scene").unwrap();
+            return tree.child_by_path("body/mesh").unwrap().polygons[0].uvs.unwrap();
+        };
+        let close = |a: f32, b: f32| f32::abs(a - b) < 1e-6;
+        let u8_uvs = uvs(5121);
+        assert!(close(u8_uvs[1].x, 128.0 / 255.0) && close(u8_uvs[1].y, 1.0 - 63.0 / 255.0));
+        let u16_uvs = uvs(5123);
+        assert!(close(u16_uvs[0].x, 0.0) && close(u16_uvs[0].y, 1.0 - 16256.0 / 65535.0));
+    }
+
+    #[test]
+    fn test_parse_glb(){
+        let json = TRIANGLE.replace(", \"uri\": \"URI\"", "");
+        let mut json = json.into_bytes();
+        while json.len() % 4 != 0{
+            json.push(b' ');
+        }
+        let bin = vec![0u8; 44];
+        let mut glb = vec![];
+        glb.extend_from_slice(b"glTF");
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&((12 + 8 + json.len() + 8 + bin.len()) as u32).to_le_bytes());
+        glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"JSON");
+        glb.extend_from_slice(&json);
+        glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"BIN\0");
+        glb.extend_from_slice(&bin);
+        let tree = parse_gltf::<PresetAsciaEnvironment>(&glb, Path::new(""), "scene").unwrap();
+        assert_eq!(tree.child_by_path("body/mesh").unwrap().polygons.len(), 1);
+
+        glb.truncate(glb.len() - 10);
+        assert!(parse_gltf::<PresetAsciaEnvironment>(&glb, Path::new(""), "scene").is_err());
+    }
+
+    #[test]
+    fn test_gltf_rejects_oversized_accessors(){
+        let uvs = |accessor: &str| TRIANGLE.replace("URI", DATA_URI)
+            .replace("{ \"POSITION\": 0 }", "{ \"POSITION\": 0, \"TEXCOORD_0\": 2 }")
+            .replace("\"type\": \"SCALAR\" }", &format!("\"type\": \"SCALAR\" }},\n            {}", accessor));
+        let parse = |json: String| parse_gltf::<PresetAsciaEnvironment>(json.as_bytes(), Path::new(""), "scene").err().unwrap();
+        assert!(parse(uvs("{ \"componentType\": 5126, \"count\": 9223372036854775808, \"type\": \"VEC2\" }")).contains("too many"));
+        assert!(parse(uvs("{ \"componentType\": 5126, \"count\": 100000000000, \"type\": \"VEC2\" }")).contains("more than the buffers hold"));
+        assert!(parse(uvs("{ \"bufferView\": 0, \"componentType\": 5126, \"count\": 100000000000, \"type\": \"VEC2\" }")).contains("reads past the end"));
+        assert!(parse(uvs("{ \"bufferView\": 0, \"byteOffset\": 18446744073709551615, \"componentType\": 5126, \"count\": 3, \"type\": \"VEC2\" }")).contains("reads past the end"));
+        let json = TRIANGLE.replace("URI", DATA_URI).replace("\"byteOffset\": 36, \"byteLength\": 6", "\"byteOffset\": 36, \"byteLength\": 18446744073709551615");
+        assert!(parse(json).contains("out of the buffer's range"));
+    }
+
+    #[test]
+    fn test_gltf_rejects_remote_buffers(){
+        let json = TRIANGLE.replace("URI", "https://example.com/triangle.bin");
+        let e = parse_gltf::<PresetAsciaEnvironment>(json.as_bytes(), Path::new(""), "scene").err().unwrap();
+        assert!(e.contains("only local files"));
+    }
+}
@@ -0,0 +1,230 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::str::FromStr;
+use crate::ascia::core::{AsciaEnvironment, Local, NodeId, ObjectNode, ObjectNodeTree, Polygon};
+use crate::ascia::math::{Matrix33, Vec2, Vec3};
+
+pub fn load_obj<E: AsciaEnvironment, P: AsRef<Path> + ?Sized, F: Fn(&str) -> Option<E::PolygonMaterials>>(path: &P, tag: &str, materials: &F) -> Result<ObjectNodeTree<E, Local>, String>{
+    if let Ok(f) = File::open(path){
+        return parse_obj(BufReader::new(f), tag, materials);
+    }
+    return Err(format!("could not open file: {:?}", path.as_ref()));
+}
+
+pub fn parse_obj<E: AsciaEnvironment, R: BufRead, F: Fn(&str) -> Option<E::PolygonMaterials>>(reader: R, tag: &str, materials: &F) -> Result<ObjectNodeTree<E, Local>, String>{
+    let mut root = ObjectNodeTree::new(ObjectNode::new(tag));
+    let mut vertices: Vec<Vec3> = vec![];
+    let mut normals: Vec<Vec3> = vec![];
+    let mut uvs: Vec<Vec2> = vec![];
+    let mut material = E::PolygonMaterials::default();
+    let mut group: Option<NodeId> = None;
+
+    for (n, line) in reader.lines().enumerate(){
+        let line_number = n + 1;
+        let line = match line {
+            Ok(l) => { l }
+            Err(e) => { return Err(format!("line {}: {}", line_number, e)); }
+        };
+        let line = match line.find('#') {
+            Some(i) => { &line[..i] }
+            None => { &line[..] }
+        };
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(k) => { k }
+            None => { continue; }
+        };
+        match keyword {
+            "v" => {
+                vertices.push(Vec3{
+                    x: parse_f32(tokens.next(), line_number)?,
+                    y: parse_f32(tokens.next(), line_number)?,
+                    z: parse_f32(tokens.next(), line_number)?,
+                });
+            }
+            "vn" => {
+                normals.push(Vec3{
+                    x: parse_f32(tokens.next(), line_number)?,
+                    y: parse_f32(tokens.next(), line_number)?,
+                    z: parse_f32(tokens.next(), line_number)?,
+                }.normalize());
+            }
+            "vt" => {
+                uvs.push(Vec2{
+                    x: parse_f32(tokens.next(), line_number)?,
+                    y: parse_f32(tokens.next(), line_number)?,
+                });
+            }
+            "f" => {
+                let mut indices = vec![];
+                let mut normal_indices = vec![];
+                let mut uv_indices = vec![];
+                for token in tokens{
+                    indices.push(parse_vertex_index(token, vertices.len(), line_number)?);
+                    normal_indices.push(parse_attribute_index(token, 2, "normal", normals.len(), line_number)?);
+                    uv_indices.push(parse_attribute_index(token, 1, "texture coordinate", uvs.len(), line_number)?);
+                }
+                if indices.len() < 3{
+                    return Err(format!("line {}: a face needs at least 3 vertices, found {}", line_number, indices.len()));
+                }
+                let target = root.node_mut(group.unwrap_or(root.root())).unwrap();
+                for i in 1..(indices.len() - 1){
+                    let mut polygon = Polygon::new(&vertices[indices[0]], &vertices[indices[i]], &vertices[indices[i + 1]]);
+                    polygon.material = material.clone();
+                    // a face is only smooth when every corner has a normal
+                    if let (Some(a), Some(b), Some(c)) = (normal_indices[0], normal_indices[i], normal_indices[i + 1]){
+                        polygon.normals = Some(Matrix33{
+                            v1: normals[a],
+                            v2: normals[b],
+                            v3: normals[c],
+                        });
+                    }
+                    if let (Some(a), Some(b), Some(c)) = (uv_indices[0], uv_indices[i], uv_indices[i + 1]){
+                        polygon.uvs = Some([uvs[a], uvs[b], uvs[c]]);
+                    }
+                    target.polygons.push(polygon);
+                }
+            }
+            "o" | "g" => {
+                let name = tokens.collect::<Vec<&str>>().join(" ");
+                if name.is_empty(){
+                    group = None;
+                    continue;
+                }
+                group = match root.child(root.root(), &name) {
+                    Some(g) => { Some(g) }
+                    None => { Some(root.add_child(root.root(), ObjectNode::new(&name))?) }
+                };
+            }
+            "usemtl" => {
+                let name = tokens.collect::<Vec<&str>>().join(" ");
+                material = match materials(&name) {
+                    Some(m) => { m }
+                    None => { return Err(format!("line {}: no material is mapped to \"{}\"", line_number, name)); }
+                };
+            }
+            _ => {}
+        }
+    }
+    return Ok(root);
+}
+
+fn parse_f32(token: Option<&str>, line_number: usize) -> Result<f32, String>{
+    return match token {
+        Some(t) => {
+            match f32::from_str(t) {
+                Ok(v) => { Ok(v) }
+                Err(_) => { Err(format!("line {}: could not parse \"{}\" as a number", line_number, t)) }
+            }
+        }
+        None => { Err(format!("line {}: missing coordinate", line_number)) }
+    };
+}
+
+fn parse_vertex_index(token: &str, vertex_count: usize, line_number: usize) -> Result<usize, String>{
+    let s = token.split('/').next().unwrap_or("");
+    return parse_index(s, token, "vertex", vertex_count, line_number);
+}
+
+// field 1 of v/vt/vn is the texture coordinate and field 2 the normal, None when the corner has none
+// files that refer to attributes without declaring any are read without them
+fn parse_attribute_index(token: &str, field: usize, kind: &str, count: usize, line_number: usize) -> Result<Option<usize>, String>{
+    return match token.split('/').nth(field) {
+        Some(s) if !s.is_empty() && count > 0 => { parse_index(s, token, kind, count, line_number).map(Some) }
+        _ => { Ok(None) }
+    };
+}
+
+fn parse_index(s: &str, token: &str, kind: &str, count: usize, line_number: usize) -> Result<usize, String>{
+    let index = match isize::from_str(s) {
+        Ok(i) => { i }
+        Err(_) => { return Err(format!("line {}: could not parse \"{}\" as a {} index", line_number, token, kind)); }
+    };
+    let resolved = if index < 0 { count as isize + index } else { index - 1 };
+    if index == 0 || resolved < 0 || resolved as usize >= count{
+        return Err(format!("line {}: {} index {} is out of range", line_number, kind, index));
+    }
+    return Ok(resolved as usize);
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::ascia::core::{FlatMaterial, PresetAsciaEnvironment, PresetPolygonMaterial};
+    use crate::ascia::color::ColorRGBf32;
+    use crate::ascia::io::obj::parse_obj;
+
+    fn red(name: &str) -> Option<PresetPolygonMaterial>{
+        if name == "red"{
+            return Some(PresetPolygonMaterial::FlatMaterial(FlatMaterial{
+                color: ColorRGBf32{
+                    r: 1.0,
+                    g: 0.0,
+                    b: 0.0,
+                },
+                priority: 0,
+            }));
+        }
+        return None;
+    }
+
+    #[test]
+    fn test_parse_obj(){
+        let src = "# square and a triangle\n\
+            v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+            f 1 2 3 4\n\
+            o tri\n\
+            usemtl red\n\
+            f -4/1 -3/2/1 -2//3\n";
+        let tree = parse_obj::<PresetAsciaEnvironment, _, _>(src.as_bytes(), "model", &red).unwrap();
+        let root = tree.root_node();
+        assert_eq!(root.polygons.len(), 2);
+        assert_eq!(root.polygons[1].poses.v3.y, 1.0);
+        let tri = tree.child_by_path("tri").unwrap();
+        assert_eq!(tri.polygons.len(), 1);
+        assert!(matches!(tri.polygons[0].material, PresetPolygonMaterial::FlatMaterial(m) if m.color.g == 0.0));
+        assert!(root.polygons[0].normals.is_none());
+    }
+
+    #[test]
+    fn test_parse_obj_normals(){
+        let src = "v 0 0 0\nv 1 0 0\nv 0 1 0\n\
+            vn 0 0 2\nvn 1 0 1\n\
+            f 1//1 2//2 3//1\n\
+            f 1 2//2 3\n";
+        let tree = parse_obj::<PresetAsciaEnvironment, _, _>(src.as_bytes(), "model", &red).unwrap();
+        let polygons = &tree.root_node().polygons;
+        let normals = polygons[0].normals.unwrap();
+        assert_eq!(normals.v1.z, 1.0);
+        assert!((normals.v2.x - f32::sqrt(0.5)).abs() < 1e-6);
+        assert!(polygons[1].normals.is_none());
+        assert!(polygons[0].uvs.is_none());
+        let e = parse_obj::<PresetAsciaEnvironment, _, _>(src.replace("3//1", "3//3").as_bytes(), "model", &red).err().unwrap();
+        assert_eq!(e, "line 6: normal index 3 is out of range");
+    }
+
+    #[test]
+    fn test_parse_obj_uvs(){
+        let src = "v 0 0 0\nv 1 0 0\nv 0 1 0\n\
+            vt 0 0\nvt 1 0\nvt 0 1\n\
+            f 1/1 2/2 3/3\n\
+            f 1/1/ 2/2 3\n";
+        let tree = parse_obj::<PresetAsciaEnvironment, _, _>(src.as_bytes(), "model", &red).unwrap();
+        let polygons = &tree.root_node().polygons;
+        let uvs = polygons[0].uvs.unwrap();
+        assert_eq!((uvs[1].x, uvs[2].y), (1.0, 1.0));
+        assert!(polygons[1].uvs.is_none());
+        let e = parse_obj::<PresetAsciaEnvironment, _, _>(src.replace("3/3", "3/4").as_bytes(), "model", &red).err().unwrap();
+        assert_eq!(e, "line 7: texture coordinate index 4 is out of range");
+    }
+
+    #[test]
+    fn test_parse_obj_errors(){
+        let e = parse_obj::<PresetAsciaEnvironment, _, _>("v 0 0 0\nv 1 0 0\nf 1 2 3\n".as_bytes(), "model", &red).err().unwrap();
+        assert!(e.starts_with("line 3:"));
+        let e = parse_obj::<PresetAsciaEnvironment, _, _>("v 0 0\n".as_bytes(), "model", &red).err().unwrap();
+        assert!(e.starts_with("line 1:"));
+        let e = parse_obj::<PresetAsciaEnvironment, _, _>("\nusemtl blue\n".as_bytes(), "model", &red).err().unwrap();
+        assert!(e.starts_with("line 2:"));
+    }
+}
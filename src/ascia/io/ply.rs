@@ -0,0 +1,423 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+use crate::ascia::color::ColorRGBf32;
+use crate::ascia::core::{AsciaEnvironment, CParticle, FlatMaterial, Local, ObjectNode, Polygon, PresetCParticleMaterial, PresetPolygonMaterial};
+use crate::ascia::math::Vec3;
+
+#[derive(Copy, Clone, PartialEq)]
+enum PlyFormat{
+    Ascii,
+    BinaryLittleEndian,
+}
+
+#[derive(Copy, Clone)]
+enum PlyScalar{
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl PlyScalar{
+    fn parse(s: &str) -> Option<PlyScalar>{
+        return match s {
+            "char" | "int8" => { Some(PlyScalar::I8) }
+            "uchar" | "uint8" => { Some(PlyScalar::U8) }
+            "short" | "int16" => { Some(PlyScalar::I16) }
+            "ushort" | "uint16" => { Some(PlyScalar::U16) }
+            "int" | "int32" => { Some(PlyScalar::I32) }
+            "uint" | "uint32" => { Some(PlyScalar::U32) }
+            "float" | "float32" => { Some(PlyScalar::F32) }
+            "double" | "float64" => { Some(PlyScalar::F64) }
+            _ => { None }
+        };
+    }
+
+    fn size(&self) -> usize{
+        return match self {
+            PlyScalar::I8 | PlyScalar::U8 => { 1 }
+            PlyScalar::I16 | PlyScalar::U16 => { 2 }
+            PlyScalar::I32 | PlyScalar::U32 | PlyScalar::F32 => { 4 }
+            PlyScalar::F64 => { 8 }
+        };
+    }
+
+    // integer colors are stored as 0-255, floating point ones as 0.0-1.0
+    fn color_scale(&self) -> f64{
+        return match self {
+            PlyScalar::F32 | PlyScalar::F64 => { 1.0 }
+            _ => { 1.0 / 255.0 }
+        };
+    }
+
+    fn read_le(&self, b: &[u8]) -> f64{
+        return match self {
+            PlyScalar::I8 => { b[0] as i8 as f64 }
+            PlyScalar::U8 => { b[0] as f64 }
+            PlyScalar::I16 => { i16::from_le_bytes([b[0], b[1]]) as f64 }
+            PlyScalar::U16 => { u16::from_le_bytes([b[0], b[1]]) as f64 }
+            PlyScalar::I32 => { i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64 }
+            PlyScalar::U32 => { u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64 }
+            PlyScalar::F32 => { f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64 }
+            PlyScalar::F64 => { f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]) }
+        };
+    }
+}
+
+enum PlyProperty{
+    Scalar(String, PlyScalar),
+    List(String, PlyScalar, PlyScalar),
+}
+
+struct PlyElement{
+    name: String,
+    count: usize,
+    properties: Vec<PlyProperty>,
+}
+
+enum PlyValue{
+    Scalar(f64),
+    List(Vec<f64>),
+}
+
+struct PlyReader<'a>{
+    format: PlyFormat,
+    data: &'a [u8],
+    offset: usize,
+    line: usize,
+    tokens: Vec<String>,
+}
+
+impl<'a> PlyReader<'a>{
+    fn next_token(&mut self) -> Result<String, String>{
+        while self.tokens.is_empty(){
+            if self.offset >= self.data.len(){
+                return Err(format!("line {}: file is truncated", self.line));
+            }
+            let end = self.data[self.offset..].iter().position(|c| *c == b'\n').map(|p| self.offset + p).unwrap_or(self.data.len());
+            let line = String::from_utf8_lossy(&self.data[self.offset..end]).to_string();
+            self.offset = end + 1;
+            self.line += 1;
+            self.tokens = line.split_whitespace().rev().map(|s| s.to_string()).collect();
+        }
+        return Ok(self.tokens.pop().unwrap());
+    }
+
+    fn read(&mut self, t: PlyScalar) -> Result<f64, String>{
+        if self.format == PlyFormat::Ascii{
+            let token = self.next_token()?;
+            return match f64::from_str(&token) {
+                Ok(v) => { Ok(v) }
+                Err(_) => { Err(format!("line {}: could not parse \"{}\" as a number", self.line, token)) }
+            };
+        }
+        if self.offset + t.size() > self.data.len(){
+            return Err(format!("file is truncated at byte {}", self.offset));
+        }
+        let v = t.read_le(&self.data[self.offset..]);
+        self.offset += t.size();
+        return Ok(v);
+    }
+
+    fn position(&self) -> String{
+        return if self.format == PlyFormat::Ascii { format!("line {}", self.line) } else { format!("byte {}", self.offset) };
+    }
+
+    fn read_element(&mut self, element: &PlyElement) -> Result<Vec<PlyValue>, String>{
+        let mut values = Vec::with_capacity(element.properties.len());
+        for p in &element.properties{
+            match p {
+                PlyProperty::Scalar(_, t) => {
+                    values.push(PlyValue::Scalar(self.read(*t)?));
+                }
+                PlyProperty::List(_, count_type, item_type) => {
+                    let count = self.read(*count_type)?;
+                    if count < 0.0 || count.fract() != 0.0{
+                        return Err(format!("{}: list length {} is not a count", self.position(), count));
+                    }
+                    // every item takes at least a byte, so a length the rest of the file cannot hold is rejected before allocating
+                    let count = count as usize;
+                    let item_size = if self.format == PlyFormat::Ascii { 1 } else { item_type.size() };
+                    if count.saturating_mul(item_size) > self.data.len().saturating_sub(self.offset) + self.tokens.len(){
+                        return Err(format!("{}: list of {} items is longer than the rest of the file", self.position(), count));
+                    }
+                    let mut items = Vec::with_capacity(count);
+                    for _ in 0..count{
+                        items.push(self.read(*item_type)?);
+                    }
+                    values.push(PlyValue::List(items));
+                }
+            }
+        }
+        if self.format == PlyFormat::Ascii && !self.tokens.is_empty(){
+            return Err(format!("line {}: too many values for element \"{}\"", self.line, element.name));
+        }
+        return Ok(values);
+    }
+}
+
+pub fn load_ply<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMaterials=PresetCParticleMaterial>, P: AsRef<Path> + ?Sized>(path: &P, tag: &str, polygon_material: &PresetPolygonMaterial, c_particle: &CParticle<E, Local>) -> Result<ObjectNode<E, Local>, String>{
+    let mut buf = vec![];
+    if let Ok(mut f) = File::open(path){
+        if let Err(e) = f.read_to_end(&mut buf){
+            return Err(e.to_string());
+        }
+        return parse_ply(&buf, tag, polygon_material, c_particle);
+    }
+    return Err(format!("could not open file: {:?}", path.as_ref()));
+}
+
+pub fn parse_ply<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMaterials=PresetCParticleMaterial>>(data: &[u8], tag: &str, polygon_material: &PresetPolygonMaterial, c_particle: &CParticle<E, Local>) -> Result<ObjectNode<E, Local>, String>{
+    let mut reader = PlyReader{
+        format: PlyFormat::Ascii,
+        data: data,
+        offset: 0,
+        line: 0,
+        tokens: vec![],
+    };
+
+    if reader.next_token()? != "ply" || !reader.tokens.is_empty(){
+        return Err("line 1: file does not start with \"ply\"".to_string());
+    }
+
+    let mut format = None;
+    let mut elements: Vec<PlyElement> = vec![];
+    loop {
+        let keyword = reader.next_token()?;
+        let line = reader.line;
+        let mut args: Vec<String> = reader.tokens.drain(..).rev().collect();
+        match keyword.as_str() {
+            "format" => {
+                format = match args.first().map(|s| s.as_str()) {
+                    Some("ascii") => { Some(PlyFormat::Ascii) }
+                    Some("binary_little_endian") => { Some(PlyFormat::BinaryLittleEndian) }
+                    Some(f) => { return Err(format!("line {}: unsupported format \"{}\"", line, f)); }
+                    None => { return Err(format!("line {}: missing format", line)); }
+                };
+            }
+            "element" => {
+                if args.len() != 2{
+                    return Err(format!("line {}: expected \"element <name> <count>\"", line));
+                }
+                let count = match usize::from_str(&args[1]) {
+                    Ok(c) => { c }
+                    Err(_) => { return Err(format!("line {}: could not parse \"{}\" as an element count", line, args[1])); }
+                };
+                elements.push(PlyElement{
+                    name: args.swap_remove(0),
+                    count: count,
+                    properties: vec![],
+                });
+            }
+            "property" => {
+                let element = match elements.last_mut() {
+                    Some(e) => { e }
+                    None => { return Err(format!("line {}: property declared before any element", line)); }
+                };
+                let scalar = |s: &str| -> Result<PlyScalar, String> {
+                    return PlyScalar::parse(s).ok_or(format!("line {}: unknown property type \"{}\"", line, s));
+                };
+                if args.len() == 4 && args[0] == "list"{
+                    element.properties.push(PlyProperty::List(args[3].clone(), scalar(&args[1])?, scalar(&args[2])?));
+                }
+                else if args.len() == 2{
+                    element.properties.push(PlyProperty::Scalar(args[1].clone(), scalar(&args[0])?));
+                }
+                else{
+                    return Err(format!("line {}: malformed property declaration", line));
+                }
+            }
+            "comment" | "obj_info" => {}
+            "end_header" => {
+                break;
+            }
+            _ => {
+                return Err(format!("line {}: unknown header keyword \"{}\"", line, keyword));
+            }
+        }
+    }
+    reader.format = match format {
+        Some(f) => { f }
+        None => { return Err(format!("line {}: the header does not declare a format", reader.line)); }
+    };
+
+    let mut positions: Vec<Vec3> = vec![];
+    let mut colors: Vec<ColorRGBf32> = vec![];
+    let mut faces: Vec<Vec<usize>> = vec![];
+    let mut has_faces = false;
+
+    for element in &elements{
+        let find = |name: &str| element.properties.iter().position(|p| match p {
+            PlyProperty::Scalar(n, _) => { n == name }
+            PlyProperty::List(n, _, _) => { n == name }
+        });
+        let color_scale = |i: usize| match &element.properties[i] {
+            PlyProperty::Scalar(_, t) => { t.color_scale() }
+            PlyProperty::List(_, _, t) => { t.color_scale() }
+        };
+        if element.name == "vertex"{
+            let (x, y, z) = match (find("x"), find("y"), find("z")) {
+                (Some(x), Some(y), Some(z)) => { (x, y, z) }
+                _ => { return Err("vertex element needs x, y and z properties".to_string()); }
+            };
+            let rgb = match (find("red"), find("green"), find("blue")) {
+                (Some(r), Some(g), Some(b)) => { Some((r, g, b)) }
+                _ => { None }
+            };
+            for _ in 0..element.count{
+                let values = reader.read_element(element)?;
+                let scalar = |i: usize| -> f64 {
+                    if let PlyValue::Scalar(v) = values[i] { v } else { 0.0 }
+                };
+                positions.push(Vec3{
+                    x: scalar(x) as f32,
+                    y: scalar(y) as f32,
+                    z: scalar(z) as f32,
+                });
+                if let Some((r, g, b)) = rgb{
+                    colors.push(ColorRGBf32{
+                        r: (scalar(r) * color_scale(r)) as f32,
+                        g: (scalar(g) * color_scale(g)) as f32,
+                        b: (scalar(b) * color_scale(b)) as f32,
+                    });
+                }
+            }
+        }
+        else if element.name == "face"{
+            has_faces = element.count > 0;
+            let indices = match find("vertex_indices").or(find("vertex_index")) {
+                Some(i) => { i }
+                None => { return Err("face element needs a vertex_indices property".to_string()); }
+            };
+            for _ in 0..element.count{
+                let values = reader.read_element(element)?;
+                if let PlyValue::List(l) = &values[indices]{
+                    if let Some(i) = l.iter().find(|i| **i < 0.0 || i.fract() != 0.0){
+                        return Err(format!("face {} has the invalid vertex index {}", faces.len(), i));
+                    }
+                    faces.push(l.iter().map(|i| *i as usize).collect());
+                }
+                else{
+                    return Err("vertex_indices must be a list property".to_string());
+                }
+            }
+        }
+        else{
+            for _ in 0..element.count{
+                reader.read_element(element)?;
+            }
+        }
+    }
+
+    let mut node = ObjectNode::new(tag);
+    if has_faces{
+        for (n, face) in faces.iter().enumerate(){
+            if face.len() < 3{
+                return Err(format!("face {} has only {} vertices", n, face.len()));
+            }
+            if let Some(i) = face.iter().find(|i| **i >= positions.len()){
+                return Err(format!("face {} refers to vertex {} but only {} vertices are declared", n, i, positions.len()));
+            }
+            let material = if colors.is_empty() {
+                polygon_material.clone()
+            } else {
+                let mut sum = ColorRGBf32::default();
+                for i in face{
+                    sum += colors[*i];
+                }
+                PresetPolygonMaterial::FlatMaterial(FlatMaterial{
+                    color: ColorRGBf32{
+                        r: sum.r / face.len() as f32,
+                        g: sum.g / face.len() as f32,
+                        b: sum.b / face.len() as f32,
+                    },
+                    priority: 0,
+                })
+            };
+            for i in 1..(face.len() - 1){
+                let mut polygon = Polygon::new(&positions[face[0]], &positions[face[i]], &positions[face[i + 1]]);
+                polygon.material = material.clone();
+                node.polygons.push(polygon);
+            }
+        }
+    }
+    else{
+        for (i, position) in positions.iter().enumerate(){
+            let mut particle = c_particle.clone();
+            particle.position = *position;
+            if !colors.is_empty(){
+                particle.material = PresetCParticleMaterial::FlatMaterial(FlatMaterial{
+                    color: colors[i],
+                    priority: 0,
+                });
+            }
+            node.c_particles.push(particle);
+        }
+    }
+    return Ok(node);
+}
+
+#[cfg(test)]
+mod tests{
+    use std::f32::consts::PI;
+    use crate::ascia::core::{CParticle, CParticleMode, Local, PresetAsciaEnvironment, PresetCParticleMaterial, PresetPolygonMaterial};
+    use crate::ascia::io::ply::parse_ply;
+
+    fn particle() -> CParticle<PresetAsciaEnvironment, Local>{
+        return CParticle{
+            position: Default::default(),
+            velocity: Default::default(),
+            c: '.',
+            threshold: PI / 400.0,
+            mode: CParticleMode::ARG,
+            material: Default::default(),
+            _ph: Default::default(),
+        };
+    }
+
+    #[test]
+    fn test_parse_ply_ascii(){
+        let src = "ply\nformat ascii 1.0\ncomment quad\nelement vertex 4\nproperty float x\nproperty float y\nproperty float z\n\
+            property uchar red\nproperty uchar green\nproperty uchar blue\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n\
+            0 0 0 255 0 0\n1 0 0 255 0 0\n1 1 0 255 0 0\n0 1 0 255 0 0\n4 0 1 2 3\n";
+        let node = parse_ply(src.as_bytes(), "quad", &PresetPolygonMaterial::default(), &particle()).unwrap();
+        assert_eq!(node.polygons.len(), 2);
+        assert!(matches!(node.polygons[0].material, PresetPolygonMaterial::FlatMaterial(m) if m.color.r == 1.0 && m.color.g == 0.0));
+    }
+
+    #[test]
+    fn test_parse_ply_binary_point_cloud(){
+        let mut data = b"ply\nformat binary_little_endian 1.0\nelement vertex 2\nproperty float x\nproperty float y\nproperty float z\nproperty float red\nproperty float green\nproperty float blue\nend_header\n".to_vec();
+        for v in [1.0f32, 2.0, 3.0, 0.0, 1.0, 0.0, -1.0, -2.0, -3.0, 0.5, 0.5, 0.5]{
+            data.extend_from_slice(&v.to_le_bytes());
+        }
+        let node = parse_ply(&data, "cloud", &PresetPolygonMaterial::default(), &particle()).unwrap();
+        assert!(node.polygons.is_empty());
+        assert_eq!(node.c_particles.len(), 2);
+        assert_eq!(node.c_particles[1].position.z, -3.0);
+        assert!(matches!(node.c_particles[0].material, PresetCParticleMaterial::FlatMaterial(m) if m.color.g == 1.0));
+
+        data.truncate(data.len() - 4);
+        assert!(parse_ply(&data, "cloud", &PresetPolygonMaterial::default(), &particle()).err().unwrap().contains("truncated"));
+    }
+
+    #[test]
+    fn test_parse_ply_invalid_lists(){
+        let header = "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\nelement face 1\nproperty list uint int vertex_indices\nend_header\n0 0 0\n1 0 0\n0 1 0\n";
+        let parse = |face: &str| parse_ply(format!("{}{}", header, face).as_bytes(), "broken", &PresetPolygonMaterial::default(), &particle());
+        assert!(parse("3 0 1 2\n").is_ok());
+        assert!(parse("4294967295 0 1 2\n").err().unwrap().contains("longer than the rest"));
+        assert!(parse("3 0 -1 2\n").err().unwrap().contains("invalid vertex index -1"));
+        assert!(parse("3 0 1.5 2\n").err().unwrap().contains("invalid vertex index 1.5"));
+
+        let mut data = b"ply\nformat binary_little_endian 1.0\nelement face 1\nproperty list uint int vertex_indices\nend_header\n".to_vec();
+        data.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(parse_ply(&data, "broken", &PresetPolygonMaterial::default(), &particle()).err().unwrap().starts_with("byte "));
+    }
+}
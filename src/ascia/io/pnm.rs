@@ -0,0 +1,169 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+use crate::ascia::color::ColorRGBf32;
+use crate::ascia::texture::Texture;
+
+pub fn load_pnm<P: AsRef<Path> + ?Sized>(path: &P) -> Result<Texture, String>{
+    let mut buf = vec![];
+    if let Ok(mut f) = File::open(path){
+        if let Err(e) = f.read_to_end(&mut buf){
+            return Err(e.to_string());
+        }
+        return parse_pnm(&buf);
+    }
+    return Err(format!("could not open file: {:?}", path.as_ref()));
+}
+
+// reads plain and raw graymaps and pixmaps (P2, P3, P5 and P6), gray pixels become gray colors
+pub fn parse_pnm(data: &[u8]) -> Result<Texture, String>{
+    let mut offset = 0;
+    let magic = match next_token(data, &mut offset) {
+        Some(m) => { m }
+        None => { return Err("pnm is empty".to_string()); }
+    };
+    let (channels, binary) = match magic {
+        b"P2" => { (1, false) }
+        b"P3" => { (3, false) }
+        b"P5" => { (1, true) }
+        b"P6" => { (3, true) }
+        m => { return Err(format!("unsupported pnm format: {}", String::from_utf8_lossy(m))); }
+    };
+    let width = next_number(data, &mut offset, "width")?;
+    let height = next_number(data, &mut offset, "height")?;
+    let max = next_number(data, &mut offset, "maximum value")?;
+    if max == 0 || max > 65535{
+        return Err(format!("pnm maximum value must be within 1..=65535, found {}", max));
+    }
+
+    let count = match width.checked_mul(height).and_then(|n| n.checked_mul(channels)) {
+        Some(c) => { c }
+        None => { return Err(format!("pnm of {}x{} pixels is too large", width, height)); }
+    };
+    // the count comes from the header, so it only sizes the allocation once the data is known to hold that many samples
+    let mut samples = vec![];
+    if binary{
+        // exactly one whitespace separates the header from the samples
+        offset += 1;
+        let size = if max < 256 { 1 } else { 2 };
+        let complete = data.len().saturating_sub(offset) / size;
+        if complete < count{
+            return Err(format!("pnm is truncated: {} samples are declared but only {} are complete", count, complete));
+        }
+        samples.reserve_exact(count);
+        for i in 0..count{
+            let o = offset + i * size;
+            samples.push(if size == 1 { data[o] as usize } else { u16::from_be_bytes([data[o], data[o + 1]]) as usize });
+        }
+    }
+    else{
+        for i in 0..count{
+            match next_token(data, &mut offset) {
+                Some(t) => { samples.push(parse_number(t, "sample")?); }
+                None => { return Err(format!("pnm is truncated: {} samples are declared but only {} are complete", count, i)); }
+            }
+        }
+    }
+    if let Some(s) = samples.iter().find(|s| **s > max){
+        return Err(format!("pnm sample {} is larger than the maximum value {}", s, max));
+    }
+
+    let scale = max as f32;
+    let pixels = samples.chunks(channels).map(|c| {
+        if channels == 1{
+            let v = c[0] as f32 / scale;
+            ColorRGBf32{
+                r: v,
+                g: v,
+                b: v,
+            }
+        }
+        else{
+            ColorRGBf32{
+                r: c[0] as f32 / scale,
+                g: c[1] as f32 / scale,
+                b: c[2] as f32 / scale,
+            }
+        }
+    }).collect();
+    return Texture::new(width, height, pixels);
+}
+
+// whitespace separated, a # comments out the rest of the line
+fn next_token<'a>(data: &'a [u8], offset: &mut usize) -> Option<&'a [u8]>{
+    while *offset < data.len(){
+        if data[*offset] == b'#'{
+            while *offset < data.len() && data[*offset] != b'\n'{
+                *offset += 1;
+            }
+        }
+        else if data[*offset].is_ascii_whitespace(){
+            *offset += 1;
+        }
+        else{
+            break;
+        }
+    }
+    let start = *offset;
+    while *offset < data.len() && !data[*offset].is_ascii_whitespace() && data[*offset] != b'#'{
+        *offset += 1;
+    }
+    return if start == *offset { None } else { Some(&data[start..*offset]) };
+}
+
+fn next_number(data: &[u8], offset: &mut usize, name: &str) -> Result<usize, String>{
+    return match next_token(data, offset) {
+        Some(t) => { parse_number(t, name) }
+        None => { Err(format!("pnm header is missing the {}", name)) }
+    };
+}
+
+fn parse_number(token: &[u8], name: &str) -> Result<usize, String>{
+    let s = String::from_utf8_lossy(token);
+    return match usize::from_str(&s) {
+        Ok(v) => { Ok(v) }
+        Err(_) => { Err(format!("could not parse \"{}\" as the pnm {}", s, name)) }
+    };
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::ascia::io::pnm::parse_pnm;
+
+    #[test]
+    fn test_parse_pnm(){
+        let texture = parse_pnm(b"P3\n# a comment\n2 1 # trailing comment\n255\n255 0 0  0 0 255\n").unwrap();
+        assert_eq!((texture.width(), texture.height()), (2, 1));
+        assert_eq!(texture.pixel(0, 0).r, 1.0);
+        assert_eq!(texture.pixel(1, 0).b, 1.0);
+
+        let mut raw = b"P6 1 2\n65535\n".to_vec();
+        raw.extend_from_slice(&[0xff, 0xff, 0, 0, 0x80, 0x00, 0, 0, 0, 0, 0xff, 0xff]);
+        let texture = parse_pnm(&raw).unwrap();
+        assert_eq!(texture.pixel(0, 0).r, 1.0);
+        assert!(f32::abs(texture.pixel(0, 0).b - 0.5) < 1e-4);
+        assert_eq!(texture.pixel(0, 1).b, 1.0);
+
+        // the byte after the header is whitespace even when it looks like a sample
+        let texture = parse_pnm(b"P5 2 1 255\n\x20\x0a").unwrap();
+        assert!(f32::abs(texture.pixel(0, 0).g - 32.0 / 255.0) < 1e-6);
+        assert!(f32::abs(texture.pixel(1, 0).g - 10.0 / 255.0) < 1e-6);
+
+        let texture = parse_pnm(b"P2 1 1 4 2").unwrap();
+        assert_eq!(texture.pixel(0, 0).r, 0.5);
+    }
+
+    #[test]
+    fn test_parse_pnm_errors(){
+        assert!(parse_pnm(b"P1 1 1 1").err().unwrap().contains("unsupported"));
+        assert!(parse_pnm(b"P3 2 1 255 0 0 0").err().unwrap().contains("truncated"));
+        assert!(parse_pnm(b"P6 2 1 255\n\x00").err().unwrap().contains("truncated"));
+        assert!(parse_pnm(b"P2 1 1 4 5").err().unwrap().contains("larger"));
+        assert!(parse_pnm(b"P2 1 x 4 5").err().unwrap().contains("height"));
+        assert!(parse_pnm(b"").is_err());
+        assert!(parse_pnm(b"P2 4294967296 4294967296 255 1").err().unwrap().contains("too large"));
+        assert!(parse_pnm(b"P2 100000000 100000000 255 1").err().unwrap().contains("truncated"));
+        assert!(parse_pnm(b"P6 100000000 100000000 65535\n\x00\x00").err().unwrap().contains("truncated"));
+    }
+}
@@ -0,0 +1,539 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::rc::Rc;
+use serde::{Deserialize, Serialize};
+use crate::ascia::camera::{SimpleBVHCamera, SimpleCamera};
+use crate::ascia::core::{ALL_LAYERS, AsciaEnvironment, CameraDispatcher, CParticle, FlatMaterial, LambertMaterial, LambertWithShadowMaterial, Local, Mesh, MeshInstance, NodeId, ObjectNode, ObjectNodeAttributeDispatcher, ObjectNodeTree, Polygon, PresetCamera, PresetCParticleMaterial, PresetLight, PresetObjectNodeAttributeDispatcher, PresetPolygonMaterial, TexturedMaterial};
+use crate::ascia::lights::PointLight;
+use crate::ascia::math::{Matrix33, Quaternion, Vec2, Vec3};
+use crate::ascia::texture::{Texture, TextureFilter, TextureWrap};
+
+#[cfg(feature = "wgpu")]
+use crate::ascia::camera_wgpu::GPUWrapper;
+
+// gpu cameras are stored as well so that scene files stay portable, they fall back to their cpu counterpart when no gpu is available
+#[derive(Serialize, Deserialize)]
+pub enum CameraDescription{
+    SimpleCamera{ angle_of_view: (f32, f32), sampling_size: u32, #[serde(default = "all_layers")] layer_mask: u32, #[serde(default = "frustum_culling")] frustum_culling: bool },
+    SimpleBVHCamera{ angle_of_view: (f32, f32), sampling_size: u32, #[serde(default = "all_layers")] layer_mask: u32, #[serde(default = "frustum_culling")] frustum_culling: bool },
+    SimpleCameraGPU{ angle_of_view: (f32, f32), sampling_size: u32, #[serde(default = "all_layers")] layer_mask: u32, #[serde(default = "frustum_culling")] frustum_culling: bool },
+    SimpleBVHCameraGPU{ angle_of_view: (f32, f32), sampling_size: u32, #[serde(default = "all_layers")] layer_mask: u32, #[serde(default = "frustum_culling")] frustum_culling: bool },
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum AttributeDescription{
+    Camera(CameraDescription),
+    Light(PresetLight),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound(serialize = "", deserialize = ""))]
+pub struct ObjectNodeDescription<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMaterials=PresetCParticleMaterial>>{
+    pub tag: String,
+    pub position: Vec3,
+    pub direction: Quaternion,
+    #[serde(default = "unit_scale")]
+    pub scale: Vec3,
+    #[serde(default = "visible")]
+    pub visible: bool,
+    #[serde(default = "all_layers")]
+    pub layers: u32,
+    #[serde(default)]
+    pub attribute: Option<AttributeDescription>,
+    #[serde(default)]
+    pub polygons: Vec<PolygonDescription>,
+    #[serde(default)]
+    pub c_particles: Vec<CParticle<E, Local>>,
+    #[serde(default)]
+    pub mesh: Option<MeshDescription>,
+    #[serde(default)]
+    pub children: Vec<ObjectNodeDescription<E>>,
+}
+
+// the first node instancing a mesh stores its polygons, later ones refer to it by the order in which the meshes first appeared
+#[derive(Serialize, Deserialize)]
+pub struct MeshDescription{
+    pub index: usize,
+    #[serde(default)]
+    pub polygons: Option<Vec<PolygonDescription>>,
+    #[serde(default)]
+    pub material: Option<MaterialDescription>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PolygonDescription{
+    pub poses: Matrix33,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normals: Option<Matrix33>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uvs: Option<[Vec2; 3]>,
+    pub material: MaterialDescription,
+}
+
+// PresetPolygonMaterial, except that textures are shared like meshes: the first material using one stores it
+// and later ones refer to it by the order in which the textures first appeared
+#[derive(Serialize, Deserialize)]
+pub enum MaterialDescription{
+    FlatMaterial(FlatMaterial),
+    LambertMaterial(LambertMaterial),
+    LambertWithShadowMaterial(LambertWithShadowMaterial),
+    TexturedMaterial{ index: usize, #[serde(default)] texture: Option<Texture>, filter: TextureFilter, wrap: TextureWrap, lit: bool, priority: u32 },
+}
+
+// a node and the descriptions of its children, which are generated after it has been added
+type GeneratedNode<E> = (ObjectNode<E, Local>, Vec<ObjectNodeDescription<E>>);
+
+fn unit_scale() -> Vec3{
+    return Vec3{
+        x: 1.0,
+        y: 1.0,
+        z: 1.0,
+    };
+}
+
+fn simple_camera<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial>>(angle_of_view: (f32, f32), sampling_size: u32, layer_mask: u32, frustum_culling: bool) -> SimpleCamera<E>{
+    let mut camera = SimpleCamera::new(angle_of_view, sampling_size);
+    camera.layer_mask = layer_mask;
+    camera.frustum_culling = frustum_culling;
+    return camera;
+}
+
+fn simple_bvh_camera<E: AsciaEnvironment>(angle_of_view: (f32, f32), sampling_size: u32, layer_mask: u32, frustum_culling: bool) -> SimpleBVHCamera<E>{
+    let mut camera = SimpleBVHCamera::new(angle_of_view, sampling_size);
+    camera.layer_mask = layer_mask;
+    camera.frustum_culling = frustum_culling;
+    return camera;
+}
+
+fn visible() -> bool{
+    return true;
+}
+
+fn all_layers() -> u32{
+    return ALL_LAYERS;
+}
+
+fn frustum_culling() -> bool{
+    return true;
+}
+
+impl MaterialDescription{
+    pub fn from_material(material: &PresetPolygonMaterial, textures: &mut Vec<Rc<Texture>>) -> Self{
+        return match material {
+            PresetPolygonMaterial::FlatMaterial(m) => { MaterialDescription::FlatMaterial(*m) }
+            PresetPolygonMaterial::LambertMaterial(m) => { MaterialDescription::LambertMaterial(*m) }
+            PresetPolygonMaterial::LambertWithShadowMaterial(m) => { MaterialDescription::LambertWithShadowMaterial(*m) }
+            PresetPolygonMaterial::TexturedMaterial(m) => {
+                let (index, texture) = match textures.iter().position(|t| Rc::ptr_eq(t, &m.texture)) {
+                    Some(i) => { (i, None) }
+                    None => {
+                        textures.push(m.texture.clone());
+                        (textures.len() - 1, Some((*m.texture).clone()))
+                    }
+                };
+                MaterialDescription::TexturedMaterial{ index: index, texture: texture, filter: m.filter, wrap: m.wrap, lit: m.lit, priority: m.priority }
+            }
+        };
+    }
+
+    pub fn generate_material(self, textures: &mut Vec<Rc<Texture>>) -> Result<PresetPolygonMaterial, String>{
+        return match self {
+            MaterialDescription::FlatMaterial(m) => { Ok(PresetPolygonMaterial::FlatMaterial(m)) }
+            MaterialDescription::LambertMaterial(m) => { Ok(PresetPolygonMaterial::LambertMaterial(m)) }
+            MaterialDescription::LambertWithShadowMaterial(m) => { Ok(PresetPolygonMaterial::LambertWithShadowMaterial(m)) }
+            MaterialDescription::TexturedMaterial{ index, texture, filter, wrap, lit, priority } => {
+                if let Some(t) = texture{
+                    if index != textures.len(){
+                        return Err(format!("texture {} is defined out of order", index));
+                    }
+                    textures.push(Rc::new(t));
+                }
+                match textures.get(index) {
+                    Some(t) => {
+                        Ok(PresetPolygonMaterial::TexturedMaterial(TexturedMaterial{
+                            texture: t.clone(),
+                            filter: filter,
+                            wrap: wrap,
+                            lit: lit,
+                            priority: priority,
+                        }))
+                    }
+                    None => { Err(format!("a material refers to texture {} before it is defined", index)) }
+                }
+            }
+        };
+    }
+}
+
+impl PolygonDescription{
+    pub fn from_polygon<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial>>(polygon: &Polygon<E, Local>, textures: &mut Vec<Rc<Texture>>) -> Self{
+        return PolygonDescription{
+            poses: polygon.poses,
+            normals: polygon.normals,
+            uvs: polygon.uvs,
+            material: MaterialDescription::from_material(&polygon.material, textures),
+        };
+    }
+
+    pub fn generate_polygon<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial>>(self, textures: &mut Vec<Rc<Texture>>) -> Result<Polygon<E, Local>, String>{
+        return Ok(Polygon{
+            poses: self.poses,
+            normals: self.normals,
+            uvs: self.uvs,
+            material: self.material.generate_material(textures)?,
+            _ph: Default::default(),
+        });
+    }
+}
+
+impl CameraDescription{
+    pub fn from_camera<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMaterials=PresetCParticleMaterial, Cameras=PresetCamera<E>, Lights=PresetLight, ObjectNodeAttributes=PresetObjectNodeAttributeDispatcher<E>>>(camera: &PresetCamera<E>) -> Self{
+        return match camera {
+            PresetCamera::SimpleCamera(c) => {
+                CameraDescription::SimpleCamera{ angle_of_view: c.angle_of_view, sampling_size: c.sampling_size, layer_mask: c.layer_mask, frustum_culling: c.frustum_culling }
+            }
+            PresetCamera::SimpleBVHCamera(c) => {
+                CameraDescription::SimpleBVHCamera{ angle_of_view: c.angle_of_view, sampling_size: c.sampling_size, layer_mask: c.layer_mask, frustum_culling: c.frustum_culling }
+            }
+            #[cfg(feature = "wgpu")]
+            PresetCamera::SimpleCameraGPU(c) => {
+                CameraDescription::SimpleCameraGPU{ angle_of_view: c.cpu_camera.angle_of_view, sampling_size: c.cpu_camera.sampling_size, layer_mask: c.cpu_camera.layer_mask, frustum_culling: c.cpu_camera.frustum_culling }
+            }
+            #[cfg(feature = "wgpu")]
+            PresetCamera::SimpleBVHCameraGPU(c) => {
+                CameraDescription::SimpleBVHCameraGPU{ angle_of_view: c.cpu_camera.angle_of_view, sampling_size: c.cpu_camera.sampling_size, layer_mask: c.cpu_camera.layer_mask, frustum_culling: c.cpu_camera.frustum_culling }
+            }
+        };
+    }
+
+    pub fn generate_attribute<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMaterials=PresetCParticleMaterial, Cameras=PresetCamera<E>, Lights=PresetLight, ObjectNodeAttributes=PresetObjectNodeAttributeDispatcher<E>>>(&self) -> PresetObjectNodeAttributeDispatcher<E>{
+        let camera: PresetCamera<E> = match *self {
+            CameraDescription::SimpleCamera{ angle_of_view, sampling_size, layer_mask, frustum_culling } => {
+                PresetCamera::SimpleCamera(simple_camera(angle_of_view, sampling_size, layer_mask, frustum_culling))
+            }
+            CameraDescription::SimpleBVHCamera{ angle_of_view, sampling_size, layer_mask, frustum_culling } => {
+                PresetCamera::SimpleBVHCamera(simple_bvh_camera(angle_of_view, sampling_size, layer_mask, frustum_culling))
+            }
+            CameraDescription::SimpleCameraGPU{ angle_of_view, sampling_size, layer_mask, frustum_culling } => {
+                #[cfg(feature = "wgpu")]
+                {
+                    match GPUWrapper::<E, SimpleCamera<E>>::generate(simple_camera(angle_of_view, sampling_size, layer_mask, frustum_culling)) {
+                        Some(c) => { PresetCamera::SimpleCameraGPU(c) }
+                        None => { PresetCamera::SimpleCamera(simple_camera(angle_of_view, sampling_size, layer_mask, frustum_culling)) }
+                    }
+                }
+                #[cfg(not(feature = "wgpu"))]
+                {
+                    PresetCamera::SimpleCamera(simple_camera(angle_of_view, sampling_size, layer_mask, frustum_culling))
+                }
+            }
+            CameraDescription::SimpleBVHCameraGPU{ angle_of_view, sampling_size, layer_mask, frustum_culling } => {
+                #[cfg(feature = "wgpu")]
+                {
+                    match GPUWrapper::<E, SimpleBVHCamera<E>>::generate(simple_bvh_camera(angle_of_view, sampling_size, layer_mask, frustum_culling)) {
+                        Some(c) => { PresetCamera::SimpleBVHCameraGPU(c) }
+                        None => { PresetCamera::SimpleBVHCamera(simple_bvh_camera(angle_of_view, sampling_size, layer_mask, frustum_culling)) }
+                    }
+                }
+                #[cfg(not(feature = "wgpu"))]
+                {
+                    PresetCamera::SimpleBVHCamera(simple_bvh_camera(angle_of_view, sampling_size, layer_mask, frustum_culling))
+                }
+            }
+        };
+        return camera.make_attribute_enum();
+    }
+}
+
+impl<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMaterials=PresetCParticleMaterial, Cameras=PresetCamera<E>, Lights=PresetLight, ObjectNodeAttributes=PresetObjectNodeAttributeDispatcher<E>>> ObjectNodeDescription<E>{
+    pub fn from_node(tree: &ObjectNodeTree<E, Local>, id: NodeId) -> Self{
+        return ObjectNodeDescription::describe(tree, id, &mut vec![], &mut vec![]);
+    }
+
+    // textures are numbered in the order generate_node meets them: the node's polygons, its mesh, then the children
+    fn describe(tree: &ObjectNodeTree<E, Local>, id: NodeId, meshes: &mut Vec<Rc<Mesh<E>>>, textures: &mut Vec<Rc<Texture>>) -> Self{
+        let node = tree.node(id).unwrap();
+        let polygons = node.polygons.iter().map(|p| PolygonDescription::from_polygon(p, textures)).collect();
+        let mesh = node.mesh.as_ref().map(|m| {
+            let (index, polygons) = match meshes.iter().position(|mesh| Rc::ptr_eq(mesh, &m.mesh)) {
+                Some(i) => { (i, None) }
+                None => {
+                    meshes.push(m.mesh.clone());
+                    (meshes.len() - 1, Some(m.mesh.polygons().iter().map(|p| PolygonDescription::from_polygon(p, textures)).collect()))
+                }
+            };
+            MeshDescription{
+                index: index,
+                polygons: polygons,
+                material: m.material.as_ref().map(|material| MaterialDescription::from_material(material, textures)),
+            }
+        });
+        let attribute = match &*node.attribute.borrow() {
+            Some(PresetObjectNodeAttributeDispatcher::Camera(c)) => {
+                Some(AttributeDescription::Camera(CameraDescription::from_camera(c)))
+            }
+            Some(PresetObjectNodeAttributeDispatcher::Light(PresetLight::PointLight(l))) => {
+                Some(AttributeDescription::Light(PresetLight::PointLight(PointLight{
+                    color: l.color,
+                    power: l.power,
+                })))
+            }
+            None => { None }
+        };
+        return ObjectNodeDescription{
+            tag: node.tag.clone(),
+            position: node.position,
+            direction: node.direction,
+            scale: node.scale,
+            visible: node.visible,
+            layers: node.layers,
+            attribute: attribute,
+            polygons: polygons,
+            c_particles: node.c_particles.clone(),
+            mesh: mesh,
+            children: node.children().iter().map(|c| ObjectNodeDescription::describe(tree, *c, meshes, textures)).collect(),
+        };
+    }
+
+    pub fn generate_tree(self) -> Result<ObjectNodeTree<E, Local>, String>{
+        let (mut meshes, mut textures) = (vec![], vec![]);
+        let (node, children) = self.generate_node(&mut meshes, &mut textures)?;
+        let mut tree = ObjectNodeTree::new(node);
+        let root = tree.root();
+        ObjectNodeDescription::add_children(&mut tree, root, children, &mut meshes, &mut textures)?;
+        return Ok(tree);
+    }
+
+    fn add_children(tree: &mut ObjectNodeTree<E, Local>, parent: NodeId, children: Vec<ObjectNodeDescription<E>>, meshes: &mut Vec<Rc<Mesh<E>>>, textures: &mut Vec<Rc<Texture>>) -> Result<(), String>{
+        for c in children{
+            let (node, grandchildren) = c.generate_node(meshes, textures)?;
+            let id = tree.add_child(parent, node)?;
+            ObjectNodeDescription::add_children(tree, id, grandchildren, meshes, textures)?;
+        }
+        return Ok(());
+    }
+
+    fn generate_node(self, meshes: &mut Vec<Rc<Mesh<E>>>, textures: &mut Vec<Rc<Texture>>) -> Result<GeneratedNode<E>, String>{
+        let mut node = ObjectNode::new(&self.tag);
+        node.position = self.position;
+        node.direction = self.direction;
+        node.scale = self.scale;
+        node.visible = self.visible;
+        node.layers = self.layers;
+        let in_node = |e: String| format!("node \"{}\": {}", self.tag, e);
+        for p in self.polygons{
+            node.polygons.push(p.generate_polygon(textures).map_err(in_node)?);
+        }
+        node.c_particles = self.c_particles;
+        if let Some(m) = self.mesh{
+            if let Some(polygons) = m.polygons{
+                if m.index != meshes.len(){
+                    return Err(format!("node \"{}\" defines mesh {} out of order", self.tag, m.index));
+                }
+                let mut generated = Vec::with_capacity(polygons.len());
+                for p in polygons{
+                    generated.push(p.generate_polygon(textures).map_err(in_node)?);
+                }
+                meshes.push(Rc::new(Mesh::new(generated)));
+            }
+            let mesh = match meshes.get(m.index) {
+                Some(mesh) => { mesh.clone() }
+                None => { return Err(format!("node \"{}\" refers to mesh {} before it is defined", self.tag, m.index)); }
+            };
+            node.mesh = Some(MeshInstance{
+                mesh: mesh,
+                material: match m.material {
+                    Some(material) => { Some(material.generate_material(textures).map_err(in_node)?) }
+                    None => { None }
+                },
+            });
+        }
+        match self.attribute {
+            Some(AttributeDescription::Camera(c)) => {
+                node.attribute = c.generate_attribute().make_shared();
+            }
+            Some(AttributeDescription::Light(l)) => {
+                node.attribute = PresetObjectNodeAttributeDispatcher::Light(l).make_shared();
+            }
+            None => {}
+        }
+        return Ok((node, self.children));
+    }
+}
+
+pub fn save_scene<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMaterials=PresetCParticleMaterial, Cameras=PresetCamera<E>, Lights=PresetLight, ObjectNodeAttributes=PresetObjectNodeAttributeDispatcher<E>>, P: AsRef<Path> + ?Sized>(path: &P, tree: &ObjectNodeTree<E, Local>) -> Result<(), String>{
+    if let Ok(file) = File::create(path){
+        return write_scene(&mut BufWriter::new(file), tree);
+    }
+    return Err(format!("could not create file: {:?}", path.as_ref()));
+}
+
+pub fn write_scene<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMaterials=PresetCParticleMaterial, Cameras=PresetCamera<E>, Lights=PresetLight, ObjectNodeAttributes=PresetObjectNodeAttributeDispatcher<E>>, W: Write>(writer: &mut W, tree: &ObjectNodeTree<E, Local>) -> Result<(), String>{
+    return match serde_json::to_writer_pretty(&mut *writer, &ObjectNodeDescription::from_node(tree, tree.root())) {
+        Ok(_) => {
+            match writer.flush() {
+                Ok(_) => { Ok(()) }
+                Err(e) => { Err(e.to_string()) }
+            }
+        }
+        Err(e) => { Err(e.to_string()) }
+    };
+}
+
+pub fn load_scene<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMaterials=PresetCParticleMaterial, Cameras=PresetCamera<E>, Lights=PresetLight, ObjectNodeAttributes=PresetObjectNodeAttributeDispatcher<E>>, P: AsRef<Path> + ?Sized>(path: &P) -> Result<ObjectNodeTree<E, Local>, String>{
+    if let Ok(file) = File::open(path){
+        return parse_scene(BufReader::new(file));
+    }
+    return Err(format!("could not open file: {:?}", path.as_ref()));
+}
+
+pub fn parse_scene<E: AsciaEnvironment<PolygonMaterials=PresetPolygonMaterial, CParticleMaterials=PresetCParticleMaterial, Cameras=PresetCamera<E>, Lights=PresetLight, ObjectNodeAttributes=PresetObjectNodeAttributeDispatcher<E>>, R: Read>(reader: R) -> Result<ObjectNodeTree<E, Local>, String>{
+    let description: ObjectNodeDescription<E> = match serde_json::from_reader(reader) {
+        Ok(d) => { d }
+        Err(e) => { return Err(e.to_string()); }
+    };
+    return description.generate_tree();
+}
+
+#[cfg(test)]
+mod tests{
+    use std::f32::consts::PI;
+    use std::rc::Rc;
+    use crate::ascia::camera::SimpleBVHCamera;
+    use crate::ascia::color::ColorRGBf32;
+    use crate::ascia::core::{CParticle, CParticleMode, LambertMaterial, Mesh, MeshInstance, ObjectNode, ObjectNodeAttributeDispatcher, ObjectNodeTree, PresetAsciaEnvironment, PresetCamera, PresetCParticleMaterial, PresetLight, PresetObjectNodeAttributeDispatcher, PresetPolygonMaterial, TexturedMaterial};
+    use crate::ascia::io::scene::{parse_scene, write_scene};
+    use crate::ascia::lights::PointLight;
+    use crate::ascia::math::{Quaternion, Vec3};
+    use crate::ascia::primitives::PrimitiveGenerator;
+    use crate::ascia::texture::Texture;
+
+    #[test]
+    fn test_scene_round_trip(){
+        let mut root = ObjectNodeTree::new(ObjectNode::<PresetAsciaEnvironment, _>::new("root"));
+        let mut cube = ObjectNode::from("cube", PrimitiveGenerator::cube(1.0, PresetPolygonMaterial::LambertMaterial(LambertMaterial{
+            color: ColorRGBf32{
+                r: 0.2,
+                g: 0.4,
+                b: 0.6,
+            },
+            priority: 3,
+        })));
+        cube.position = Vec3{ x: 1.0, y: 2.0, z: 3.0 };
+        cube.direction = Quaternion::new(&Vec3{ x: 0.0, y: 1.0, z: 0.0 }, PI / 3.0, 1.0);
+        cube.scale = Vec3{ x: 1.0, y: 2.0, z: 0.5 };
+        cube.layers = 0b101;
+        cube.c_particles.push(CParticle{
+            position: Vec3{ x: 0.0, y: 5.0, z: 0.0 },
+            velocity: Default::default(),
+            c: '*',
+            threshold: 0.1,
+            mode: CParticleMode::SPHERE,
+            material: PresetCParticleMaterial::default(),
+            _ph: Default::default(),
+        });
+        let mut light = ObjectNode::new("light");
+        light.attribute = PresetObjectNodeAttributeDispatcher::from(PointLight{
+            color: ColorRGBf32{
+                r: 1.0,
+                g: 0.5,
+                b: 0.5,
+            },
+            power: 1.4,
+        }).make_shared();
+        light.visible = false;
+        let mut camera = ObjectNode::new("camera");
+        let mut bvh_camera = SimpleBVHCamera::new((PI / 2.0, PI / 4.0), 3);
+        bvh_camera.layer_mask = 0b11;
+        bvh_camera.frustum_culling = false;
+        camera.attribute = PresetObjectNodeAttributeDispatcher::from(PresetCamera::SimpleBVHCamera(bvh_camera)).make_shared();
+        root.add_child(root.root(), camera).unwrap();
+        let cube = root.add_child(root.root(), cube).unwrap();
+        root.add_child(cube, light).unwrap();
+
+        let mesh = Rc::new(Mesh::new(PrimitiveGenerator::cube(2.0, PresetPolygonMaterial::default())));
+        root.add_child(root.root(), ObjectNode::from_mesh("pot 1", MeshInstance::new(mesh.clone()))).unwrap();
+        let mut tinted = MeshInstance::new(mesh.clone());
+        tinted.material = Some(PresetPolygonMaterial::FlatMaterial(Default::default()));
+        root.add_child(cube, ObjectNode::from_mesh("pot 2", tinted)).unwrap();
+
+        let mut buf = vec![];
+        write_scene(&mut buf, &root).unwrap();
+        let loaded = parse_scene::<PresetAsciaEnvironment, _>(&buf[..]).unwrap();
+
+        let cube = loaded.child_by_path("cube").unwrap();
+        assert_eq!(cube.position, Vec3{ x: 1.0, y: 2.0, z: 3.0 });
+        assert_eq!(cube.direction, Quaternion::new(&Vec3{ x: 0.0, y: 1.0, z: 0.0 }, PI / 3.0, 1.0));
+        assert_eq!(cube.scale, Vec3{ x: 1.0, y: 2.0, z: 0.5 });
+        assert_eq!(cube.layers, 0b101);
+        assert!(cube.visible);
+        assert!(!loaded.child_by_path("cube/light").unwrap().visible);
+        assert_eq!(cube.polygons.len(), 12);
+        assert!(matches!(cube.polygons[0].material, PresetPolygonMaterial::LambertMaterial(m) if m.priority == 3 && m.color.b == 0.6));
+        assert_eq!(cube.c_particles[0].c, '*');
+        assert!(cube.c_particles[0].mode == CParticleMode::SPHERE);
+        let light = loaded.child_by_path("cube/light").unwrap().attribute.borrow();
+        assert!(matches!(light.as_ref().unwrap().light(), Some(PresetLight::PointLight(l)) if l.power == 1.4));
+        let camera = loaded.child_by_path("camera").unwrap().attribute.borrow();
+        assert!(matches!(camera.as_ref().unwrap().camera(), Some(PresetCamera::SimpleBVHCamera(c)) if c.sampling_size == 3 && c.angle_of_view.0 == PI / 2.0 && c.layer_mask == 0b11 && !c.frustum_culling));
+
+        let (pot1, pot2) = (loaded.child_by_path("pot 1").unwrap().mesh.as_ref().unwrap(), loaded.child_by_path("cube/pot 2").unwrap().mesh.as_ref().unwrap());
+        assert!(Rc::ptr_eq(&pot1.mesh, &pot2.mesh));
+        assert_eq!(pot1.mesh.polygons().len(), 12);
+        assert!(pot1.material.is_none());
+        assert!(matches!(pot2.material, Some(PresetPolygonMaterial::FlatMaterial(_))));
+
+        let mut again = vec![];
+        write_scene(&mut again, &loaded).unwrap();
+        assert_eq!(buf, again);
+    }
+
+    #[test]
+    fn test_scene_shares_textures(){
+        let gray = ColorRGBf32{ r: 0.5, g: 0.5, b: 0.5 };
+        let material = PresetPolygonMaterial::TexturedMaterial(TexturedMaterial::new(Rc::new(Texture::new(2, 2, vec![gray; 4]).unwrap())));
+        let mut root = ObjectNodeTree::new(ObjectNode::<PresetAsciaEnvironment, _>::new("root"));
+        root.add_child(root.root(), ObjectNode::from("floor", PrimitiveGenerator::cube(1.0, material.clone()))).unwrap();
+        let mut tiled = MeshInstance::new(Rc::new(Mesh::new(PrimitiveGenerator::cube(2.0, PresetPolygonMaterial::default()))));
+        tiled.material = Some(material);
+        root.add_child(root.root(), ObjectNode::from_mesh("wall", tiled)).unwrap();
+
+        // twelve polygons and a mesh use the texture, but its pixels are written once
+        let mut buf = vec![];
+        write_scene(&mut buf, &root).unwrap();
+        assert_eq!(String::from_utf8(buf.clone()).unwrap().matches("pixels").count(), 1);
+
+        let loaded = parse_scene::<PresetAsciaEnvironment, _>(&buf[..]).unwrap();
+        let texture_of = |m: &PresetPolygonMaterial| match m {
+            PresetPolygonMaterial::TexturedMaterial(t) => { t.texture.clone() }
+            _ => { panic!("the material lost its texture") }
+        };
+        let floor = loaded.child_by_path("floor").unwrap();
+        let first = texture_of(&floor.polygons[0].material);
+        assert!(floor.polygons.iter().all(|p| Rc::ptr_eq(&texture_of(&p.material), &first)));
+        assert!(Rc::ptr_eq(&texture_of(loaded.child_by_path("wall").unwrap().mesh.as_ref().unwrap().material.as_ref().unwrap()), &first));
+        assert_eq!(first.pixel(1, 1).g, 0.5);
+        assert_eq!(first.average().r, 0.5);
+    }
+
+    #[test]
+    fn test_parse_scene_errors(){
+        let duplicated = r#"{ "tag": "root", "position": { "x": 0, "y": 0, "z": 0 }, "direction": { "vec4": { "w": 1, "x": 0, "y": 0, "z": 0 } },
+            "children": [
+                { "tag": "a", "position": { "x": 0, "y": 0, "z": 0 }, "direction": { "vec4": { "w": 1, "x": 0, "y": 0, "z": 0 } } },
+                { "tag": "a", "position": { "x": 0, "y": 0, "z": 0 }, "direction": { "vec4": { "w": 1, "x": 0, "y": 0, "z": 0 } } }
+            ] }"#;
+        assert!(parse_scene::<PresetAsciaEnvironment, _>(duplicated.as_bytes()).err().unwrap().contains("already has a child tagged \"a\""));
+        let dangling = r#"{ "tag": "root", "position": { "x": 0, "y": 0, "z": 0 }, "direction": { "vec4": { "w": 1, "x": 0, "y": 0, "z": 0 } }, "mesh": { "index": 0 } }"#;
+        assert!(parse_scene::<PresetAsciaEnvironment, _>(dangling.as_bytes()).err().unwrap().contains("refers to mesh 0 before it is defined"));
+        assert!(parse_scene::<PresetAsciaEnvironment, _>("{ \"tag\": 1 }".as_bytes()).is_err());
+        let textured = |index: usize, texture: &str| format!(r#"{{ "poses": {{ "v1": {{ "x": 0, "y": 0, "z": 0 }}, "v2": {{ "x": 1, "y": 0, "z": 0 }}, "v3": {{ "x": 0, "y": 1, "z": 0 }} }},
+            "material": {{ "TexturedMaterial": {{ "index": {}, {} "filter": "Nearest", "wrap": "Repeat", "lit": false, "priority": 0 }} }} }}"#, index, texture);
+        let with_polygon = |polygon: String| format!(r#"{{ "tag": "root", "position": {{ "x": 0, "y": 0, "z": 0 }}, "direction": {{ "vec4": {{ "w": 1, "x": 0, "y": 0, "z": 0 }} }}, "polygons": [{}] }}"#, polygon);
+        assert!(parse_scene::<PresetAsciaEnvironment, _>(with_polygon(textured(0, "")).as_bytes()).err().unwrap().contains("refers to texture 0 before it is defined"));
+        // a stored texture goes through Texture::new, so its size has to match its pixels
+        let short = textured(0, r#""texture": { "width": 2, "height": 2, "pixels": [{ "r": 1, "g": 1, "b": 1 }] },"#);
+        assert!(parse_scene::<PresetAsciaEnvironment, _>(with_polygon(short).as_bytes()).err().unwrap().contains("needs 4 pixels"));
+    }
+}
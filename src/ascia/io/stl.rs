@@ -0,0 +1,285 @@
+use std::fs::File;
+use std::io::{BufRead, BufWriter, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+use crate::ascia::core::{AsciaEnvironment, Global, Local, ObjectNodeTree, Polygon};
+use crate::ascia::math::Vec3;
+
+pub fn load_stl<E: AsciaEnvironment, P: AsRef<Path> + ?Sized>(path: &P, material: &E::PolygonMaterials) -> Result<Vec<Polygon<E, Local>>, String>{
+    let mut buf = vec![];
+    if let Ok(mut f) = File::open(path){
+        if let Err(e) = f.read_to_end(&mut buf){
+            return Err(e.to_string());
+        }
+        return parse_stl(&buf, material);
+    }
+    return Err(format!("could not open file: {:?}", path.as_ref()));
+}
+
+pub fn parse_stl<E: AsciaEnvironment>(data: &[u8], material: &E::PolygonMaterials) -> Result<Vec<Polygon<E, Local>>, String>{
+    // binary files may also start with "solid", so trust the size declared in the header first
+    if data.len() >= 84{
+        let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+        if data.len() == 84 + count * 50{
+            return parse_stl_binary(data, material);
+        }
+    }
+    if data.trim_ascii_start().starts_with(b"solid"){
+        return parse_stl_ascii(data, material);
+    }
+    return parse_stl_binary(data, material);
+}
+
+pub fn parse_stl_binary<E: AsciaEnvironment>(data: &[u8], material: &E::PolygonMaterials) -> Result<Vec<Polygon<E, Local>>, String>{
+    if data.len() < 84{
+        return Err(format!("binary stl is truncated: the header needs 84 bytes but the file has {}", data.len()));
+    }
+    let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+    if data.len() < 84 + count * 50{
+        return Err(format!("binary stl is truncated: {} facets are declared but only {} are complete", count, (data.len() - 84) / 50));
+    }
+    let read_vec3 = |offset: usize| -> Vec3 {
+        let f = |o: usize| f32::from_le_bytes([data[o], data[o + 1], data[o + 2], data[o + 3]]);
+        return Vec3{
+            x: f(offset),
+            y: f(offset + 4),
+            z: f(offset + 8),
+        };
+    };
+    let mut polygons = Vec::with_capacity(count);
+    for i in 0..count{
+        let offset = 84 + i * 50;
+        polygons.push(facet(&read_vec3(offset), &read_vec3(offset + 12), &read_vec3(offset + 24), &read_vec3(offset + 36), material));
+    }
+    return Ok(polygons);
+}
+
+pub fn parse_stl_ascii<E: AsciaEnvironment, R: BufRead>(reader: R, material: &E::PolygonMaterials) -> Result<Vec<Polygon<E, Local>>, String>{
+    let mut tokens: Vec<(usize, String)> = vec![];
+    for (n, line) in reader.lines().enumerate(){
+        match line {
+            Ok(l) => {
+                for t in l.split_whitespace(){
+                    tokens.push((n + 1, t.to_string()));
+                }
+            }
+            Err(e) => { return Err(format!("line {}: {}", n + 1, e)); }
+        }
+    }
+    let last_line = tokens.last().map(|t| t.0).unwrap_or(0);
+    let mut iter = tokens.into_iter().peekable();
+
+    let header_line = expect_token(&mut iter, last_line, "solid")?;
+    // skip the optional solid name
+    while let Some((n, t)) = iter.peek(){
+        if *n != header_line || t == "facet" || t == "endsolid"{
+            break;
+        }
+        iter.next();
+    }
+
+    let mut polygons = vec![];
+    loop {
+        match iter.next() {
+            Some((_, t)) if t == "facet" => {
+                expect_token(&mut iter, last_line, "normal")?;
+                let normal = read_token_vec3(&mut iter, last_line)?;
+                expect_token(&mut iter, last_line, "outer")?;
+                expect_token(&mut iter, last_line, "loop")?;
+                let mut vs = [Vec3::default(); 3];
+                for v in vs.iter_mut(){
+                    expect_token(&mut iter, last_line, "vertex")?;
+                    *v = read_token_vec3(&mut iter, last_line)?;
+                }
+                expect_token(&mut iter, last_line, "endloop")?;
+                expect_token(&mut iter, last_line, "endfacet")?;
+                polygons.push(facet(&normal, &vs[0], &vs[1], &vs[2], material));
+            }
+            Some((_, t)) if t == "endsolid" => {
+                break;
+            }
+            Some((n, t)) => { return Err(format!("line {}: expected \"facet\" or \"endsolid\", found \"{}\"", n, t)); }
+            None => { return Err(format!("line {}: file is truncated, expected \"endsolid\"", last_line)); }
+        }
+    }
+    return Ok(polygons);
+}
+
+type Tokens = std::iter::Peekable<std::vec::IntoIter<(usize, String)>>;
+
+fn expect_token(iter: &mut Tokens, last_line: usize, keyword: &str) -> Result<usize, String>{
+    return match iter.next() {
+        Some((n, t)) if t == keyword => { Ok(n) }
+        Some((n, t)) => { Err(format!("line {}: expected \"{}\", found \"{}\"", n, keyword, t)) }
+        None => { Err(format!("line {}: file is truncated, expected \"{}\"", last_line, keyword)) }
+    };
+}
+
+fn read_token_vec3(iter: &mut Tokens, last_line: usize) -> Result<Vec3, String>{
+    let mut v = [0.0f32; 3];
+    for c in v.iter_mut(){
+        *c = match iter.next() {
+            Some((n, t)) => {
+                match f32::from_str(&t) {
+                    Ok(f) => { f }
+                    Err(_) => { return Err(format!("line {}: could not parse \"{}\" as a number", n, t)); }
+                }
+            }
+            None => { return Err(format!("line {}: file is truncated, expected a coordinate", last_line)); }
+        };
+    }
+    return Ok(Vec3{
+        x: v[0],
+        y: v[1],
+        z: v[2],
+    });
+}
+
+fn facet<E: AsciaEnvironment>(normal: &Vec3, v1: &Vec3, v2: &Vec3, v3: &Vec3, material: &E::PolygonMaterials) -> Polygon<E, Local>{
+    let mut polygon = if ((*v2 - *v1) ^ (*v3 - *v1)) * *normal < 0.0 {
+        Polygon::new(v1, v3, v2)
+    } else {
+        Polygon::new(v1, v2, v3)
+    };
+    polygon.material = material.clone();
+    return polygon;
+}
+
+fn flatten_polygons<E: AsciaEnvironment>(tree: &ObjectNodeTree<E, Global>) -> Vec<Polygon<E, Global>>{
+    let mut polygons = vec![];
+    for n in tree.iter(){
+        polygons.extend(n.polygons.iter().cloned());
+        polygons.extend(n.mesh_polygons());
+    }
+    return polygons;
+}
+
+fn facet_normal<E: AsciaEnvironment>(p: &Polygon<E, Global>) -> Vec3{
+    let n = (p.poses.v2 - p.poses.v1) ^ (p.poses.v3 - p.poses.v1);
+    let l = n.norm();
+    return if l == 0.0 { Vec3::default() } else { n / l };
+}
+
+pub fn save_stl_binary<E: AsciaEnvironment, P: AsRef<Path> + ?Sized>(path: &P, tree: &ObjectNodeTree<E, Global>) -> Result<(), String>{
+    if let Ok(file) = File::create(path){
+        return write_stl_binary(&mut BufWriter::new(file), tree);
+    }
+    return Err(format!("could not create file: {:?}", path.as_ref()));
+}
+
+pub fn write_stl_binary<E: AsciaEnvironment, W: Write>(writer: &mut W, tree: &ObjectNodeTree<E, Global>) -> Result<(), String>{
+    let polygons = flatten_polygons(tree);
+    let mut buf = Vec::with_capacity(84 + polygons.len() * 50);
+    let mut header = [0u8; 80];
+    let name = tree.root_node().tag.as_bytes();
+    let l = usize::min(name.len(), 80);
+    header[..l].copy_from_slice(&name[..l]);
+    buf.extend_from_slice(&header);
+    buf.extend_from_slice(&(polygons.len() as u32).to_le_bytes());
+    for p in &polygons{
+        for v in [facet_normal(p), p.poses.v1, p.poses.v2, p.poses.v3]{
+            buf.extend_from_slice(&v.x.to_le_bytes());
+            buf.extend_from_slice(&v.y.to_le_bytes());
+            buf.extend_from_slice(&v.z.to_le_bytes());
+        }
+        buf.extend_from_slice(&0u16.to_le_bytes());
+    }
+    return match writer.write_all(&buf).and_then(|_| writer.flush()) {
+        Ok(_) => { Ok(()) }
+        Err(e) => { Err(e.to_string()) }
+    };
+}
+
+pub fn save_stl_ascii<E: AsciaEnvironment, P: AsRef<Path> + ?Sized>(path: &P, tree: &ObjectNodeTree<E, Global>) -> Result<(), String>{
+    if let Ok(file) = File::create(path){
+        return write_stl_ascii(&mut BufWriter::new(file), tree);
+    }
+    return Err(format!("could not create file: {:?}", path.as_ref()));
+}
+
+pub fn write_stl_ascii<E: AsciaEnvironment, W: Write>(writer: &mut W, tree: &ObjectNodeTree<E, Global>) -> Result<(), String>{
+    let name = tree.root_node().tag.replace(char::is_whitespace, "_");
+    let mut s = format!("solid {}\n", name);
+    for p in &flatten_polygons(tree){
+        let n = facet_normal(p);
+        s.push_str(&format!("  facet normal {:e} {:e} {:e}\n    outer loop\n", n.x, n.y, n.z));
+        for v in [&p.poses.v1, &p.poses.v2, &p.poses.v3]{
+            s.push_str(&format!("      vertex {:e} {:e} {:e}\n", v.x, v.y, v.z));
+        }
+        s.push_str("    endloop\n  endfacet\n");
+    }
+    s.push_str(&format!("endsolid {}\n", name));
+    return match writer.write_all(s.as_bytes()).and_then(|_| writer.flush()) {
+        Ok(_) => { Ok(()) }
+        Err(e) => { Err(e.to_string()) }
+    };
+}
+
+#[cfg(test)]
+mod tests{
+    use std::rc::Rc;
+    use crate::ascia::core::{Mesh, MeshInstance, ObjectNode, ObjectNodeTree, PresetAsciaEnvironment, PresetPolygonMaterial};
+    use crate::ascia::io::stl::{parse_stl, write_stl_ascii, write_stl_binary};
+    use crate::ascia::math::Vec3;
+    use crate::ascia::primitives::PrimitiveGenerator;
+
+    #[test]
+    fn test_stl_round_trip(){
+        let mut cube = ObjectNodeTree::new(ObjectNode::<PresetAsciaEnvironment, _>::new("cube"));
+        cube.add_child(cube.root(), ObjectNode::from("inner", PrimitiveGenerator::cube(2.0, PresetPolygonMaterial::default()))).unwrap();
+        let global = cube.generate_global_nodes();
+
+        let mut binary = vec![];
+        write_stl_binary(&mut binary, &global).unwrap();
+        let mut ascii = vec![];
+        write_stl_ascii(&mut ascii, &global).unwrap();
+
+        let expected = &global.child_by_path("inner").unwrap().polygons;
+        for buf in [binary, ascii]{
+            let loaded = parse_stl::<PresetAsciaEnvironment>(&buf, &PresetPolygonMaterial::default()).unwrap();
+            assert_eq!(loaded.len(), 12);
+            for (a, b) in expected.iter().zip(loaded.iter()){
+                assert_eq!(a.poses.v1, b.poses.v1);
+                assert_eq!(a.poses.v2, b.poses.v2);
+                assert_eq!(a.poses.v3, b.poses.v3);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stl_mesh_instances(){
+        let mut tree = ObjectNodeTree::new(ObjectNode::<PresetAsciaEnvironment, _>::new("pots"));
+        let mesh = Rc::new(Mesh::new(PrimitiveGenerator::cube(2.0, PresetPolygonMaterial::default())));
+        let mut pot = ObjectNode::from_mesh("pot", MeshInstance::new(mesh.clone()));
+        pot.position = Vec3{ x: 5.0, y: 0.0, z: 0.0 };
+        tree.add_child(tree.root(), pot).unwrap();
+        tree.add_child(tree.root(), ObjectNode::from("cube", PrimitiveGenerator::cube(2.0, PresetPolygonMaterial::default()))).unwrap();
+        let global = tree.generate_global_nodes();
+
+        let mut binary = vec![];
+        write_stl_binary(&mut binary, &global).unwrap();
+        let mut ascii = vec![];
+        write_stl_ascii(&mut ascii, &global).unwrap();
+
+        let expected = global.child_by_path("pot").unwrap().mesh_polygons();
+        for buf in [binary, ascii]{
+            let loaded = parse_stl::<PresetAsciaEnvironment>(&buf, &PresetPolygonMaterial::default()).unwrap();
+            assert_eq!(loaded.len(), 24);
+            assert!(expected.iter().all(|a| loaded.iter().any(|b| a.poses.v1 == b.poses.v1 && a.poses.v2 == b.poses.v2 && a.poses.v3 == b.poses.v3)));
+        }
+    }
+
+    #[test]
+    fn test_stl_winding_and_errors(){
+        let src = "solid flipped\nfacet normal 0 0 -1\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nvertex 0 1 0\nendloop\nendfacet\nendsolid flipped\n";
+        let loaded = parse_stl::<PresetAsciaEnvironment>(src.as_bytes(), &PresetPolygonMaterial::default()).unwrap();
+        let p = &loaded[0].poses;
+        assert!(((p.v2 - p.v1) ^ (p.v3 - p.v1)).z < 0.0);
+
+        let truncated = "solid a\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\n";
+        assert!(parse_stl::<PresetAsciaEnvironment>(truncated.as_bytes(), &PresetPolygonMaterial::default()).err().unwrap().starts_with("line 4:"));
+        let mut binary = vec![0u8; 84];
+        binary[80] = 2;
+        assert!(parse_stl::<PresetAsciaEnvironment>(&binary, &PresetPolygonMaterial::default()).err().unwrap().contains("truncated"));
+    }
+}
@@ -0,0 +1,146 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use crate::ascia::core::{AsciaEnvironment, CoordinateType, Local, Polygon};
+use crate::ascia::math::{Matrix33, Vec3};
+
+// https://users.cs.utah.edu/~dejohnso/models/teapot.html
+pub fn load_tris<E: AsciaEnvironment, P: AsRef<Path> + ?Sized>(path: &P, material: &E::PolygonMaterials) -> Result<Vec<Polygon<E, Local>>, String>{
+    if let Ok(f) = File::open(path){
+        return parse_tris(BufReader::new(f), material);
+    }
+    return Err(format!("could not open file: {:?}", path.as_ref()));
+}
+
+pub fn parse_tris<E: AsciaEnvironment, R: BufRead>(reader: R, material: &E::PolygonMaterials) -> Result<Vec<Polygon<E, Local>>, String>{
+    let mut lines = reader.lines().enumerate().filter(|(_, l)| {
+        match l {
+            Ok(s) => { !s.trim().is_empty() }
+            Err(_) => { true }
+        }
+    });
+
+    let num_polygon = match lines.next() {
+        Some((n, Ok(s))) => {
+            match usize::from_str(s.trim()) {
+                Ok(c) => { c }
+                Err(_) => { return Err(format!("line {}: could not parse \"{}\" as a triangle count", n + 1, s.trim())); }
+            }
+        }
+        Some((n, Err(e))) => { return Err(format!("line {}: {}", n + 1, e)); }
+        None => { return Err("file is empty".to_string()); }
+    };
+
+    // the count comes from the file, so it does not size the allocation
+    let mut polygons = vec![];
+    for i in 0..num_polygon{
+        let mut vs = [Vec3::default(); 3];
+        for v in vs.iter_mut(){
+            *v = match lines.next() {
+                Some((n, Ok(s))) => { parse_vertex(&s, n + 1)? }
+                Some((n, Err(e))) => { return Err(format!("line {}: {}", n + 1, e)); }
+                None => { return Err(format!("file is truncated: {} triangles are declared but only {} are complete", num_polygon, i)); }
+            };
+        }
+        polygons.push(Polygon{
+            poses: Matrix33{
+                v1: vs[0],
+                v2: vs[1],
+                v3: vs[2],
+            },
+            normals: None,
+            uvs: None,
+            material: material.clone(),
+            _ph: Default::default(),
+        });
+    }
+
+    if let Some((n, _)) = lines.next(){
+        return Err(format!("line {}: {} triangles are declared but the file continues", n + 1, num_polygon));
+    }
+    return Ok(polygons);
+}
+
+pub(crate) fn parse_vertex(s: &str, line_number: usize) -> Result<Vec3, String>{
+    let mut v = [0.0f32; 3];
+    let mut tokens = s.split_whitespace();
+    for c in v.iter_mut(){
+        *c = match tokens.next() {
+            Some(t) => {
+                match f32::from_str(t) {
+                    Ok(f) => { f }
+                    Err(_) => { return Err(format!("line {}: could not parse \"{}\" as a number", line_number, t)); }
+                }
+            }
+            None => { return Err(format!("line {}: expected 3 coordinates", line_number)); }
+        };
+    }
+    if tokens.next().is_some(){
+        return Err(format!("line {}: expected 3 coordinates", line_number));
+    }
+    return Ok(Vec3{
+        x: v[0],
+        y: v[1],
+        z: v[2],
+    });
+}
+
+pub fn save_tris<E: AsciaEnvironment, CO: CoordinateType, P: AsRef<Path> + ?Sized>(path: &P, polygons: &[Polygon<E, CO>]) -> Result<(), String>{
+    if let Ok(file) = File::create(path){
+        return write_tris(&mut BufWriter::new(file), polygons);
+    }
+    return Err(format!("could not create file: {:?}", path.as_ref()));
+}
+
+pub fn write_tris<E: AsciaEnvironment, CO: CoordinateType, W: Write>(writer: &mut W, polygons: &[Polygon<E, CO>]) -> Result<(), String>{
+    let mut s = format!("{}\n", polygons.len());
+    for p in polygons{
+        for v in [&p.poses.v1, &p.poses.v2, &p.poses.v3]{
+            // the shortest text that parses back to the same f32
+            s.push_str(&format!("{} {} {}\n", v.x, v.y, v.z));
+        }
+        s.push('\n');
+    }
+    return match writer.write_all(s.as_bytes()).and_then(|_| writer.flush()) {
+        Ok(_) => { Ok(()) }
+        Err(e) => { Err(e.to_string()) }
+    };
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::ascia::core::{Local, Polygon, PresetAsciaEnvironment, PresetPolygonMaterial};
+    use crate::ascia::io::tris::{parse_tris, write_tris};
+    use crate::ascia::math::Vec3;
+
+    #[test]
+    fn test_tris_round_trip(){
+        let polygons: Vec<Polygon<PresetAsciaEnvironment, Local>> = vec![
+            Polygon::new(&Vec3{ x: 0.0, y: 2.25, z: 1.5 }, &Vec3{ x: 0.168, y: 2.25, z: 1.5 }, &Vec3{ x: 0.0, y: 2.282812, z: 1.484375 }),
+            Polygon::new(&Vec3{ x: -1.0, y: 0.0, z: 0.0 }, &Vec3{ x: 0.0, y: -1.0, z: 0.0 }, &Vec3{ x: 0.0, y: 0.0, z: -1.0 }),
+            Polygon::new(&Vec3{ x: 0.1234567, y: -1e-9, z: 3.4e38 }, &Vec3{ x: 1.0 / 3.0, y: 123456.79, z: f32::MIN_POSITIVE }, &Vec3{ x: 0.0, y: 0.0, z: 1.0 }),
+        ];
+        let mut buf = vec![];
+        write_tris(&mut buf, &polygons).unwrap();
+        let loaded = parse_tris::<PresetAsciaEnvironment, _>(&buf[..], &PresetPolygonMaterial::default()).unwrap();
+        assert_eq!(loaded.len(), 3);
+        for (a, b) in polygons.iter().zip(loaded.iter()){
+            assert_eq!(a.poses.v1, b.poses.v1);
+            assert_eq!(a.poses.v2, b.poses.v2);
+            assert_eq!(a.poses.v3, b.poses.v3);
+        }
+    }
+
+    #[test]
+    fn test_tris_validation(){
+        let truncated = "2\n0 0 0\n1 0 0\n0 1 0\n\n0 0 0\n1 0 0\n";
+        assert!(parse_tris::<PresetAsciaEnvironment, _>(truncated.as_bytes(), &PresetPolygonMaterial::default()).err().unwrap().contains("truncated"));
+        let overlong = "1\n0 0 0\n1 0 0\n0 1 0\n\n0 0 0\n";
+        assert!(parse_tris::<PresetAsciaEnvironment, _>(overlong.as_bytes(), &PresetPolygonMaterial::default()).err().unwrap().starts_with("line 6:"));
+        let broken = "1\n0 0 0\n1 0\n0 1 0\n";
+        assert!(parse_tris::<PresetAsciaEnvironment, _>(broken.as_bytes(), &PresetPolygonMaterial::default()).err().unwrap().starts_with("line 3:"));
+        let huge = "18446744073709551615\n";
+        assert!(parse_tris::<PresetAsciaEnvironment, _>(huge.as_bytes(), &PresetPolygonMaterial::default()).err().unwrap().contains("truncated"));
+    }
+}
@@ -2,6 +2,10 @@ use crate::ascia::color::ColorRGBf32;
 use crate::ascia::core::{AsciaEnvironment, Global, Light, LightDispatcher, ObjectNode, ObjectNodeAttribute, PresetLight, PresetObjectNodeAttributeDispatcher};
 use crate::ascia::math::{Vec3};
 
+#[cfg(feature = "export")]
+use serde::{Deserialize, Serialize};
+
+#[cfg_attr(feature = "export", derive(Serialize, Deserialize))]
 pub struct PointLight{
     pub color: ColorRGBf32,
     pub power: f32,
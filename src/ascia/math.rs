@@ -1,7 +1,11 @@
 use std::f32::consts::PI;
 
+#[cfg(feature = "export")]
+use serde::{Deserialize, Serialize};
+
 #[derive(Debug,Clone,Copy)]
 #[repr(C)]
+#[cfg_attr(feature = "export", derive(Serialize, Deserialize))]
 pub struct Vec2{
     pub x:f32,
     pub y:f32,
@@ -81,6 +85,7 @@ impl Vec2{
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[repr(C)]
+#[cfg_attr(feature = "export", derive(Serialize, Deserialize))]
 pub struct Vec3{
     pub x:f32,
     pub y:f32,
@@ -188,10 +193,20 @@ impl Vec3{
     pub fn rotate_by(&self,rotator:&Quaternion) -> Self{
         return rotator.rotate(self);
     }
+
+    #[inline]
+    pub fn scale_by(&self,scale:&Vec3) -> Self{
+        return Vec3{
+            x:self.x * scale.x,
+            y:self.y * scale.y,
+            z:self.z * scale.z
+        }
+    }
 }
 
 #[derive(Debug,Clone,Copy,PartialEq)]
 #[repr(C)]
+#[cfg_attr(feature = "export", derive(Serialize, Deserialize))]
 pub struct Vec4{
     pub w:f32,
     pub x:f32,
@@ -244,6 +259,7 @@ impl std::ops::Sub<Vec4> for Vec4{
 
 #[derive(Debug,Clone,Copy,PartialEq)]
 #[repr(C)]
+#[cfg_attr(feature = "export", derive(Serialize, Deserialize))]
 pub struct Quaternion {
     pub vec4:Vec4
 }
@@ -350,6 +366,215 @@ impl Quaternion{
     pub fn normalize(&self) -> Self{
         return Quaternion{vec4:self.vec4.normalize()};
     }
+
+    // rotates along the shorter arc, t = 0 gives self and t = 1 gives other, a scale carried by the norm is interpolated linearly
+    pub fn slerp(&self, other: &Quaternion, t: f32) -> Self{
+        let (na, nb) = (self.norm(), other.norm());
+        let a = self.vec4.normalize();
+        let mut b = other.vec4.normalize();
+        let mut d = a.w * b.w + a.x * b.x + a.y * b.y + a.z * b.z;
+        if d < 0.0{
+            b = Vec4{
+                w: -b.w,
+                x: -b.x,
+                y: -b.y,
+                z: -b.z,
+            };
+            d = -d;
+        }
+        // nearly parallel rotations are lerped, the sine below would lose all of its precision
+        let (ka, kb) = if d > 0.9995 {
+            (1.0 - t, t)
+        } else {
+            let theta = f32::acos(d);
+            (f32::sin((1.0 - t) * theta) / f32::sin(theta), f32::sin(t * theta) / f32::sin(theta))
+        };
+        let r = Vec4{
+            w: a.w * ka + b.w * kb,
+            x: a.x * ka + b.x * kb,
+            y: a.y * ka + b.y * kb,
+            z: a.z * ka + b.z * kb,
+        }.normalize();
+        let n = na + (nb - na) * t;
+        return Quaternion{
+            vec4: Vec4{
+                w: r.w * n,
+                x: r.x * n,
+                y: r.y * n,
+                z: r.z * n,
+            },
+        };
+    }
+}
+
+impl Quaternion{
+    // like slerp but the rotation is lerped and normalized, cheaper and close enough for small steps
+    pub fn nlerp(&self, other: &Quaternion, t: f32) -> Self{
+        let (na, nb) = (self.norm(), other.norm());
+        let a = self.vec4.normalize();
+        let b = other.vec4.normalize();
+        let k = if a.w * b.w + a.x * b.x + a.y * b.y + a.z * b.z < 0.0 { -t } else { t };
+        let r = Vec4{
+            w: a.w * (1.0 - t) + b.w * k,
+            x: a.x * (1.0 - t) + b.x * k,
+            y: a.y * (1.0 - t) + b.y * k,
+            z: a.z * (1.0 - t) + b.z * k,
+        }.normalize();
+        let n = na + (nb - na) * t;
+        return Quaternion{
+            vec4: Vec4{
+                w: r.w * n,
+                x: r.x * n,
+                y: r.y * n,
+                z: r.z * n,
+            },
+        };
+    }
+
+    // +x is forward, +y is up and +z is the left of the screen as in SimpleCamera
+    // yaw turns around +y, pitch raises the forward towards +y and roll turns around the forward, applied in this order
+    pub fn from_euler(yaw: f32, pitch: f32, roll: f32) -> Self{
+        return Quaternion::new(&Vec3{
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        }, yaw, 1.0) * Quaternion::new(&Vec3{
+            x: 0.0,
+            y: 0.0,
+            z: 1.0,
+        }, pitch, 1.0) * Quaternion::new(&Vec3{
+            x: 1.0,
+            y: 0.0,
+            z: 0.0,
+        }, roll, 1.0);
+    }
+
+    // (yaw, pitch, roll) as taken by from_euler, the yaw is 0 when looking straight up or down
+    pub fn to_euler(&self) -> (f32, f32, f32){
+        let q = self.normalize();
+        let forward = q.rotate(&Vec3{
+            x: 1.0,
+            y: 0.0,
+            z: 0.0,
+        });
+        let pitch = f32::asin(f32::clamp(forward.y, -1.0, 1.0));
+        let yaw = if f32::abs(forward.y) > 0.99999 { 0.0 } else { f32::atan2(-forward.z, forward.x) };
+        let r = (Quaternion::from_euler(yaw, pitch, 0.0).conjugate() * q).vec4;
+        let mut roll = 2.0 * f32::atan2(r.x, r.w);
+        if roll > PI{
+            roll -= 2.0 * PI;
+        }
+        else if roll <= -PI{
+            roll += 2.0 * PI;
+        }
+        return (yaw, pitch, roll);
+    }
+
+    // turns +x to forward and +y to up, up only has to be somewhere above the forward
+    pub fn look_at(forward: &Vec3, up: &Vec3) -> Self{
+        let f = forward.normalize();
+        let u = *up - (*up * f) * f;
+        if u.norm() <= f32::EPSILON{
+            return Quaternion::rotator(&Vec3{
+                x: 1.0,
+                y: 0.0,
+                z: 0.0,
+            }, &f);
+        }
+        let u = u.normalize();
+        return Quaternion::from_rotation_matrix(&Matrix33{
+            v1: f,
+            v2: u,
+            v3: f ^ u,
+        });
+    }
+
+    // the angle is within [0, π], the axis is +x for the identity
+    pub fn to_axis_angle(&self) -> (Vec3, f32){
+        let q = self.vec4.normalize();
+        let sign = if q.w < 0.0 { -1.0 } else { 1.0 };
+        let w = f32::clamp(q.w * sign, -1.0, 1.0);
+        let s = f32::sqrt(1.0 - w * w);
+        if s < 1e-6{
+            return (Vec3{
+                x: 1.0,
+                y: 0.0,
+                z: 0.0,
+            }, 0.0);
+        }
+        return (Vec3{
+            x: q.x * sign / s,
+            y: q.y * sign / s,
+            z: q.z * sign / s,
+        }, 2.0 * f32::acos(w));
+    }
+
+    // the columns are the images of the axes, any scale carried by the norm is dropped
+    pub fn to_rotation_matrix(&self) -> Matrix33{
+        let q = self.normalize();
+        return Matrix33{
+            v1: q.rotate(&Vec3{
+                x: 1.0,
+                y: 0.0,
+                z: 0.0,
+            }),
+            v2: q.rotate(&Vec3{
+                x: 0.0,
+                y: 1.0,
+                z: 0.0,
+            }),
+            v3: q.rotate(&Vec3{
+                x: 0.0,
+                y: 0.0,
+                z: 1.0,
+            }),
+        };
+    }
+
+    // m has to be a rotation, its columns are the images of the axes
+    pub fn from_rotation_matrix(m: &Matrix33) -> Self{
+        let (m00, m10, m20) = (m.v1.x, m.v1.y, m.v1.z);
+        let (m01, m11, m21) = (m.v2.x, m.v2.y, m.v2.z);
+        let (m02, m12, m22) = (m.v3.x, m.v3.y, m.v3.z);
+        let trace = m00 + m11 + m22;
+        // the largest of the four components is computed first so that the divisions stay stable
+        let v = if trace > 0.0 {
+            let s = f32::sqrt(trace + 1.0) * 2.0;
+            Vec4{
+                w: s / 4.0,
+                x: (m21 - m12) / s,
+                y: (m02 - m20) / s,
+                z: (m10 - m01) / s,
+            }
+        } else if m00 > m11 && m00 > m22 {
+            let s = f32::sqrt(1.0 + m00 - m11 - m22) * 2.0;
+            Vec4{
+                w: (m21 - m12) / s,
+                x: s / 4.0,
+                y: (m01 + m10) / s,
+                z: (m02 + m20) / s,
+            }
+        } else if m11 > m22 {
+            let s = f32::sqrt(1.0 + m11 - m00 - m22) * 2.0;
+            Vec4{
+                w: (m02 - m20) / s,
+                x: (m01 + m10) / s,
+                y: s / 4.0,
+                z: (m12 + m21) / s,
+            }
+        } else {
+            let s = f32::sqrt(1.0 + m22 - m00 - m11) * 2.0;
+            Vec4{
+                w: (m10 - m01) / s,
+                x: (m02 + m20) / s,
+                y: (m12 + m21) / s,
+                z: s / 4.0,
+            }
+        };
+        return Quaternion{
+            vec4: v.normalize(),
+        };
+    }
 }
 
 impl std::default::Default for Quaternion{
@@ -368,7 +593,7 @@ impl std::default::Default for Quaternion{
 #[cfg(test)]
 mod tests{
     use std::f32::consts::PI;
-    use crate::ascia::math::{Quaternion, Vec3};
+    use crate::ascia::math::{Matrix44, Quaternion, Vec3, Vec4};
 
     #[test]
     pub fn test_rotate(){
@@ -393,6 +618,194 @@ mod tests{
             assert!(f32::abs(diff.z) <= f32::EPSILON * 2.0);
         }
     }
+    #[test]
+    pub fn test_slerp(){
+        let axis = Vec3{
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        };
+        let a = Quaternion::new(&axis, 0.0, 1.0);
+        let b = Quaternion::new(&axis, PI / 2.0, 3.0);
+        let m = a.slerp(&b, 0.5);
+        let expected = Quaternion::new(&axis, PI / 4.0, 2.0);
+        assert!((m.vec4 - expected.vec4).norm() < 1e-5);
+        assert!((a.slerp(&b, 1.0).vec4 - b.vec4).norm() < 1e-5);
+        // -q is the same rotation as q, so the shorter arc is still taken
+        let c = Quaternion{
+            vec4: Vec4{
+                w: -b.vec4.w,
+                x: -b.vec4.x,
+                y: -b.vec4.y,
+                z: -b.vec4.z,
+            },
+        };
+        let v = Vec3{
+            x: 1.0,
+            y: 0.0,
+            z: 0.0,
+        };
+        assert!((a.slerp(&c, 0.5).rotate(&v) - m.rotate(&v)).norm() < 1e-4);
+    }
+
+    #[test]
+    pub fn test_nlerp(){
+        let axis = Vec3{
+            x: 0.0,
+            y: 0.0,
+            z: 1.0,
+        };
+        let a = Quaternion::new(&axis, 0.0, 1.0);
+        let b = Quaternion::new(&axis, PI / 2.0, 3.0);
+        // halfway between two rotations nlerp and slerp agree
+        assert!((a.nlerp(&b, 0.5).vec4 - a.slerp(&b, 0.5).vec4).norm() < 1e-5);
+        assert!((a.nlerp(&b, 1.0).vec4 - b.vec4).norm() < 1e-5);
+    }
+
+    #[test]
+    pub fn test_euler(){
+        let v = Vec3{
+            x: 1.0,
+            y: 0.0,
+            z: 0.0,
+        };
+        // a quarter yaw turns the forward to the right of the screen, a quarter pitch raises it
+        assert!((Quaternion::from_euler(PI / 2.0, 0.0, 0.0).rotate(&v) - Vec3{
+            x: 0.0,
+            y: 0.0,
+            z: -1.0,
+        }).norm() < 1e-5);
+        assert!((Quaternion::from_euler(0.0, PI / 2.0, 0.0).rotate(&v) - Vec3{
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        }).norm() < 1e-5);
+        for (yaw, pitch, roll) in [(0.3, -0.4, 1.2), (-2.5, 1.0, -3.0), (3.0, 0.0, 0.1)]{
+            let (y, p, r) = Quaternion::from_euler(yaw, pitch, roll).to_euler();
+            assert!(f32::abs(y - yaw) < 1e-4 && f32::abs(p - pitch) < 1e-4 && f32::abs(r - roll) < 1e-4);
+        }
+        // looking straight up the yaw is folded into the roll
+        let q = Quaternion::from_euler(0.7, PI / 2.0, 0.2);
+        let (y, p, r) = q.to_euler();
+        assert!(y == 0.0);
+        let u = Vec3{
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        };
+        assert!((Quaternion::from_euler(y, p, r).rotate(&u) - q.rotate(&u)).norm() < 1e-3);
+    }
+
+    #[test]
+    pub fn test_look_at(){
+        let forward = Vec3{
+            x: 1.0,
+            y: 2.0,
+            z: -3.0,
+        };
+        let up = Vec3{
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        };
+        let q = Quaternion::look_at(&forward, &up);
+        assert!((q.rotate(&Vec3{
+            x: 1.0,
+            y: 0.0,
+            z: 0.0,
+        }) - forward.normalize()).norm() < 1e-5);
+        let u = q.rotate(&up);
+        assert!(f32::abs(u * forward) < 1e-5 && u.y > 0.0);
+        // up parallel to the forward still gives a rotation towards the forward
+        let q = Quaternion::look_at(&up, &up);
+        assert!((q.rotate(&Vec3{
+            x: 1.0,
+            y: 0.0,
+            z: 0.0,
+        }) - up).norm() < 1e-5);
+    }
+
+    #[test]
+    pub fn test_axis_angle_and_matrix(){
+        let axis = Vec3{
+            x: 1.0,
+            y: -1.0,
+            z: 2.0,
+        }.normalize();
+        let q = Quaternion::new(&axis, 2.0, 1.5);
+        let (a, angle) = q.to_axis_angle();
+        assert!((a - axis).norm() < 1e-5 && f32::abs(angle - 2.0) < 1e-5);
+        let (_, angle) = Quaternion::new(&axis, 0.0, 1.0).to_axis_angle();
+        assert!(angle == 0.0);
+        let v = Vec3{
+            x: 0.5,
+            y: 3.0,
+            z: -1.0,
+        };
+        let m = q.to_rotation_matrix();
+        assert!((m * v - q.normalize().rotate(&v)).norm() < 1e-5);
+        // every branch of the conversion back is taken by one of these
+        for angle in [0.5, 3.0, -3.0]{
+            for axis in [Vec3{ x: 1.0, y: 0.0, z: 0.0 }, Vec3{ x: 0.0, y: 1.0, z: 0.0 }, Vec3{ x: 0.0, y: 0.0, z: 1.0 }]{
+                let q = Quaternion::new(&axis, angle, 1.0);
+                let p = Quaternion::from_rotation_matrix(&q.to_rotation_matrix());
+                assert!((p.rotate(&v) - q.rotate(&v)).norm() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_matrix44(){
+        let q = Quaternion::new(&Vec3{
+            x: 1.0,
+            y: 2.0,
+            z: 0.5,
+        }.normalize(), 1.2, 1.0);
+        let position = Vec3{
+            x: 3.0,
+            y: -1.0,
+            z: 2.0,
+        };
+        let scale = Vec3{
+            x: 2.0,
+            y: 0.5,
+            z: -1.0,
+        };
+        let m = Matrix44::trs(&position, &q, &scale);
+        let composed = Matrix44::translation(&position) * Matrix44::rotation(&q) * Matrix44::scaling(&scale);
+        let p = Vec3{
+            x: 0.3,
+            y: 4.0,
+            z: -2.0,
+        };
+        let expected = position + q.rotate(&p.scale_by(&scale));
+        assert!((m.transform_point(&p) - expected).norm() < 1e-5);
+        assert!((composed.transform_point(&p) - expected).norm() < 1e-5);
+        assert!((m.transform_vector(&p) - q.rotate(&p.scale_by(&scale))).norm() < 1e-5);
+        let inverse = m.inverse().unwrap();
+        assert!(((inverse * m).transform_point(&p) - p).norm() < 1e-4);
+        assert!((inverse.transform_point(&expected) - p).norm() < 1e-4);
+        assert!(Matrix44::scaling(&Vec3{
+            x: 1.0,
+            y: 0.0,
+            z: 1.0,
+        }).inverse().is_none());
+        // a projection that divides by the distance along +x
+        let mut projection = Matrix44::identity();
+        projection.v1.w = 1.0;
+        projection.v4.w = 0.0;
+        let projected = projection.transform_point(&Vec3{
+            x: 4.0,
+            y: 2.0,
+            z: -1.0,
+        });
+        assert!((projected - Vec3{
+            x: 1.0,
+            y: 0.5,
+            z: -0.25,
+        }).norm() < 1e-6);
+    }
+
     #[test]
     pub fn test_rotator(){
         {
@@ -533,6 +946,7 @@ impl std::ops::Mul<Quaternion> for Quaternion{
 
 #[derive(Debug,Clone,Copy)]
 #[repr(C)]
+#[cfg_attr(feature = "export", derive(Serialize, Deserialize))]
 pub struct Matrix33{
     pub v1:Vec3,
     pub v2:Vec3,
@@ -647,6 +1061,188 @@ impl Matrix33 {
     }
 }
 
+// a homogeneous transform, v1..v4 are the columns like Matrix33 and the w component of each column is the bottom row
+#[derive(Debug,Clone,Copy,PartialEq)]
+#[repr(C)]
+#[cfg_attr(feature = "export", derive(Serialize, Deserialize))]
+pub struct Matrix44{
+    pub v1:Vec4,
+    pub v2:Vec4,
+    pub v3:Vec4,
+    pub v4:Vec4
+}
+
+impl std::ops::Mul<Vec4> for Matrix44 {
+    type Output = Vec4;
+
+    fn mul(self, rhs: Vec4) -> Self::Output{
+        return Vec4{
+            w:self.v1.w * rhs.x + self.v2.w * rhs.y + self.v3.w * rhs.z + self.v4.w * rhs.w,
+            x:self.v1.x * rhs.x + self.v2.x * rhs.y + self.v3.x * rhs.z + self.v4.x * rhs.w,
+            y:self.v1.y * rhs.x + self.v2.y * rhs.y + self.v3.y * rhs.z + self.v4.y * rhs.w,
+            z:self.v1.z * rhs.x + self.v2.z * rhs.y + self.v3.z * rhs.z + self.v4.z * rhs.w
+        }
+    }
+}
+
+// a * b applies b first
+impl std::ops::Mul<Matrix44> for Matrix44 {
+    type Output = Matrix44;
+
+    fn mul(self, rhs: Matrix44) -> Self::Output {
+        return Matrix44 {
+            v1:self * rhs.v1,
+            v2:self * rhs.v2,
+            v3:self * rhs.v3,
+            v4:self * rhs.v4,
+        }
+    }
+}
+
+impl std::default::Default for Matrix44{
+    fn default() -> Self {
+        return Matrix44::identity();
+    }
+}
+
+impl Matrix44 {
+    pub fn identity() -> Matrix44{
+        return Matrix44::from_matrix33(&Matrix33{
+            v1: Vec3{ x: 1.0, y: 0.0, z: 0.0 },
+            v2: Vec3{ x: 0.0, y: 1.0, z: 0.0 },
+            v3: Vec3{ x: 0.0, y: 0.0, z: 1.0 },
+        }, &Vec3::default());
+    }
+
+    // m is applied first and then translation
+    pub fn from_matrix33(m: &Matrix33, translation: &Vec3) -> Matrix44{
+        return Matrix44{
+            v1: Vec4{ w: 0.0, x: m.v1.x, y: m.v1.y, z: m.v1.z },
+            v2: Vec4{ w: 0.0, x: m.v2.x, y: m.v2.y, z: m.v2.z },
+            v3: Vec4{ w: 0.0, x: m.v3.x, y: m.v3.y, z: m.v3.z },
+            v4: Vec4{ w: 1.0, x: translation.x, y: translation.y, z: translation.z },
+        };
+    }
+
+    pub fn translation(v: &Vec3) -> Matrix44{
+        let mut m = Matrix44::identity();
+        m.v4 = Vec4{ w: 1.0, x: v.x, y: v.y, z: v.z };
+        return m;
+    }
+
+    pub fn scaling(v: &Vec3) -> Matrix44{
+        return Matrix44::from_matrix33(&Matrix33{
+            v1: Vec3{ x: v.x, y: 0.0, z: 0.0 },
+            v2: Vec3{ x: 0.0, y: v.y, z: 0.0 },
+            v3: Vec3{ x: 0.0, y: 0.0, z: v.z },
+        }, &Vec3::default());
+    }
+
+    // the same as q.rotate, so the norm of q scales uniformly
+    pub fn rotation(q: &Quaternion) -> Matrix44{
+        return Matrix44::from_matrix33(&Matrix33{
+            v1: q.rotate(&Vec3{ x: 1.0, y: 0.0, z: 0.0 }),
+            v2: q.rotate(&Vec3{ x: 0.0, y: 1.0, z: 0.0 }),
+            v3: q.rotate(&Vec3{ x: 0.0, y: 0.0, z: 1.0 }),
+        }, &Vec3::default());
+    }
+
+    // scale, then rotate, then translate, which is how the engine places a node
+    pub fn trs(position: &Vec3, direction: &Quaternion, scale: &Vec3) -> Matrix44{
+        let r = Matrix44::rotation(direction).upper();
+        return Matrix44::from_matrix33(&Matrix33{
+            v1: r.v1 * scale.x,
+            v2: r.v2 * scale.y,
+            v3: r.v3 * scale.z,
+        }, position);
+    }
+
+    // the linear part, without translation and projection
+    pub fn upper(&self) -> Matrix33{
+        return Matrix33{
+            v1: Vec3{ x: self.v1.x, y: self.v1.y, z: self.v1.z },
+            v2: Vec3{ x: self.v2.x, y: self.v2.y, z: self.v2.z },
+            v3: Vec3{ x: self.v3.x, y: self.v3.y, z: self.v3.z },
+        };
+    }
+
+    pub fn transpose(&self) -> Matrix44{
+        return Matrix44{
+            v1: Vec4{ x: self.v1.x, y: self.v2.x, z: self.v3.x, w: self.v4.x },
+            v2: Vec4{ x: self.v1.y, y: self.v2.y, z: self.v3.y, w: self.v4.y },
+            v3: Vec4{ x: self.v1.z, y: self.v2.z, z: self.v3.z, w: self.v4.z },
+            v4: Vec4{ x: self.v1.w, y: self.v2.w, z: self.v3.w, w: self.v4.w },
+        };
+    }
+
+    fn rows(&self) -> [[f32; 4]; 4]{
+        let t = self.transpose();
+        return [t.v1, t.v2, t.v3, t.v4].map(|r| [r.x, r.y, r.z, r.w]);
+    }
+
+    fn from_rows(rows: &[[f32; 4]; 4]) -> Matrix44{
+        let [r1, r2, r3, r4] = rows.map(|r| Vec4{ x: r[0], y: r[1], z: r[2], w: r[3] });
+        return Matrix44{
+            v1: r1,
+            v2: r2,
+            v3: r3,
+            v4: r4,
+        }.transpose();
+    }
+
+    // gauss-jordan elimination with partial pivoting, None when the matrix is singular
+    pub fn inverse(&self) -> Option<Matrix44>{
+        let mut a = self.rows();
+        let mut inv = Matrix44::identity().rows();
+        for c in 0..4{
+            let pivot = (c..4).max_by(|i, j| f32::abs(a[*i][c]).total_cmp(&f32::abs(a[*j][c]))).unwrap();
+            if a[pivot][c] == 0.0{
+                return None;
+            }
+            a.swap(c, pivot);
+            inv.swap(c, pivot);
+            let d = a[c][c];
+            for k in 0..4{
+                a[c][k] /= d;
+                inv[c][k] /= d;
+            }
+            for r in 0..4{
+                if r != c{
+                    let f = a[r][c];
+                    for k in 0..4{
+                        a[r][k] -= f * a[c][k];
+                        inv[r][k] -= f * inv[c][k];
+                    }
+                }
+            }
+        }
+        return Some(Matrix44::from_rows(&inv));
+    }
+
+    // divides by w, so projections work as well; a point that projects to w == 0 is at infinity and comes out as inf or NaN
+    pub fn transform_point(&self, p: &Vec3) -> Vec3{
+        let r = *self * Vec4{ w: 1.0, x: p.x, y: p.y, z: p.z };
+        return Vec3{
+            x: r.x / r.w,
+            y: r.y / r.w,
+            z: r.z / r.w,
+        };
+    }
+
+    // directions and offsets ignore the translation
+    pub fn transform_vector(&self, v: &Vec3) -> Vec3{
+        return self.upper() * *v;
+    }
+
+    // normals go through the inverse transpose so they stay perpendicular under non-uniform scale, the result is normalized
+    pub fn transform_normal(&self, n: &Vec3) -> Vec3{
+        return match self.upper().inverse() {
+            Some(m) => { (m.transpose() * *n).normalize() }
+            None => { self.transform_vector(n).normalize() }
+        };
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct AABB3D{
     pub(crate) a: Vec3,
@@ -698,5 +1294,78 @@ impl AABB3D{
             },
         }
     }
+    pub fn min(&self) -> Vec3{
+        return self.a;
+    }
+
+    pub fn max(&self) -> Vec3{
+        return self.b;
+    }
+
+    pub fn contains(&self, p: &Vec3) -> bool{
+        return self.a.x <= p.x && p.x <= self.b.x && self.a.y <= p.y && p.y <= self.b.y && self.a.z <= p.z && p.z <= self.b.z;
+    }
+
+    pub fn intersects(&self, other: &AABB3D) -> bool{
+        return self.a.x <= other.b.x && other.a.x <= self.b.x && self.a.y <= other.b.y && other.a.y <= self.b.y && self.a.z <= other.b.z && other.a.z <= self.b.z;
+    }
+
+    // zero when the point is inside
+    pub fn distance(&self, p: &Vec3) -> f32{
+        let d = Vec3{
+            x: f32::max(0.0, f32::max(self.a.x - p.x, p.x - self.b.x)),
+            y: f32::max(0.0, f32::max(self.a.y - p.y, p.y - self.b.y)),
+            z: f32::max(0.0, f32::max(self.a.z - p.z, p.z - self.b.z)),
+        };
+        return d.norm();
+    }
+}
+
+// the volume seen by a camera at position looking along direction, +x is forward, +y is up, and angle_of_view is (horizontal, vertical)
+// there is no far plane since cameras trace rays without a length limit
+#[derive(Debug, Copy, Clone)]
+pub struct Frustum{
+    // outward normals and offsets, a point p is outside of a plane when normal * p > offset
+    planes: [(Vec3, f32); 5],
 }
 
+impl Frustum{
+    pub fn new(position: &Vec3, direction: &Quaternion, angle_of_view: (f32, f32)) -> Self{
+        let h = f32::tan(angle_of_view.0 * 0.5);
+        let v = f32::tan(angle_of_view.1 * 0.5);
+        let normals = [
+            Vec3{ x: -1.0, y: 0.0, z: 0.0 },
+            Vec3{ x: -v, y: 1.0, z: 0.0 },
+            Vec3{ x: -v, y: -1.0, z: 0.0 },
+            Vec3{ x: -h, y: 0.0, z: 1.0 },
+            Vec3{ x: -h, y: 0.0, z: -1.0 },
+        ];
+        let mut planes = [(Vec3::default(), 0.0); 5];
+        for (plane, n) in planes.iter_mut().zip(normals.iter()){
+            let n = direction.rotate(n).normalize();
+            *plane = (n, n * *position);
+        }
+        return Frustum{
+            planes: planes,
+        };
+    }
+
+    pub fn contains(&self, p: &Vec3) -> bool{
+        return self.planes.iter().all(|(n, d)| *n * *p <= *d);
+    }
+
+    // conservative, a box near an edge of the frustum may pass without being visible
+    pub fn intersects(&self, aabb: &AABB3D) -> bool{
+        for (n, d) in &self.planes{
+            let nearest = Vec3{
+                x: if n.x > 0.0 { aabb.a.x } else { aabb.b.x },
+                y: if n.y > 0.0 { aabb.a.y } else { aabb.b.y },
+                z: if n.z > 0.0 { aabb.a.z } else { aabb.b.z },
+            };
+            if *n * nearest > *d{
+                return false;
+            }
+        }
+        return true;
+    }
+}
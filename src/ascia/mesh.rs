@@ -0,0 +1,336 @@
+use std::collections::HashMap;
+use crate::ascia::core::{AsciaEnvironment, CoordinateType, Polygon};
+use crate::ascia::math::{AABB3D, Matrix33, Vec2, Vec3};
+
+// helpers over plain polygon lists, every polygon keeps its own material
+
+pub fn merge<E: AsciaEnvironment, CO: CoordinateType>(meshes: impl IntoIterator<Item = Vec<Polygon<E, CO>>>) -> Vec<Polygon<E, CO>>{
+    return meshes.into_iter().flatten().collect();
+}
+
+// moves vertices closer than tolerance onto the first of them and drops the polygons that collapse on the way
+// a tolerance of zero or less only welds vertices at exactly the same position
+pub fn weld<E: AsciaEnvironment, CO: CoordinateType>(polygons: &mut Vec<Polygon<E, CO>>, tolerance: f32){
+    if tolerance.is_nan() || tolerance <= 0.0{
+        let mut welded: HashMap<(u32, u32, u32), Vec3> = HashMap::new();
+        for p in polygons.iter_mut(){
+            for v in [&mut p.poses.v1, &mut p.poses.v2, &mut p.poses.v3]{
+                *v = *welded.entry(vertex_key(v)).or_insert(*v);
+            }
+        }
+    }
+    else{
+        // cells of far away vertices under a tiny tolerance saturate, the distance check still keeps them apart
+        let cell = |v: &Vec3| ((v.x / tolerance).floor() as i64, (v.y / tolerance).floor() as i64, (v.z / tolerance).floor() as i64);
+        let mut welded: HashMap<(i64, i64, i64), Vec<Vec3>> = HashMap::new();
+        let mut snap = |v: &mut Vec3| {
+            let (x, y, z) = cell(v);
+            // anything within tolerance is at most one cell away
+            for dx in -1..=1{
+                for dy in -1..=1{
+                    for dz in -1..=1{
+                        if let Some(w) = welded.get(&(x.saturating_add(dx), y.saturating_add(dy), z.saturating_add(dz))).and_then(|c| c.iter().find(|w| (**w - *v).norm() <= tolerance)){
+                            *v = *w;
+                            return;
+                        }
+                    }
+                }
+            }
+            welded.entry((x, y, z)).or_default().push(*v);
+        };
+        for p in polygons.iter_mut(){
+            snap(&mut p.poses.v1);
+            snap(&mut p.poses.v2);
+            snap(&mut p.poses.v3);
+        }
+    }
+    polygons.retain(|p| p.poses.v1 != p.poses.v2 && p.poses.v2 != p.poses.v3 && p.poses.v3 != p.poses.v1);
+}
+
+// turns every polygon around, its normals included
+pub fn flip<E: AsciaEnvironment, CO: CoordinateType>(polygons: &mut [Polygon<E, CO>]){
+    for p in polygons{
+        std::mem::swap(&mut p.poses.v2, &mut p.poses.v3);
+        if let Some(n) = &mut p.normals{
+            *n = Matrix33{
+                v1: n.v1 * -1.0,
+                v2: n.v3 * -1.0,
+                v3: n.v2 * -1.0,
+            };
+        }
+        if let Some(uv) = &mut p.uvs{
+            uv.swap(1, 2);
+        }
+    }
+}
+
+// splits every triangle into four and smooths the vertices with Loop's weights
+// vertices are shared by their exact position so the mesh should be welded first, edges without exactly two faces are kept as creases
+// uvs and normals are interpolated across each original triangle
+pub fn loop_subdivide<E: AsciaEnvironment, CO: CoordinateType>(polygons: &[Polygon<E, CO>], iterations: usize) -> Vec<Polygon<E, CO>>{
+    let mut polygons = polygons.to_vec();
+    for _ in 0..iterations{
+        polygons = loop_subdivide_once(&polygons);
+    }
+    return polygons;
+}
+
+fn loop_subdivide_once<E: AsciaEnvironment, CO: CoordinateType>(polygons: &[Polygon<E, CO>]) -> Vec<Polygon<E, CO>>{
+    let mut indices: HashMap<(u32, u32, u32), usize> = HashMap::new();
+    let mut positions = vec![];
+    let mut index_of = |v: &Vec3| *indices.entry(vertex_key(v)).or_insert_with(|| {
+        positions.push(*v);
+        positions.len() - 1
+    });
+    let faces: Vec<[usize; 3]> = polygons.iter().map(|p| [index_of(&p.poses.v1), index_of(&p.poses.v2), index_of(&p.poses.v3)]).collect();
+    let edge = |a: usize, b: usize| (a.min(b), a.max(b));
+
+    // the vertices opposite of each edge, one per face using it
+    let mut opposite: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for f in faces.iter().filter(|f| f[0] != f[1] && f[1] != f[2] && f[2] != f[0]){
+        for k in 0..3{
+            opposite.entry(edge(f[k], f[(k + 1) % 3])).or_default().push(f[(k + 2) % 3]);
+        }
+    }
+    let mut neighbors = vec![vec![]; positions.len()];
+    let mut crease_neighbors = vec![vec![]; positions.len()];
+    for ((a, b), o) in &opposite{
+        neighbors[*a].push(*b);
+        neighbors[*b].push(*a);
+        if o.len() != 2{
+            crease_neighbors[*a].push(*b);
+            crease_neighbors[*b].push(*a);
+        }
+    }
+
+    let moved: Vec<Vec3> = positions.iter().enumerate().map(|(i, v)| {
+        let sum = |vs: &[usize]| vs.iter().fold(Vec3::default(), |s, j| s + positions[*j]);
+        match crease_neighbors[i].len() {
+            0 => {
+                let n = neighbors[i].len();
+                if n == 0{
+                    return *v;
+                }
+                let beta = if n == 3 { 3.0 / 16.0 } else { 3.0 / (8.0 * n as f32) };
+                *v * (1.0 - n as f32 * beta) + sum(&neighbors[i]) * beta
+            }
+            2 => { *v * 0.75 + sum(&crease_neighbors[i]) * 0.125 }
+            // where creases meet or end the vertex is a corner and stays
+            _ => { *v }
+        }
+    }).collect();
+    let edge_point = |a: usize, b: usize| {
+        return match opposite.get(&edge(a, b)).map(|o| o.as_slice()) {
+            Some([c, d]) => { (positions[a] + positions[b]) * 0.375 + (positions[*c] + positions[*d]) * 0.125 }
+            _ => { (positions[a] + positions[b]) * 0.5 }
+        };
+    };
+
+    let mut subdivided = Vec::with_capacity(polygons.len() * 4);
+    for (p, f) in polygons.iter().zip(faces.iter()){
+        if f[0] == f[1] || f[1] == f[2] || f[2] == f[0]{
+            continue;
+        }
+        // each new vertex with its place on the original triangle as in position_on_polygon
+        let corners = [
+            (moved[f[0]], Vec2{ x: 0.0, y: 0.0 }),
+            (moved[f[1]], Vec2{ x: 1.0, y: 0.0 }),
+            (moved[f[2]], Vec2{ x: 0.0, y: 1.0 }),
+        ];
+        let middles = [
+            (edge_point(f[0], f[1]), Vec2{ x: 0.5, y: 0.0 }),
+            (edge_point(f[1], f[2]), Vec2{ x: 0.5, y: 0.5 }),
+            (edge_point(f[2], f[0]), Vec2{ x: 0.0, y: 0.5 }),
+        ];
+        for [a, b, c] in [
+            [&corners[0], &middles[0], &middles[2]],
+            [&middles[0], &corners[1], &middles[1]],
+            [&middles[2], &middles[1], &corners[2]],
+            [&middles[0], &middles[1], &middles[2]],
+        ]{
+            subdivided.push(Polygon{
+                poses: Matrix33{
+                    v1: a.0,
+                    v2: b.0,
+                    v3: c.0,
+                },
+                normals: p.normals.map(|_| Matrix33{
+                    v1: p.normal_at(&a.1),
+                    v2: p.normal_at(&b.1),
+                    v3: p.normal_at(&c.1),
+                }),
+                uvs: p.uvs.map(|_| [a.1, b.1, c.1].map(|t| p.uv_at(&t).unwrap())),
+                material: p.material.clone(),
+                _ph: Default::default(),
+            });
+        }
+    }
+    return subdivided;
+}
+
+// +0.0 folds -0.0 into 0.0 so that both are the same vertex
+fn vertex_key(v: &Vec3) -> (u32, u32, u32){
+    return ((v.x + 0.0).to_bits(), (v.y + 0.0).to_bits(), (v.z + 0.0).to_bits());
+}
+
+pub fn bounds<E: AsciaEnvironment, CO: CoordinateType>(polygons: &[Polygon<E, CO>]) -> Option<AABB3D>{
+    return polygons.iter().map(|p| p.aabb()).reduce(|a, b| AABB3D::concat(&a, &b));
+}
+
+// moves the center of the bounding box to the origin and returns where it was
+pub fn recenter<E: AsciaEnvironment, CO: CoordinateType>(polygons: &mut [Polygon<E, CO>]) -> Vec3{
+    let center = match bounds(polygons) {
+        Some(b) => { (b.min() + b.max()) * 0.5 }
+        None => { return Vec3::default(); }
+    };
+    for p in polygons.iter_mut(){
+        p.poses = Matrix33{
+            v1: p.poses.v1 - center,
+            v2: p.poses.v2 - center,
+            v3: p.poses.v3 - center,
+        };
+    }
+    return center;
+}
+
+// scales uniformly around the center of the bounding box until its longest side is size and returns the factor
+pub fn scale_to_fit<E: AsciaEnvironment, CO: CoordinateType>(polygons: &mut [Polygon<E, CO>], size: f32) -> f32{
+    let b = match bounds(polygons) {
+        Some(b) => { b }
+        None => { return 1.0; }
+    };
+    let extent = b.max() - b.min();
+    let longest = f32::max(extent.x, f32::max(extent.y, extent.z));
+    if longest <= 0.0{
+        return 1.0;
+    }
+    let factor = size / longest;
+    let center = (b.min() + b.max()) * 0.5;
+    for p in polygons.iter_mut(){
+        p.poses = Matrix33{
+            v1: center + (p.poses.v1 - center) * factor,
+            v2: center + (p.poses.v2 - center) * factor,
+            v3: center + (p.poses.v3 - center) * factor,
+        };
+    }
+    return factor;
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::ascia::color::ColorRGBf32;
+    use crate::ascia::core::{FlatMaterial, Local, Polygon, PresetAsciaEnvironment, PresetPolygonMaterial};
+    use crate::ascia::math::{Vec2, Vec3};
+    use crate::ascia::mesh::{bounds, flip, loop_subdivide, merge, recenter, scale_to_fit, weld};
+    use crate::ascia::primitives::PrimitiveGenerator;
+
+    type Polygons = Vec<Polygon<PresetAsciaEnvironment, Local>>;
+
+    fn volume(polygons: &Polygons) -> f32{
+        return polygons.iter().map(|p| p.poses.v1 * (p.poses.v2 ^ p.poses.v3) / 6.0).sum();
+    }
+
+    fn red() -> PresetPolygonMaterial{
+        return PresetPolygonMaterial::FlatMaterial(FlatMaterial{
+            color: ColorRGBf32{
+                r: 1.0,
+                g: 0.0,
+                b: 0.0,
+            },
+            priority: 0,
+        });
+    }
+
+    #[test]
+    fn test_merge_and_weld(){
+        let cube: Polygons = PrimitiveGenerator::cube(2.0, PresetPolygonMaterial::default());
+        let mut square: Polygons = PrimitiveGenerator::square(1.0, red());
+        square[1].poses.v2.y += 1e-4;
+        let mut merged = merge(vec![cube, square]);
+        assert_eq!(merged.len(), 14);
+        assert!(matches!(merged[13].material, PresetPolygonMaterial::FlatMaterial(_)));
+
+        // the nudged corner goes back onto the one it was split from
+        weld(&mut merged, 1e-3);
+        assert_eq!(merged.len(), 14);
+        assert_eq!(merged[13].poses.v2, merged[12].poses.v3);
+
+        // a sliver thinner than the tolerance disappears
+        let mut sliver: Polygons = vec![Polygon::new(&Vec3{ x: 0.0, y: 0.0, z: 0.0 }, &Vec3{ x: 1.0, y: 0.0, z: 0.0 }, &Vec3{ x: 0.0, y: 0.0, z: 1e-4 })];
+        weld(&mut sliver, 1e-3);
+        assert!(sliver.is_empty());
+
+        // without a tolerance only exact copies are joined, -0.0 included, and far away vertices do not overflow the cells
+        let far = Vec3{ x: 3e38, y: -3e38, z: 1.0 };
+        for tolerance in [0.0, -1.0, 1e-20]{
+            let mut pair: Polygons = vec![
+                Polygon::new(&Vec3{ x: 0.0, y: 0.0, z: 0.0 }, &far, &Vec3{ x: 0.0, y: 1.0, z: 0.0 }),
+                Polygon::new(&Vec3{ x: -0.0, y: 0.0, z: 0.0 }, &Vec3{ x: 1e-10, y: 0.0, z: 0.0 }, &far),
+            ];
+            weld(&mut pair, tolerance);
+            assert_eq!(pair.len(), 2);
+            assert_eq!(pair[1].poses.v1.x.to_bits(), 0.0f32.to_bits());
+            assert_eq!(pair[1].poses.v2.x, 1e-10);
+        }
+    }
+
+    #[test]
+    fn test_flip(){
+        let mut sphere: Polygons = PrimitiveGenerator::uv_sphere(1.0, 8, 4, PresetPolygonMaterial::default());
+        let before = volume(&sphere);
+        let uv = sphere[0].uvs.unwrap();
+        flip(&mut sphere);
+        assert!(f32::abs(volume(&sphere) + before) < 1e-5);
+        assert_eq!(sphere[0].uvs.unwrap()[1].x, uv[2].x);
+        for p in &sphere{
+            assert!(p.normal() * p.normal_at(&Vec2{ x: 0.3, y: 0.3 }) > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_loop_subdivide(){
+        let cube: Polygons = PrimitiveGenerator::cube(2.0, red());
+        let smooth = loop_subdivide(&cube, 2);
+        assert_eq!(smooth.len(), 12 * 16);
+        assert!(matches!(smooth[100].material, PresetPolygonMaterial::FlatMaterial(_)));
+        // the cube rounds off inside itself but keeps its winding
+        let v = volume(&smooth);
+        assert!(v > 2.0 && v < 8.0);
+        let b = bounds(&smooth).unwrap();
+        assert!(b.max().x < 1.0 && b.max().x > 0.5);
+        assert!((b.max() + b.min()).norm() < 1e-5);
+
+        // the border of an open grid is a crease, so a flat grid stays flat and keeps its outline
+        let grid: Polygons = PrimitiveGenerator::plane_grid(2.0, 2.0, 2, 2, PresetPolygonMaterial::default());
+        let subdivided = loop_subdivide(&grid, 1);
+        assert_eq!(subdivided.len(), 32);
+        for p in &subdivided{
+            assert_eq!(p.poses.v1.y, 0.0);
+            assert!(p.normal().y > 0.99);
+            assert!(p.uvs.is_some() && p.normals.is_some());
+        }
+        let b = bounds(&subdivided).unwrap();
+        assert_eq!((b.min().x, b.max().z), (-1.0, 1.0));
+        // uvs follow their positions across the flat grid
+        let p = &subdivided[5];
+        let uv = p.uvs.unwrap()[1];
+        assert!(f32::abs(uv.x - (p.poses.v2.x + 1.0) * 0.5) < 1e-5);
+    }
+
+    #[test]
+    fn test_recenter_and_scale_to_fit(){
+        let mut cube: Polygons = PrimitiveGenerator::cube(2.0, PresetPolygonMaterial::default());
+        for p in cube.iter_mut(){
+            p.poses.v1 = p.poses.v1 + Vec3{ x: 3.0, y: 0.0, z: -1.0 };
+            p.poses.v2 = p.poses.v2 + Vec3{ x: 3.0, y: 0.0, z: -1.0 };
+            p.poses.v3 = p.poses.v3 + Vec3{ x: 3.0, y: 0.0, z: -1.0 };
+        }
+        assert_eq!(recenter(&mut cube), Vec3{ x: 3.0, y: 0.0, z: -1.0 });
+        let b = bounds(&cube).unwrap();
+        assert_eq!((b.min(), b.max()), (Vec3{ x: -1.0, y: -1.0, z: -1.0 }, Vec3{ x: 1.0, y: 1.0, z: 1.0 }));
+
+        assert_eq!(scale_to_fit(&mut cube, 10.0), 5.0);
+        assert_eq!(bounds(&cube).unwrap().max(), Vec3{ x: 5.0, y: 5.0, z: 5.0 });
+        assert_eq!(recenter::<PresetAsciaEnvironment, Local>(&mut []), Vec3::default());
+    }
+}
@@ -1,5 +1,9 @@
+use std::f32::consts::PI;
 use crate::ascia::core::{AsciaEnvironment, Local, Polygon};
-use crate::ascia::math::{Matrix33, Vec3};
+use crate::ascia::math::{Matrix33, Vec2, Vec3};
+
+// the position, normal and uv of a generated vertex
+type Vertex = (Vec3, Vec3, Vec2);
 
 pub struct PrimitiveGenerator{
 
@@ -15,6 +19,8 @@ impl PrimitiveGenerator {
                 v2:Vec3{ x:0.0, y:-p, z:-p},
                 v3:Vec3{ x:0.0, y:p, z:p}
             },
+            normals: None,
+            uvs: None,
             material:material.clone(),
             _ph: Default::default(),
         });
@@ -24,6 +30,8 @@ impl PrimitiveGenerator {
                 v2:Vec3{ x:0.0, y:p, z:p},
                 v3:Vec3{ x:0.0, y:-p, z:-p}
             },
+            normals: None,
+            uvs: None,
             material:material.clone(),
             _ph: Default::default(),
         });
@@ -40,6 +48,8 @@ impl PrimitiveGenerator {
                 v2: Vec3 { x: -p, y: -p, z: -p },
                 v3: Vec3 { x: -p, y: p, z: p }
             },
+            normals: None,
+            uvs: None,
             material:material.clone(),
             _ph: Default::default(),
         });
@@ -49,6 +59,8 @@ impl PrimitiveGenerator {
                 v2:Vec3{ x:-p, y:p, z:p},
                 v3:Vec3{ x:-p, y:-p, z:-p}
             },
+            normals: None,
+            uvs: None,
             material:material.clone(),
             _ph: Default::default(),
         });
@@ -59,6 +71,8 @@ impl PrimitiveGenerator {
                 v2:Vec3{ x:p, y:-p, z:p},
                 v3:Vec3{ x:p, y:p, z:-p},
             },
+            normals: None,
+            uvs: None,
             material:material.clone(),
             _ph: Default::default(),
         });
@@ -68,6 +82,8 @@ impl PrimitiveGenerator {
                 v1:Vec3{ x:p, y:p,z:-p},
                 v2:Vec3{ x:p, y:-p, z:p}
             },
+            normals: None,
+            uvs: None,
             material:material.clone(),
             _ph: Default::default(),
         });
@@ -79,6 +95,8 @@ impl PrimitiveGenerator {
                 v2:Vec3{ x:-p, y:-p, z:-p},
                 v3:Vec3{ x:p, y:-p, z:p}
             },
+            normals: None,
+            uvs: None,
             material:material.clone(),
             _ph: Default::default(),
         });
@@ -88,6 +106,8 @@ impl PrimitiveGenerator {
                 v2:Vec3{ x:p, y:-p, z:p},
                 v3:Vec3{ x:-p, y:-p, z:-p}
             },
+            normals: None,
+            uvs: None,
             material:material.clone(),
             _ph: Default::default(),
         });
@@ -98,6 +118,8 @@ impl PrimitiveGenerator {
                 v2:Vec3{ x:-p, y:p, z:p},
                 v3:Vec3{ x:p, y:p, z:-p}
             },
+            normals: None,
+            uvs: None,
             material:material.clone(),
             _ph: Default::default(),
         });
@@ -107,6 +129,8 @@ impl PrimitiveGenerator {
                 v2:Vec3{ x:p, y:p, z:-p},
                 v3:Vec3{ x:-p, y:p, z:p}
             },
+            normals: None,
+            uvs: None,
             material:material.clone(),
             _ph: Default::default(),
         });
@@ -118,6 +142,8 @@ impl PrimitiveGenerator {
                 v2:Vec3{ x:p, y:-p, z:-p},
                 v3:Vec3{ x:-p, y:p, z:-p}
             },
+            normals: None,
+            uvs: None,
             material:material.clone(),
             _ph: Default::default(),
         });
@@ -127,6 +153,8 @@ impl PrimitiveGenerator {
                 v2:Vec3{ x:-p, y:p, z:-p},
                 v3:Vec3{ x:p, y:-p, z:-p}
             },
+            normals: None,
+            uvs: None,
             material:material.clone(),
             _ph: Default::default(),
         });
@@ -137,6 +165,8 @@ impl PrimitiveGenerator {
                 v2:Vec3{ x:-p, y:-p, z:p},
                 v3:Vec3{ x:p, y:p, z:p}
             },
+            normals: None,
+            uvs: None,
             material:material.clone(),
             _ph: Default::default(),
         });
@@ -146,10 +176,276 @@ impl PrimitiveGenerator {
                 v2:Vec3{ x:p, y:p, z:p},
                 v3:Vec3{ x:-p, y:-p, z:p}
             },
+            normals: None,
+            uvs: None,
             material:material.clone(),
             _ph: Default::default(),
         });
 
         return polygons;
     }
-}
\ No newline at end of file
+
+    // the rest are centered at the origin with y as their axis, come with normals and uvs and wind outwards
+    // segments go around the axis and rings along it, counts that are too small to give a solid are raised
+
+    pub fn uv_sphere<E: AsciaEnvironment>(radius: f32, segments: usize, rings: usize, material: E::PolygonMaterials) -> Vec<Polygon<E, Local>>{
+        let segments = segments.max(3);
+        let rings = rings.max(2);
+        return Self::surface(segments, rings, &material, |i, j| {
+            let n = Self::sphere_normal(i, segments, j, rings);
+            (n * radius, n, Vec2{ x: i as f32 / segments as f32, y: j as f32 / rings as f32 })
+        });
+    }
+
+    // an icosahedron whose triangles are split in four subdivisions times, every vertex on the sphere
+    pub fn icosphere<E: AsciaEnvironment>(radius: f32, subdivisions: usize, material: E::PolygonMaterials) -> Vec<Polygon<E, Local>>{
+        let t = (1.0 + f32::sqrt(5.0)) * 0.5;
+        let v = |x: f32, y: f32, z: f32| Vec3{ x: x, y: y, z: z }.normalize();
+        let vertices = [
+            v(-1.0, t, 0.0), v(1.0, t, 0.0), v(-1.0, -t, 0.0), v(1.0, -t, 0.0),
+            v(0.0, -1.0, t), v(0.0, 1.0, t), v(0.0, -1.0, -t), v(0.0, 1.0, -t),
+            v(t, 0.0, -1.0), v(t, 0.0, 1.0), v(-t, 0.0, -1.0), v(-t, 0.0, 1.0),
+        ];
+        let faces = [
+            (0, 11, 5), (0, 5, 1), (0, 1, 7), (0, 7, 10), (0, 10, 11),
+            (1, 5, 9), (5, 11, 4), (11, 10, 2), (10, 7, 6), (7, 1, 8),
+            (3, 9, 4), (3, 4, 2), (3, 2, 6), (3, 6, 8), (3, 8, 9),
+            (4, 9, 5), (2, 4, 11), (6, 2, 10), (8, 6, 7), (9, 8, 1),
+        ];
+        let mut triangles: Vec<[Vec3; 3]> = faces.iter().map(|(a, b, c)| {
+            let (a, b, c) = (vertices[*a], vertices[*b], vertices[*c]);
+            if ((b - a) ^ (c - a)) * (a + b + c) < 0.0 { [a, c, b] } else { [a, b, c] }
+        }).collect();
+        for _ in 0..subdivisions{
+            triangles = triangles.iter().flat_map(|[a, b, c]| {
+                let (ab, bc, ca) = ((*a + *b).normalize(), (*b + *c).normalize(), (*c + *a).normalize());
+                [[*a, ab, ca], [ab, *b, bc], [ca, bc, *c], [ab, bc, ca]]
+            }).collect();
+        }
+
+        let uv = |n: &Vec3| Vec2{ x: f32::atan2(-n.z, n.x) / (2.0 * PI) + 0.5, y: f32::asin(n.y.clamp(-1.0, 1.0)) / PI + 0.5 };
+        return triangles.iter().map(|corners| {
+            let mut uvs = corners.map(|n| uv(&n));
+            // triangles across the seam would otherwise stretch over the whole texture
+            if uvs.iter().any(|t| t.x > 0.75) && uvs.iter().any(|t| t.x < 0.25){
+                for t in uvs.iter_mut().filter(|t| t.x < 0.5){
+                    t.x += 1.0;
+                }
+            }
+            Self::triangle(&(corners[0] * radius, corners[0], uvs[0]), &(corners[1] * radius, corners[1], uvs[1]), &(corners[2] * radius, corners[2], uvs[2]), &material)
+        }).collect();
+    }
+
+    pub fn cylinder<E: AsciaEnvironment>(radius: f32, height: f32, segments: usize, material: E::PolygonMaterials) -> Vec<Polygon<E, Local>>{
+        let segments = segments.max(3);
+        let h = height * 0.5;
+        let mut polygons = Self::surface(segments, 1, &material, |i, j| {
+            let n = Self::ring_normal(i, segments);
+            (n * radius + Vec3{ x: 0.0, y: if j == 0 { -h } else { h }, z: 0.0 }, n, Vec2{ x: i as f32 / segments as f32, y: j as f32 })
+        });
+        polygons.extend(Self::cap(radius, -h, segments, &material));
+        polygons.extend(Self::cap(radius, h, segments, &material));
+        return polygons;
+    }
+
+    // the base is at -height / 2 and the apex at height / 2
+    pub fn cone<E: AsciaEnvironment>(radius: f32, height: f32, segments: usize, material: E::PolygonMaterials) -> Vec<Polygon<E, Local>>{
+        let segments = segments.max(3);
+        let h = height * 0.5;
+        let mut polygons = Self::surface(segments, 1, &material, |i, j| {
+            let n = Self::ring_normal(i, segments);
+            let position = if j == 0 { n * radius - Vec3{ x: 0.0, y: h, z: 0.0 } } else { Vec3{ x: 0.0, y: h, z: 0.0 } };
+            // the slope leans the normal up by radius / height
+            (position, (n * height + Vec3{ x: 0.0, y: radius, z: 0.0 }).normalize(), Vec2{ x: i as f32 / segments as f32, y: j as f32 })
+        });
+        polygons.extend(Self::cap(radius, -h, segments, &material));
+        return polygons;
+    }
+
+    // major_radius from the axis to the center of the tube and minor_radius of the tube
+    pub fn torus<E: AsciaEnvironment>(major_radius: f32, minor_radius: f32, major_segments: usize, minor_segments: usize, material: E::PolygonMaterials) -> Vec<Polygon<E, Local>>{
+        let major_segments = major_segments.max(3);
+        let minor_segments = minor_segments.max(3);
+        return Self::surface(major_segments, minor_segments, &material, |i, j| {
+            let outwards = Self::ring_normal(i, major_segments);
+            let angle = 2.0 * PI * (j % minor_segments) as f32 / minor_segments as f32;
+            let n = outwards * f32::cos(angle) + Vec3{ x: 0.0, y: f32::sin(angle), z: 0.0 };
+            (outwards * major_radius + n * minor_radius, n, Vec2{ x: i as f32 / major_segments as f32, y: j as f32 / minor_segments as f32 })
+        });
+    }
+
+    // a cylinder of the given height with a hemisphere on each end, rings is per hemisphere
+    pub fn capsule<E: AsciaEnvironment>(radius: f32, height: f32, segments: usize, rings: usize, material: E::PolygonMaterials) -> Vec<Polygon<E, Local>>{
+        let segments = segments.max(3);
+        let rings = rings.max(1);
+        let h = height * 0.5;
+        let length = PI * radius + height;
+        return Self::surface(segments, 2 * rings + 1, &material, |i, j| {
+            // rows 0..=rings are the lower hemisphere and the rest the upper one
+            let (j, y) = if j <= rings { (j, -h) } else { (j - 1, h) };
+            let n = Self::sphere_normal(i, segments, j, 2 * rings);
+            let along = if y < 0.0 { (f32::asin(n.y) + PI * 0.5) * radius } else { (f32::asin(n.y) + PI * 0.5) * radius + height };
+            (n * radius + Vec3{ x: 0.0, y: y, z: 0.0 }, n, Vec2{ x: i as f32 / segments as f32, y: along / length })
+        });
+    }
+
+    // a grid on the xz plane facing +y, the ground rather than a wall like square
+    pub fn plane_grid<E: AsciaEnvironment>(width: f32, depth: f32, width_segments: usize, depth_segments: usize, material: E::PolygonMaterials) -> Vec<Polygon<E, Local>>{
+        let width_segments = width_segments.max(1);
+        let depth_segments = depth_segments.max(1);
+        return Self::surface(width_segments, depth_segments, &material, |i, j| {
+            let uv = Vec2{ x: i as f32 / width_segments as f32, y: j as f32 / depth_segments as f32 };
+            (Vec3{ x: (uv.x - 0.5) * width, y: 0.0, z: (0.5 - uv.y) * depth }, Vec3{ x: 0.0, y: 1.0, z: 0.0 }, uv)
+        });
+    }
+
+    // two triangles for each cell of a columns x rows grid, f gives the vertex at a grid point
+    // the direction of growing columns crossed with that of growing rows has to point outwards
+    fn surface<E: AsciaEnvironment>(columns: usize, rows: usize, material: &E::PolygonMaterials, f: impl Fn(usize, usize) -> Vertex) -> Vec<Polygon<E, Local>>{
+        let mut polygons = vec![];
+        for i in 0..columns{
+            for j in 0..rows{
+                let (v00, v10, v11, v01) = (f(i, j), f(i + 1, j), f(i + 1, j + 1), f(i, j + 1));
+                for (a, b, c) in [(&v00, &v10, &v11), (&v00, &v11, &v01)]{
+                    // cells touching a pole or an apex collapse into a single triangle
+                    if a.0 == b.0 || b.0 == c.0 || c.0 == a.0{
+                        continue;
+                    }
+                    polygons.push(Self::triangle(a, b, c, material));
+                }
+            }
+        }
+        return polygons;
+    }
+
+    // a disc at height y facing away from the origin
+    fn cap<E: AsciaEnvironment>(radius: f32, y: f32, segments: usize, material: &E::PolygonMaterials) -> Vec<Polygon<E, Local>>{
+        let normal = Vec3{ x: 0.0, y: f32::signum(y), z: 0.0 };
+        let vertex = |i: usize| {
+            let n = Self::ring_normal(i, segments);
+            (n * radius + Vec3{ x: 0.0, y: y, z: 0.0 }, normal, Vec2{ x: 0.5 + n.x * 0.5, y: 0.5 - n.z * 0.5 })
+        };
+        let center = (Vec3{ x: 0.0, y: y, z: 0.0 }, normal, Vec2{ x: 0.5, y: 0.5 });
+        return (0..segments).map(|i| {
+            if y > 0.0 { Self::triangle(&center, &vertex(i), &vertex(i + 1), material) } else { Self::triangle(&center, &vertex(i + 1), &vertex(i), material) }
+        }).collect();
+    }
+
+    // the unit vector on the xz plane at segment i, going counterclockwise seen from +y
+    fn ring_normal(i: usize, segments: usize) -> Vec3{
+        let angle = 2.0 * PI * (i % segments) as f32 / segments as f32;
+        return Vec3{
+            x: f32::cos(angle),
+            y: 0.0,
+            z: -f32::sin(angle),
+        };
+    }
+
+    // ring 0 is the bottom pole and ring rings the top one, the poles are exact so that their cells collapse
+    fn sphere_normal(i: usize, segments: usize, j: usize, rings: usize) -> Vec3{
+        if j == 0 || j == rings{
+            return Vec3{
+                x: 0.0,
+                y: if j == 0 { -1.0 } else { 1.0 },
+                z: 0.0,
+            };
+        }
+        let elevation = PI * (j as f32 / rings as f32 - 0.5);
+        return Self::ring_normal(i, segments) * f32::cos(elevation) + Vec3{ x: 0.0, y: f32::sin(elevation), z: 0.0 };
+    }
+
+    fn triangle<E: AsciaEnvironment>(a: &Vertex, b: &Vertex, c: &Vertex, material: &E::PolygonMaterials) -> Polygon<E, Local>{
+        return Polygon{
+            poses: Matrix33{
+                v1: a.0,
+                v2: b.0,
+                v3: c.0,
+            },
+            normals: Some(Matrix33{
+                v1: a.1,
+                v2: b.1,
+                v3: c.1,
+            }),
+            uvs: Some([a.2, b.2, c.2]),
+            material: material.clone(),
+            _ph: Default::default(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use std::f32::consts::PI;
+    use crate::ascia::core::{Local, Polygon, PresetAsciaEnvironment, PresetPolygonMaterial};
+    use crate::ascia::math::Vec3;
+    use crate::ascia::primitives::PrimitiveGenerator;
+
+    // the divergence theorem gives the enclosed volume, which is negative when the faces wind inwards
+    fn volume(polygons: &[Polygon<PresetAsciaEnvironment, Local>]) -> f32{
+        return polygons.iter().map(|p| p.poses.v1 * (p.poses.v2 ^ p.poses.v3) / 6.0).sum();
+    }
+
+    // every face has to agree with the normals it carries and face away from core, a point inside the solid nearest to the face
+    fn assert_outwards(name: &str, polygons: &[Polygon<PresetAsciaEnvironment, Local>], core: impl Fn(&Vec3) -> Vec3){
+        assert!(!polygons.is_empty(), "{}", name);
+        for p in polygons{
+            let face = p.normal();
+            let normals = p.normals.unwrap();
+            let center = (p.poses.v1 + p.poses.v2 + p.poses.v3) / 3.0;
+            assert!(face * (center - core(&center)) > 0.0, "{} has a face winding inwards at {:?}", name, center);
+            for n in [normals.v1, normals.v2, normals.v3]{
+                assert!(face * n > 0.0, "{} has a normal {:?} against its face at {:?}", name, n, center);
+                assert!(f32::abs(n.norm() - 1.0) < 1e-4);
+            }
+            for uv in p.uvs.unwrap(){
+                assert!(uv.x >= 0.0 && uv.x <= 1.5 && uv.y >= 0.0 && uv.y <= 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_winding(){
+        let m = PresetPolygonMaterial::default();
+        let origin = |_: &Vec3| Vec3::default();
+
+        let sphere = PrimitiveGenerator::uv_sphere(2.0, 32, 16, m.clone());
+        assert_outwards("uv sphere", &sphere, origin);
+        // the poles are fans of single triangles
+        assert_eq!(sphere.len(), 32 * 16 * 2 - 32 * 2);
+        assert!(f32::abs(volume(&sphere) / (4.0 / 3.0 * PI * 8.0) - 1.0) < 0.03);
+
+        let icosphere = PrimitiveGenerator::icosphere(2.0, 2, m.clone());
+        assert_outwards("icosphere", &icosphere, origin);
+        assert_eq!(icosphere.len(), 20 * 16);
+        assert!(f32::abs(volume(&icosphere) / (4.0 / 3.0 * PI * 8.0) - 1.0) < 0.05);
+        for p in &icosphere{
+            assert!(f32::abs(p.poses.v1.norm() - 2.0) < 1e-4);
+        }
+
+        let cylinder = PrimitiveGenerator::cylinder(1.0, 3.0, 64, m.clone());
+        assert_outwards("cylinder", &cylinder, origin);
+        assert!(f32::abs(volume(&cylinder) / (PI * 3.0) - 1.0) < 0.01);
+
+        let cone = PrimitiveGenerator::cone(1.0, 3.0, 64, m.clone());
+        // convex solids can use any point inside
+        assert_outwards("cone", &cone, |_| Vec3{ x: 0.0, y: -1.0, z: 0.0 });
+        assert!(f32::abs(volume(&cone) / (PI / 3.0 * 3.0) - 1.0) < 0.01);
+
+        let torus = PrimitiveGenerator::torus(3.0, 1.0, 48, 24, m.clone());
+        assert_outwards("torus", &torus, |p| Vec3{ x: p.x, y: 0.0, z: p.z }.normalize() * 3.0);
+        assert!(f32::abs(volume(&torus) / (2.0 * PI * PI * 3.0) - 1.0) < 0.02);
+
+        let capsule = PrimitiveGenerator::capsule(1.0, 2.0, 32, 8, m.clone());
+        assert_outwards("capsule", &capsule, |p| Vec3{ x: 0.0, y: p.y.clamp(-1.0, 1.0), z: 0.0 });
+        assert!(f32::abs(volume(&capsule) / (PI * 2.0 + 4.0 / 3.0 * PI) - 1.0) < 0.02);
+
+        let grid = PrimitiveGenerator::plane_grid(4.0, 2.0, 4, 2, m.clone());
+        assert_eq!(grid.len(), 4 * 2 * 2);
+        assert_outwards("plane grid", &grid, |p| *p - Vec3{ x: 0.0, y: 1.0, z: 0.0 });
+        let area: f32 = grid.iter().map(|p| ((p.poses.v2 - p.poses.v1) ^ (p.poses.v3 - p.poses.v1)).norm() * 0.5).sum();
+        assert!(f32::abs(area - 8.0) < 1e-4);
+
+        // even with too few segments the solids stay closed
+        assert!(volume(&PrimitiveGenerator::cylinder(1.0, 1.0, 0, m.clone())) > 0.0);
+    }
+}
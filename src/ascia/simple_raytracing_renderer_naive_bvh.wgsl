@@ -1,6 +1,7 @@
 struct Polygon{
     vertices: mat3x3<f32>,
     material: Material,
+    normals: mat3x3<f32>,
 }
 
 struct CParticle{
@@ -55,7 +56,8 @@ struct RaytracingSetting{
     max_reflection:u32,
     sampling_size:u32,
     sampling_threshold:u32,
-    render_range_radius: f32
+    render_range_radius: f32,
+    in_view_polygons: u32
 }
 
 @group(0) @binding(0) var<storage,read> settings: RaytracingSetting;
@@ -305,18 +307,18 @@ fn project_polygon(ray: Ray, polygon_id:u32) -> PolygonRayIntersection{
     if(psy > 0.0){
         let v2 = v1 * psy;
         if (all(vec3<f32>() <= v2) && all(v2 <= vec3<f32>(1.0,1.0,1.0))){
-            return PolygonRayIntersection(polygon_id, max(0.0,length(ray.direction) * psy), ray, ray.position + ray.direction * psy, vec2<f32>(v2.y, v2.z), normalize(cross(m[1] - m[0], m[2] - m[0])));
+            return PolygonRayIntersection(polygon_id, max(0.0,length(ray.direction) * psy), ray, ray.position + ray.direction * psy, vec2<f32>(v2.y, v2.z), normalize(polygons[polygon_id].normals * v2));
         }
     }
     return PolygonRayIntersection(polygon_id, settings.render_range_radius, ray, vec3<f32>(), vec2<f32>(), vec3<f32>());
 }
 
-fn project_polygons(ray: Ray, exclude_polygon_id:u32) -> PolygonRayIntersection{
-    let polygons_len = arrayLength(&polygons);
+// only the first polygons_len polygons are tested, camera rays skip the ones outside of the view
+fn project_polygons(ray: Ray, exclude_polygon_id:u32, polygons_len:u32) -> PolygonRayIntersection{
     var nearest_intersection = PolygonRayIntersection(2147483649u, settings.render_range_radius, ray, vec3<f32>(), vec2<f32>(), vec3<f32>());
 
     var i = 1u;
-    let tree_width = 1u << (firstLeadingBit(polygons_len - 1u) + 1u);
+    let tree_width = 1u << (firstLeadingBit(arrayLength(&polygons) - 1u) + 1u);
     var count = 0u;
 
     loop{
@@ -450,7 +452,7 @@ fn project_c_particles(ray: Ray, exclude_c_particle_id: u32) -> CParticleRayInte
 fn calc_intersections_polygons_1x(@builtin(global_invocation_id) global_id:vec3<u32>){
     if (global_id.x < settings.screen_width && global_id.y < settings.screen_height){
         let index = global_id.y * settings.screen_width + global_id.x;
-        intersections_polygons[index] = project_polygons(generate_ray_1x(global_id), 2147483649u);
+        intersections_polygons[index] = project_polygons(generate_ray_1x(global_id), 2147483649u, settings.in_view_polygons);
     }
 }
 
@@ -459,7 +461,7 @@ fn calc_intersections_polygons_1x(@builtin(global_invocation_id) global_id:vec3<
 fn calc_intersections_polygons_3x(@builtin(global_invocation_id) global_id:vec3<u32>){
     if (global_id.x < settings.screen_width && global_id.y < settings.screen_height && global_id.z < 8u){
         let index = (global_id.y * settings.screen_width + global_id.x) * 8u + global_id.z;
-        intersections_polygons[index] = project_polygons(generate_ray_3x(global_id), 2147483649u);
+        intersections_polygons[index] = project_polygons(generate_ray_3x(global_id), 2147483649u, settings.in_view_polygons);
     }
 }
 
@@ -614,7 +616,7 @@ fn calc_color_polygon_lambert_with_shadow_projection(intersection_id: u32) -> ve
     let n = arrayLength(&pointlights);
     for(var i = 0u; i < n; i += 1u){
         let light = pointlights[i];
-        if (project_polygons(Ray(intersection.position, light.position - intersection.position), intersections_polygons[intersection_id].polygon_id).depth < settings.render_range_radius) {
+        if (project_polygons(Ray(intersection.position, light.position - intersection.position), intersections_polygons[intersection_id].polygon_id, arrayLength(&polygons)).depth < settings.render_range_radius) {
             continue;
         }
         let co:f32 = dot(normalize(light.position - intersection.position), intersection.normal);
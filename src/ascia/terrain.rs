@@ -0,0 +1,256 @@
+use crate::ascia::color::ColorRGBf32;
+use crate::ascia::core::{AsciaEnvironment, LambertMaterial, Local, ObjectNode, ObjectNodeTree, Polygon, PresetPolygonMaterial};
+use crate::ascia::math::{Matrix33, Vec2, Vec3};
+use crate::ascia::texture::{Texture, TextureFilter, TextureWrap};
+
+// a height field over width x depth on the xz plane centered on the origin, triangulated into width_segments x depth_segments quads
+pub struct TerrainGenerator<E: AsciaEnvironment + ?Sized + 'static>{
+    pub width: f32,
+    pub depth: f32,
+    pub width_segments: usize,
+    pub depth_segments: usize,
+    // splits the grid into child nodes of up to chunk_segments x chunk_segments quads, so that whole tiles can be culled by their bounds
+    pub chunk_segments: Option<usize>,
+    // upper heights in ascending order, a polygon takes the first band above its average height and the last one past all of them
+    pub bands: Vec<(f32, E::PolygonMaterials)>,
+    // for polygons when there are no bands
+    pub material: E::PolygonMaterials,
+}
+
+impl<E: AsciaEnvironment> TerrainGenerator<E>{
+    pub fn new(width: f32, depth: f32, width_segments: usize, depth_segments: usize, material: E::PolygonMaterials) -> Self{
+        return TerrainGenerator{
+            width: width,
+            depth: depth,
+            width_segments: width_segments.max(1),
+            depth_segments: depth_segments.max(1),
+            chunk_segments: None,
+            bands: vec![],
+            material: material,
+        };
+    }
+
+    // the root is tagged tag and, when chunked, has the tiles as children tagged "tag x z" in grid order
+    // tiles are placed at their centers on the xz plane and keep their polygons relative to it
+    pub fn generate(&self, tag: &str, height: impl Fn(f32, f32) -> f32) -> ObjectNodeTree<E, Local>{
+        let (w, d) = (self.width_segments.max(1), self.depth_segments.max(1));
+        // grid point (i, j) is i quads along +x and j quads along -z from the corner at (-width / 2, depth / 2)
+        let position = |i: usize, j: usize| Vec2{ x: self.width * (i as f32 / w as f32 - 0.5), y: self.depth * (0.5 - j as f32 / d as f32) };
+        let heights: Vec<Vec<f32>> = (0..=w).map(|i| (0..=d).map(|j| {
+            let p = position(i, j);
+            height(p.x, p.y)
+        }).collect()).collect();
+        let vertex = |i: usize, j: usize| {
+            let p = position(i, j);
+            // central differences inside and one-sided ones on the border
+            let (i0, i1, j0, j1) = (i.saturating_sub(1), (i + 1).min(w), j.saturating_sub(1), (j + 1).min(d));
+            let dx = (heights[i1][j] - heights[i0][j]) / (position(i1, j).x - position(i0, j).x);
+            let dz = (heights[i][j1] - heights[i][j0]) / (position(i, j1).y - position(i, j0).y);
+            (Vec3{ x: p.x, y: heights[i][j], z: p.y }, Vec3{ x: -dx, y: 1.0, z: -dz }.normalize(), Vec2{ x: i as f32 / w as f32, y: j as f32 / d as f32 })
+        };
+
+        let quads = |is: std::ops::Range<usize>, js: std::ops::Range<usize>, origin: Vec3| {
+            let mut polygons = vec![];
+            for i in is{
+                for j in js.clone(){
+                    let (v00, v10, v11, v01) = (vertex(i, j), vertex(i + 1, j), vertex(i + 1, j + 1), vertex(i, j + 1));
+                    for [a, b, c] in [[&v00, &v10, &v11], [&v00, &v11, &v01]]{
+                        polygons.push(Polygon{
+                            poses: Matrix33{
+                                v1: a.0 - origin,
+                                v2: b.0 - origin,
+                                v3: c.0 - origin,
+                            },
+                            normals: Some(Matrix33{
+                                v1: a.1,
+                                v2: b.1,
+                                v3: c.1,
+                            }),
+                            uvs: Some([a.2, b.2, c.2]),
+                            material: self.material_at((a.0.y + b.0.y + c.0.y) / 3.0),
+                            _ph: Default::default(),
+                        });
+                    }
+                }
+            }
+            return polygons;
+        };
+
+        let chunk = match self.chunk_segments {
+            Some(c) => { c.max(1) }
+            None => { return ObjectNodeTree::new(ObjectNode::from(tag, quads(0..w, 0..d, Vec3::default()))); }
+        };
+        let mut tree = ObjectNodeTree::new(ObjectNode::new(tag));
+        let root = tree.root();
+        for ci in 0..w.div_ceil(chunk){
+            for cj in 0..d.div_ceil(chunk){
+                let (is, js) = (ci * chunk..((ci + 1) * chunk).min(w), cj * chunk..((cj + 1) * chunk).min(d));
+                let center = (position(is.start, js.start) + position(is.end, js.end)) * 0.5;
+                let origin = Vec3{ x: center.x, y: 0.0, z: center.y };
+                let mut tile = ObjectNode::from(&format!("{} {} {}", tag, ci, cj), quads(is, js, origin));
+                tile.position = origin;
+                tree.add_child(root, tile).unwrap();
+            }
+        }
+        return tree;
+    }
+
+    // heights from the brightness of an image stretched over the terrain, black at 0 and white at height_scale
+    // the top of the image is at -z like a map seen from above with x to the right
+    pub fn generate_from_heightmap(&self, tag: &str, heightmap: &Texture, height_scale: f32) -> ObjectNodeTree<E, Local>{
+        return self.generate(tag, |x, z| {
+            let c = heightmap.sample(&Vec2{ x: x / self.width + 0.5, y: 0.5 - z / self.depth }, TextureFilter::Bilinear, TextureWrap::Clamp);
+            (c.r + c.g + c.b) / 3.0 * height_scale
+        });
+    }
+
+    fn material_at(&self, height: f32) -> E::PolygonMaterials{
+        return match self.bands.iter().find(|(upper, _)| height <= *upper).or(self.bands.last()) {
+            Some((_, m)) => { m.clone() }
+            None => { self.material.clone() }
+        };
+    }
+}
+
+// water up to sea_level, then grass, rock and snow splitting the rest up to peak in three
+pub fn lambert_bands(sea_level: f32, peak: f32) -> Vec<(f32, PresetPolygonMaterial)>{
+    let band = |upper: f32, r: f32, g: f32, b: f32| (upper, PresetPolygonMaterial::LambertMaterial(LambertMaterial{
+        color: ColorRGBf32{
+            r: r,
+            g: g,
+            b: b,
+        },
+        priority: 0,
+    }));
+    let step = (peak - sea_level) / 3.0;
+    return vec![
+        band(sea_level, 0.2, 0.4, 0.9),
+        band(sea_level + step, 0.3, 0.8, 0.3),
+        band(sea_level + step * 2.0, 0.6, 0.5, 0.4),
+        band(f32::INFINITY, 1.0, 1.0, 1.0),
+    ];
+}
+
+// smoothly interpolated random values on the integer lattice, within [-1, 1]
+pub fn value_noise(seed: u32, x: f32, z: f32) -> f32{
+    let (x0, z0) = (f32::floor(x), f32::floor(z));
+    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+    let (tx, tz) = (smooth(x - x0), smooth(z - z0));
+    let (x0, z0) = (x0 as i32, z0 as i32);
+    let at = |i: i32, j: i32| lattice_value(seed, x0.wrapping_add(i), z0.wrapping_add(j));
+    let top = at(0, 0) + (at(1, 0) - at(0, 0)) * tx;
+    let bottom = at(0, 1) + (at(1, 1) - at(0, 1)) * tx;
+    return top + (bottom - top) * tz;
+}
+
+// octaves of value noise, each at twice the frequency and half the amplitude of the one before, normalized back into [-1, 1]
+pub fn fbm(seed: u32, x: f32, z: f32, octaves: usize) -> f32{
+    let (mut sum, mut amplitude, mut frequency, mut total) = (0.0, 1.0, 1.0, 0.0);
+    for o in 0..octaves{
+        sum += value_noise(seed.wrapping_add(o as u32), x * frequency, z * frequency) * amplitude;
+        total += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    return if total > 0.0 { sum / total } else { 0.0 };
+}
+
+fn lattice_value(seed: u32, x: i32, z: i32) -> f32{
+    let mut h = seed ^ (x as u32).wrapping_mul(0x27d4eb2d) ^ (z as u32).wrapping_mul(0x165667b1);
+    h = (h ^ (h >> 15)).wrapping_mul(0x85ebca6b);
+    h = (h ^ (h >> 13)).wrapping_mul(0xc2b2ae35);
+    h ^= h >> 16;
+    return h as f32 / u32::MAX as f32 * 2.0 - 1.0;
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::ascia::color::ColorRGBf32;
+    use crate::ascia::core::{PresetAsciaEnvironment, PresetPolygonMaterial};
+    use crate::ascia::math::Vec3;
+    use crate::ascia::terrain::{fbm, lambert_bands, lattice_value, value_noise, TerrainGenerator};
+    use crate::ascia::texture::Texture;
+
+    #[test]
+    fn test_generate(){
+        let terrain = TerrainGenerator::<PresetAsciaEnvironment>::new(8.0, 4.0, 4, 2, PresetPolygonMaterial::default());
+        let tree = terrain.generate("terrain", |x, z| x * 0.5 + z);
+        let polygons = &tree.root_node().polygons;
+        assert_eq!(polygons.len(), 4 * 2 * 2);
+        for p in polygons{
+            // the plane y = x / 2 + z faces up and its normals follow it exactly
+            let expected = Vec3{ x: -0.5, y: 1.0, z: -1.0 }.normalize();
+            assert!((p.normal() - expected).norm() < 1e-5);
+            assert!((p.normals.unwrap().v2 - expected).norm() < 1e-5);
+            for v in [p.poses.v1, p.poses.v2, p.poses.v3]{
+                assert!(f32::abs(v.y - (v.x * 0.5 + v.z)) < 1e-5);
+                assert!(f32::abs(v.x) <= 4.0 && f32::abs(v.z) <= 2.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_chunks_and_bands(){
+        let mut terrain = TerrainGenerator::<PresetAsciaEnvironment>::new(10.0, 10.0, 5, 5, PresetPolygonMaterial::default());
+        terrain.chunk_segments = Some(2);
+        terrain.bands = lambert_bands(0.0, 3.0);
+        let tree = terrain.generate("land", |x, _| x);
+        let root = tree.root();
+        assert!(tree.root_node().polygons.is_empty());
+        assert_eq!(tree.node(root).unwrap().children().len(), 9);
+
+        // the last tile in each direction takes the single remaining row of quads
+        let corner = tree.node(tree.child(root, "land 2 2").unwrap()).unwrap();
+        assert_eq!(corner.polygons.len(), 2);
+        assert_eq!((corner.position.x, corner.position.z), (4.0, -4.0));
+        let total: usize = tree.node(root).unwrap().children().iter().map(|c| tree.node(*c).unwrap().polygons.len()).sum();
+        assert_eq!(total, 5 * 5 * 2);
+
+        let color = |tag: &str| {
+            let tile = tree.node(tree.child(root, tag).unwrap()).unwrap();
+            return tile.polygons.iter().map(|p| match &p.material {
+                PresetPolygonMaterial::LambertMaterial(m) => { m.color }
+                _ => { panic!("terrain bands are lambert materials") }
+            }).collect::<Vec<ColorRGBf32>>();
+        };
+        // x below 0 is under water and the far end above 3 is snow
+        assert!(color("land 0 1").iter().all(|c| c.b > c.g));
+        assert!(color("land 2 1").iter().all(|c| c.r == 1.0 && c.g == 1.0));
+    }
+
+    #[test]
+    fn test_generate_from_heightmap(){
+        let gray = |v: f32| ColorRGBf32{ r: v, g: v, b: v };
+        // a single bright pixel at the top right is the corner at +x and -z
+        let heightmap = Texture::new(2, 2, vec![gray(0.0), gray(1.0), gray(0.0), gray(0.0)]).unwrap();
+        let terrain = TerrainGenerator::<PresetAsciaEnvironment>::new(2.0, 2.0, 2, 2, PresetPolygonMaterial::default());
+        let tree = terrain.generate_from_heightmap("map", &heightmap, 10.0);
+        let highest = tree.root_node().polygons.iter().flat_map(|p| [p.poses.v1, p.poses.v2, p.poses.v3]).fold(None, |h: Option<Vec3>, v| {
+            match h {
+                Some(h) if h.y >= v.y => { Some(h) }
+                _ => { Some(v) }
+            }
+        }).unwrap();
+        assert_eq!((highest.x, highest.y, highest.z), (1.0, 10.0, -1.0));
+    }
+
+    #[test]
+    fn test_noise(){
+        for (x, z) in [(0, 0), (3, -7)]{
+            // the lattice values are hit exactly and the noise is continuous between them
+            let (fx, fz) = (x as f32, z as f32);
+            assert_eq!(value_noise(1, fx, fz), lattice_value(1, x, z));
+            assert!(f32::abs(value_noise(1, fx + 1.0 / 1024.0, fz) - lattice_value(1, x, z)) < 1e-2);
+            assert!(f32::abs(value_noise(1, fx + 0.5, fz) - value_noise(1, fx + 0.5 + 1.0 / 1024.0, fz)) < 1e-2);
+        }
+        assert_ne!(value_noise(1, 0.0, 0.0), value_noise(2, 0.0, 0.0));
+        let mut sum = 0.0;
+        for i in 0..1000{
+            let v = fbm(7, i as f32 * 0.37, i as f32 * 0.11, 5);
+            assert!((-1.0..=1.0).contains(&v));
+            sum += v;
+        }
+        assert!(f32::abs(sum / 1000.0) < 0.2);
+        assert_eq!(fbm(7, 1.3, 2.4, 0), 0.0);
+    }
+}
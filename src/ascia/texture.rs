@@ -0,0 +1,172 @@
+use crate::ascia::color::ColorRGBf32;
+use crate::ascia::math::Vec2;
+
+#[cfg(feature = "export")]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "export", derive(Serialize, Deserialize))]
+pub enum TextureFilter{
+    Nearest,
+    Bilinear,
+}
+
+// what happens to uvs outside of [0, 1]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "export", derive(Serialize, Deserialize))]
+pub enum TextureWrap{
+    Repeat,
+    Clamp,
+}
+
+// an RGB image, uv (0, 0) is the lower left corner and (1, 1) the upper right like in OBJ files
+// deserialized through Texture::new, so a stored texture is checked like a loaded one
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "export", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "export", serde(try_from = "TextureData"))]
+pub struct Texture{
+    width: usize,
+    height: usize,
+    // rows from the top
+    pixels: Vec<ColorRGBf32>,
+    // kept for polygons without uvs and renderers that cannot sample
+    #[cfg_attr(feature = "export", serde(skip_serializing))]
+    average: ColorRGBf32,
+}
+
+#[cfg(feature = "export")]
+#[derive(Deserialize)]
+struct TextureData{
+    width: usize,
+    height: usize,
+    pixels: Vec<ColorRGBf32>,
+}
+
+#[cfg(feature = "export")]
+impl TryFrom<TextureData> for Texture{
+    type Error = String;
+
+    fn try_from(value: TextureData) -> Result<Self, Self::Error> {
+        return Texture::new(value.width, value.height, value.pixels);
+    }
+}
+
+impl Texture{
+    pub fn new(width: usize, height: usize, pixels: Vec<ColorRGBf32>) -> Result<Self, String>{
+        if width == 0 || height == 0{
+            return Err(format!("a texture cannot be {}x{}", width, height));
+        }
+        match width.checked_mul(height) {
+            Some(n) if n == pixels.len() => {}
+            Some(n) => { return Err(format!("a {}x{} texture needs {} pixels, found {}", width, height, n, pixels.len())); }
+            None => { return Err(format!("a {}x{} texture is too large", width, height)); }
+        }
+        let mut sum = ColorRGBf32::default();
+        for p in &pixels{
+            sum += *p;
+        }
+        let n = pixels.len() as f32;
+        return Ok(Texture{
+            width: width,
+            height: height,
+            pixels: pixels,
+            average: ColorRGBf32{
+                r: sum.r / n,
+                g: sum.g / n,
+                b: sum.b / n,
+            },
+        });
+    }
+
+    pub fn width(&self) -> usize{
+        return self.width;
+    }
+
+    pub fn height(&self) -> usize{
+        return self.height;
+    }
+
+    // x from the left and y from the top
+    pub fn pixel(&self, x: usize, y: usize) -> ColorRGBf32{
+        return self.pixels[y * self.width + x];
+    }
+
+    pub fn average(&self) -> ColorRGBf32{
+        return self.average;
+    }
+
+    pub fn sample(&self, uv: &Vec2, filter: TextureFilter, wrap: TextureWrap) -> ColorRGBf32{
+        // texel centers are at half integers
+        let x = uv.x * self.width as f32;
+        let y = (1.0 - uv.y) * self.height as f32;
+        return match filter {
+            TextureFilter::Nearest => {
+                self.texel(f32::floor(x) as isize, f32::floor(y) as isize, wrap)
+            }
+            TextureFilter::Bilinear => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (f32::floor(x), f32::floor(y));
+                let (fx, fy) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as isize, y0 as isize);
+                let top = lerp(&self.texel(x0, y0, wrap), &self.texel(x0 + 1, y0, wrap), fx);
+                let bottom = lerp(&self.texel(x0, y0 + 1, wrap), &self.texel(x0 + 1, y0 + 1, wrap), fx);
+                lerp(&top, &bottom, fy)
+            }
+        };
+    }
+
+    fn texel(&self, x: isize, y: isize, wrap: TextureWrap) -> ColorRGBf32{
+        let (w, h) = (self.width as isize, self.height as isize);
+        let (x, y) = match wrap {
+            TextureWrap::Repeat => { (x.rem_euclid(w), y.rem_euclid(h)) }
+            TextureWrap::Clamp => { (x.clamp(0, w - 1), y.clamp(0, h - 1)) }
+        };
+        return self.pixel(x as usize, y as usize);
+    }
+}
+
+fn lerp(a: &ColorRGBf32, b: &ColorRGBf32, t: f32) -> ColorRGBf32{
+    return ColorRGBf32{
+        r: a.r + (b.r - a.r) * t,
+        g: a.g + (b.g - a.g) * t,
+        b: a.b + (b.b - a.b) * t,
+    };
+}
+
+#[cfg(test)]
+mod tests{
+    use crate::ascia::color::ColorRGBf32;
+    use crate::ascia::math::Vec2;
+    use crate::ascia::texture::{Texture, TextureFilter, TextureWrap};
+
+    fn gray(v: f32) -> ColorRGBf32{
+        return ColorRGBf32{
+            r: v,
+            g: v,
+            b: v,
+        };
+    }
+
+    #[test]
+    fn test_sample(){
+        // 0 1
+        // 2 3
+        let texture = Texture::new(2, 2, vec![gray(0.0), gray(1.0), gray(2.0), gray(3.0)]).unwrap();
+        let at = |u: f32, v: f32, filter, wrap| texture.sample(&Vec2{ x: u, y: v }, filter, wrap).r;
+        // v runs upwards, so the lower left is the last row
+        assert_eq!(at(0.1, 0.1, TextureFilter::Nearest, TextureWrap::Clamp), 2.0);
+        assert_eq!(at(0.9, 0.9, TextureFilter::Nearest, TextureWrap::Clamp), 1.0);
+        assert_eq!(at(1.1, 0.9, TextureFilter::Nearest, TextureWrap::Repeat), 0.0);
+        assert_eq!(at(1.1, 0.9, TextureFilter::Nearest, TextureWrap::Clamp), 1.0);
+        assert_eq!(at(-0.4, -0.4, TextureFilter::Nearest, TextureWrap::Repeat), 1.0);
+        // at the texel centers bilinear filtering gives the texels, between them it blends
+        assert!(f32::abs(at(0.25, 0.75, TextureFilter::Bilinear, TextureWrap::Clamp) - 0.0) < 1e-6);
+        assert!(f32::abs(at(0.5, 0.5, TextureFilter::Bilinear, TextureWrap::Clamp) - 1.5) < 1e-6);
+        assert!(f32::abs(at(0.5, 0.75, TextureFilter::Bilinear, TextureWrap::Clamp) - 0.5) < 1e-6);
+        // past the edge clamping holds the border while repeating blends with the opposite side
+        assert!(f32::abs(at(0.0, 0.75, TextureFilter::Bilinear, TextureWrap::Clamp) - 0.0) < 1e-6);
+        assert!(f32::abs(at(0.0, 0.75, TextureFilter::Bilinear, TextureWrap::Repeat) - 0.5) < 1e-6);
+        assert!(f32::abs(texture.average().g - 1.5) < 1e-6);
+        assert!(Texture::new(2, 2, vec![gray(0.0)]).is_err());
+    }
+}
@@ -10,7 +10,7 @@ use std::path::Path;
 use std::time::Duration;
 use crate::ascia::camera::{SimpleBVHCamera, SimpleCamera};
 use crate::ascia::core::{AsciaEngine, AsciaEnvironment, CoordinateType, CParticle, Local, ObjectNode, ObjectNodeAttribute, ObjectNodeAttributeDispatcher, Polygon, PresetAsciaEnvironment, PresetCamera, PresetObjectNodeAttributeDispatcher, RenderChar};
-use crate::ascia::math::{Quaternion, Vec3};
+use crate::ascia::math::{Matrix33, Quaternion, Vec3};
 use crate::ascia::color::ColorANSI256;
 
 #[cfg(feature = "wgpu")]
@@ -111,6 +111,49 @@ pub fn sort_polygons_by_morton_code<E: AsciaEnvironment, CO: CoordinateType>(pol
     polygons.sort_by_key(|p: &Polygon<E, CO>| { parse_vec3_to_morton_code(&((p.poses.v1 + p.poses.v2 + p.poses.v3) / 3.0), 16) });
 }
 
+// sets per-vertex normals by averaging the faces that share a vertex position, weighted by their angle there so that the triangulation does not matter
+// faces meeting at a sharper angle than angle_threshold (in radians) keep their edge hard
+pub fn smooth_normals<E: AsciaEnvironment, CO: CoordinateType>(polygons: &mut [Polygon<E, CO>], angle_threshold: f32){
+    // +0.0 folds -0.0 into 0.0 so that both are the same vertex
+    let key = |v: &Vec3| { ((v.x + 0.0).to_bits(), (v.y + 0.0).to_bits(), (v.z + 0.0).to_bits()) };
+    let corners = |p: &Polygon<E, CO>| { [(p.poses.v1, p.poses.v2, p.poses.v3), (p.poses.v2, p.poses.v3, p.poses.v1), (p.poses.v3, p.poses.v1, p.poses.v2)] };
+    let faces: Vec<Vec3> = polygons.iter().map(|p| (p.poses.v2 - p.poses.v1) ^ (p.poses.v3 - p.poses.v1)).collect();
+    // the faces around each vertex position with their angle there
+    let mut shared: HashMap<(u32, u32, u32), Vec<(usize, f32)>> = HashMap::new();
+    for (i, p) in polygons.iter().enumerate(){
+        if faces[i].norm() == 0.0{
+            continue;
+        }
+        for (v, a, b) in corners(p){
+            let cos = (a - v).normalize() * (b - v).normalize();
+            shared.entry(key(&v)).or_default().push((i, f32::acos(f32::clamp(cos, -1.0, 1.0))));
+        }
+    }
+    let cos_threshold = f32::cos(angle_threshold);
+    for (i, p) in polygons.iter_mut().enumerate(){
+        let face = faces[i];
+        if face.norm() == 0.0{
+            continue;
+        }
+        let face = face.normalize();
+        let normal_at = |v: &Vec3| {
+            let mut sum = Vec3::default();
+            for (j, angle) in &shared[&key(v)]{
+                let other = faces[*j].normalize();
+                if other * face >= cos_threshold{
+                    sum = sum + other * *angle;
+                }
+            }
+            return sum.normalize();
+        };
+        p.normals = Some(Matrix33{
+            v1: normal_at(&p.poses.v1),
+            v2: normal_at(&p.poses.v2),
+            v3: normal_at(&p.poses.v3),
+        });
+    }
+}
+
 #[cfg(feature = "termios-controller")]
 pub struct TermiosController<'a, E: AsciaEnvironment>{
     input: RefCell<File>,